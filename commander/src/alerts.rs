@@ -0,0 +1,424 @@
+// =============================================================================
+// IDS LAB COMMANDER - Parsing des alertes IDS
+// =============================================================================
+//
+// DESCRIPTION:
+// Transforme les lignes brutes produites par les trois IDS en une structure
+// `Alert` unique, exploitable par l'API et le dashboard.
+//
+// FORMATS SUPPORTÉS (voir sample-logs/):
+// - Snort 3 alert_fast:  02/28-10:33:23.528916 [**] [1:1000001:2] "msg" [**] [Priority: 0] {TCP} a:p -> b:p
+// - Suricata fast.log:   02/28/2026-10:18:49.680961  [**] [1:2000001:1] msg [**] [Classification: x] [Priority: 2] {TCP} a:p -> b:p
+// - Suricata eve.json:   une ligne JSON par événement, seuls les event_type "alert" sont retenus
+// - Zeek notice.log:     TSV avec en-têtes #fields, une notice par ligne
+//
+// CHOIX STRATÉGIQUES:
+// - Un parser par ligne: permet de lire les fichiers de logs en continu
+// - Les lignes non reconnues sont ignorées (None) plutôt que de faire échouer
+//   la lecture complète d'un fichier
+// - Zeek nécessite un état (la liste des colonnes vient de l'en-tête #fields),
+//   d'où la structure `AlertParser` qui encapsule cet état
+//
+// =============================================================================
+
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Moteur IDS ayant produit une alerte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    Snort,
+    Suricata,
+    Zeek,
+}
+
+impl Engine {
+    /// Nom court utilisé dans les routes et les noms de containers.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Engine::Snort => "snort",
+            Engine::Suricata => "suricata",
+            Engine::Zeek => "zeek",
+        }
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "snort" => Ok(Engine::Snort),
+            "suricata" => Ok(Engine::Suricata),
+            "zeek" => Ok(Engine::Zeek),
+            other => Err(format!("Unknown IDS engine: {}", other)),
+        }
+    }
+}
+
+/// Alerte IDS normalisée, quel que soit le format d'origine.
+///
+/// Les champs absents du format source (ex: pas de SID chez Zeek,
+/// pas d'adresses pour les alertes décodeur de Snort) valent None.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub timestamp: DateTime<Utc>,
+    pub engine: Engine,
+    pub gid: Option<u32>,
+    pub sid: Option<u32>,
+    pub rev: Option<u32>,
+    pub message: String,
+    pub classification: Option<String>,
+    pub priority: Option<u8>,
+    pub protocol: Option<String>,
+    pub src_ip: Option<IpAddr>,
+    pub src_port: Option<u16>,
+    pub dst_ip: Option<IpAddr>,
+    pub dst_port: Option<u16>,
+}
+
+impl Alert {
+    fn new(timestamp: DateTime<Utc>, engine: Engine, message: String) -> Self {
+        Alert {
+            timestamp,
+            engine,
+            gid: None,
+            sid: None,
+            rev: None,
+            message,
+            classification: None,
+            priority: None,
+            protocol: None,
+            src_ip: None,
+            src_port: None,
+            dst_ip: None,
+            dst_port: None,
+        }
+    }
+}
+
+/// Format d'un fichier (ou flux) de logs IDS.
+/// Noms snake_case dans labs.toml (ex: "suricata_eve").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    SnortFast,
+    SuricataFast,
+    SuricataEve,
+    ZeekNotice,
+}
+
+/// Parser ligne à ligne, avec l'état nécessaire à certains formats.
+///
+/// - Zeek: mémorise les colonnes déclarées par la ligne `#fields`
+/// - Snort: l'horodatage alert_fast ne contient pas l'année, on utilise
+///   celle fournie à la construction (année courante par défaut)
+pub struct AlertParser {
+    format: LogFormat,
+    year: i32,
+    zeek_fields: Vec<String>,
+}
+
+impl AlertParser {
+    pub fn new(format: LogFormat) -> Self {
+        Self::with_year(format, Utc::now().year())
+    }
+
+    pub fn with_year(format: LogFormat, year: i32) -> Self {
+        AlertParser {
+            format,
+            year,
+            zeek_fields: Vec::new(),
+        }
+    }
+
+    /// Parse une ligne. Retourne None pour les lignes vides, les en-têtes
+    /// et tout ce qui n'est pas une alerte.
+    pub fn parse_line(&mut self, line: &str) -> Option<Alert> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() {
+            return None;
+        }
+        match self.format {
+            LogFormat::SnortFast => parse_snort_fast(line, self.year),
+            LogFormat::SuricataFast => parse_suricata_fast(line),
+            LogFormat::SuricataEve => parse_suricata_eve(line),
+            LogFormat::ZeekNotice => {
+                if let Some(fields) = line.strip_prefix("#fields\t") {
+                    self.zeek_fields = fields.split('\t').map(String::from).collect();
+                    return None;
+                }
+                if line.starts_with('#') {
+                    return None;
+                }
+                parse_zeek_notice(&self.zeek_fields, line)
+            }
+        }
+    }
+
+    /// Parse un bloc de texte complet (plusieurs lignes).
+    pub fn parse_str(&mut self, content: &str) -> Vec<Alert> {
+        content.lines().filter_map(|l| self.parse_line(l)).collect()
+    }
+}
+
+// ============================================================================
+// SNORT / SURICATA - FORMAT "FAST"
+// ============================================================================
+// Les deux moteurs partagent la même structure après l'horodatage:
+// [**] [gid:sid:rev] message [**] [Classification: ...] [Priority: n] {PROTO} src -> dst
+
+/// Parse une ligne Snort 3 `alert_fast`.
+/// L'horodatage est au format MM/DD-HH:MM:SS.ffffff (sans année).
+pub fn parse_snort_fast(line: &str, year: i32) -> Option<Alert> {
+    let (ts, rest) = line.split_once(' ')?;
+    let timestamp = parse_naive(&format!("{}/{}", year, ts), "%Y/%m/%d-%H:%M:%S%.f")?;
+    parse_fast_body(rest, timestamp, Engine::Snort)
+}
+
+/// Parse une ligne Suricata `fast.log`.
+/// L'horodatage est au format MM/DD/YYYY-HH:MM:SS.ffffff.
+pub fn parse_suricata_fast(line: &str) -> Option<Alert> {
+    let (ts, rest) = line.split_once(' ')?;
+    let timestamp = parse_naive(ts, "%m/%d/%Y-%H:%M:%S%.f")?;
+    parse_fast_body(rest, timestamp, Engine::Suricata)
+}
+
+fn parse_naive(ts: &str, fmt: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(ts, fmt).ok().map(|n| n.and_utc())
+}
+
+fn parse_fast_body(body: &str, timestamp: DateTime<Utc>, engine: Engine) -> Option<Alert> {
+    let rest = body.trim_start().strip_prefix("[**]")?.trim_start();
+
+    // [gid:sid:rev]
+    let rest = rest.strip_prefix('[')?;
+    let (ids, rest) = rest.split_once(']')?;
+    let mut parts = ids.split(':').map(|p| p.trim().parse::<u32>().ok());
+    let (gid, sid, rev) = (parts.next()??, parts.next()??, parts.next()??);
+
+    // Message jusqu'au second [**]
+    let (message, rest) = rest.split_once("[**]")?;
+    let message = message.trim().trim_matches('"').to_string();
+
+    let mut alert = Alert::new(timestamp, engine, message);
+    alert.gid = Some(gid);
+    alert.sid = Some(sid);
+    alert.rev = Some(rev);
+
+    // Blocs optionnels [Classification: ...] [Priority: n]
+    let mut rest = rest.trim_start();
+    while let Some(inner) = rest.strip_prefix('[') {
+        let (block, after) = inner.split_once(']')?;
+        if let Some((key, value)) = block.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "Classification" if value != "(null)" && !value.is_empty() => {
+                    alert.classification = Some(value.to_string());
+                }
+                "Priority" => alert.priority = value.parse().ok(),
+                _ => {}
+            }
+        }
+        rest = after.trim_start();
+    }
+
+    // {PROTO} src -> dst
+    if let Some(inner) = rest.strip_prefix('{') {
+        let (proto, after) = inner.split_once('}')?;
+        alert.protocol = Some(proto.trim().to_string());
+        rest = after;
+    }
+    if let Some((src, dst)) = rest.split_once("->") {
+        (alert.src_ip, alert.src_port) = parse_endpoint(src);
+        (alert.dst_ip, alert.dst_port) = parse_endpoint(dst);
+    }
+
+    Some(alert)
+}
+
+/// Parse "ip:port", "ip" seul (ICMP) ou une chaîne vide (alertes décodeur).
+fn parse_endpoint(s: &str) -> (Option<IpAddr>, Option<u16>) {
+    let s = s.trim();
+    if s.is_empty() {
+        return (None, None);
+    }
+    if let Ok(ip) = s.parse::<IpAddr>() {
+        return (Some(ip), None);
+    }
+    match s.rsplit_once(':') {
+        Some((ip, port)) => (
+            ip.trim_matches(['[', ']']).parse().ok(),
+            port.parse().ok(),
+        ),
+        None => (None, None),
+    }
+}
+
+// ============================================================================
+// SURICATA - EVE JSON
+// ============================================================================
+
+/// Parse une ligne eve.json. Seuls les événements `event_type: alert`
+/// produisent une alerte (flow, stats, mdns... sont ignorés).
+pub fn parse_suricata_eve(line: &str) -> Option<Alert> {
+    let v: serde_json::Value = serde_json::from_str(line).ok()?;
+    if v.get("event_type")?.as_str()? != "alert" {
+        return None;
+    }
+
+    let timestamp = DateTime::parse_from_str(v.get("timestamp")?.as_str()?, "%Y-%m-%dT%H:%M:%S%.f%z")
+        .ok()?
+        .with_timezone(&Utc);
+    let a = v.get("alert")?;
+    let str_field = |obj: &serde_json::Value, key: &str| obj.get(key).and_then(|x| x.as_str()).map(String::from);
+    let u64_field = |obj: &serde_json::Value, key: &str| obj.get(key).and_then(|x| x.as_u64());
+
+    let mut alert = Alert::new(timestamp, Engine::Suricata, str_field(a, "signature").unwrap_or_default());
+    alert.gid = u64_field(a, "gid").map(|x| x as u32);
+    alert.sid = u64_field(a, "signature_id").map(|x| x as u32);
+    alert.rev = u64_field(a, "rev").map(|x| x as u32);
+    alert.classification = str_field(a, "category").filter(|c| !c.is_empty());
+    alert.priority = u64_field(a, "severity").map(|x| x as u8);
+    alert.protocol = str_field(&v, "proto");
+    alert.src_ip = str_field(&v, "src_ip").and_then(|ip| ip.parse().ok());
+    alert.src_port = u64_field(&v, "src_port").map(|p| p as u16);
+    alert.dst_ip = str_field(&v, "dest_ip").and_then(|ip| ip.parse().ok());
+    alert.dst_port = u64_field(&v, "dest_port").map(|p| p as u16);
+    Some(alert)
+}
+
+// ============================================================================
+// ZEEK - NOTICE.LOG
+// ============================================================================
+
+/// Parse une ligne de données notice.log à partir des colonnes de l'en-tête.
+/// La colonne `note` (ex: IDS_Lab::SYN_Flood_Detected) sert de classification.
+fn parse_zeek_notice(fields: &[String], line: &str) -> Option<Alert> {
+    if fields.is_empty() {
+        return None;
+    }
+    let values: Vec<&str> = line.split('\t').collect();
+    let get = |name: &str| {
+        fields
+            .iter()
+            .position(|f| f == name)
+            .and_then(|i| values.get(i).copied())
+            .filter(|v| *v != "-" && *v != "(empty)" && !v.is_empty())
+    };
+
+    // ts = secondes epoch avec 6 décimales, découpé à la main pour éviter
+    // les erreurs d'arrondi d'un f64
+    let (secs, frac) = get("ts")?.split_once('.').unwrap_or((get("ts")?, "0"));
+    let nanos = format!("{:0<9}", frac).get(..9)?.parse().ok()?;
+    let timestamp = DateTime::from_timestamp(secs.parse().ok()?, nanos)?;
+
+    let mut alert = Alert::new(timestamp, Engine::Zeek, get("msg").unwrap_or_default().to_string());
+    alert.classification = get("note").map(String::from);
+    alert.protocol = get("proto").map(|p| p.to_uppercase());
+    alert.src_ip = get("id.orig_h").or_else(|| get("src")).and_then(|ip| ip.parse().ok());
+    alert.src_port = get("id.orig_p").and_then(|p| p.parse().ok());
+    alert.dst_ip = get("id.resp_h").or_else(|| get("dst")).and_then(|ip| ip.parse().ok());
+    alert.dst_port = get("id.resp_p").or_else(|| get("p")).and_then(|p| p.parse().ok());
+    Some(alert)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../sample-logs").join(name);
+        std::fs::read_to_string(path).expect("fixture manquante")
+    }
+
+    #[test]
+    fn snort_fast_fixture() {
+        let alerts = AlertParser::with_year(LogFormat::SnortFast, 2026).parse_str(&fixture("snort_alerts.txt"));
+        assert_eq!(alerts.len(), 168);
+
+        let first = &alerts[0];
+        assert_eq!(first.engine, Engine::Snort);
+        assert_eq!((first.gid, first.sid, first.rev), (Some(1), Some(1000001), Some(2)));
+        assert_eq!(first.message, "NMAP SYN Scan Detected");
+        assert_eq!(first.priority, Some(0));
+        assert_eq!(first.protocol.as_deref(), Some("TCP"));
+        assert_eq!(first.src_ip, Some("172.28.0.1".parse().unwrap()));
+        assert_eq!(first.src_port, Some(39384));
+        assert_eq!(first.dst_ip, Some("172.28.0.100".parse().unwrap()));
+        assert_eq!(first.dst_port, Some(21));
+        assert_eq!(first.timestamp.to_rfc3339(), "2026-02-28T10:33:23.528916+00:00");
+
+        // Alertes décodeur sans adresses: "{eth}  -> "
+        let decoder = alerts.iter().find(|a| a.sid == Some(6) && a.gid == Some(116)).unwrap();
+        assert_eq!(decoder.protocol.as_deref(), Some("eth"));
+        assert_eq!(decoder.src_ip, None);
+        assert_eq!(decoder.dst_port, None);
+    }
+
+    #[test]
+    fn suricata_fast_fixture() {
+        let alerts = AlertParser::new(LogFormat::SuricataFast).parse_str(&fixture("suricata_fast.log"));
+        assert_eq!(alerts.len(), 50);
+
+        let first = &alerts[0];
+        assert_eq!(first.engine, Engine::Suricata);
+        assert_eq!(first.sid, Some(2000001));
+        assert_eq!(first.message, "ET SCAN NMAP SYN Scan");
+        assert_eq!(first.classification.as_deref(), Some("Attempted Information Leak"));
+        assert_eq!(first.priority, Some(2));
+        assert_eq!(first.dst_port, Some(48));
+
+        let http = alerts.iter().find(|a| a.sid == Some(2000007)).unwrap();
+        assert_eq!(http.classification, None);
+        assert_eq!(http.priority, Some(3));
+    }
+
+    #[test]
+    fn suricata_eve_ignores_non_alert_events() {
+        let alerts = AlertParser::new(LogFormat::SuricataEve).parse_str(&fixture("suricata_eve.json"));
+        assert!(alerts.is_empty());
+    }
+
+    #[test]
+    fn suricata_eve_alert() {
+        let line = r#"{"timestamp":"2026-02-28T10:18:49.680961+0000","flow_id":1,"in_iface":"eth0","event_type":"alert","src_ip":"172.29.0.1","src_port":51442,"dest_ip":"172.29.0.100","dest_port":80,"proto":"TCP","alert":{"action":"allowed","gid":1,"signature_id":2003002,"rev":1,"signature":"ET SCAN NMAP SYN Scan","category":"Attempted Information Leak","severity":2}}"#;
+        let alert = parse_suricata_eve(line).unwrap();
+        assert_eq!(alert.sid, Some(2003002));
+        assert_eq!(alert.message, "ET SCAN NMAP SYN Scan");
+        assert_eq!(alert.priority, Some(2));
+        assert_eq!(alert.dst_port, Some(80));
+        assert_eq!(alert.timestamp.to_rfc3339(), "2026-02-28T10:18:49.680961+00:00");
+    }
+
+    #[test]
+    fn zeek_notice_fixture() {
+        let alerts = AlertParser::new(LogFormat::ZeekNotice).parse_str(&fixture("zeek_notice.log"));
+        assert_eq!(alerts.len(), 62);
+
+        let first = &alerts[0];
+        assert_eq!(first.engine, Engine::Zeek);
+        assert_eq!(first.sid, None);
+        assert_eq!(first.message, "HTTP GET / from 172.30.0.1");
+        assert_eq!(first.classification.as_deref(), Some("IDS_Lab::HTTP_Request_Detected"));
+        assert_eq!(first.src_ip, Some("172.30.0.1".parse().unwrap()));
+        assert_eq!(first.timestamp.timestamp(), 1772277755);
+        assert_eq!(first.timestamp.timestamp_subsec_micros(), 362478);
+    }
+
+    #[test]
+    fn zeek_requires_header() {
+        let mut parser = AlertParser::new(LogFormat::ZeekNotice);
+        assert!(parser.parse_line("1772277755.362478\t-\t-").is_none());
+    }
+}
@@ -0,0 +1,252 @@
+// =============================================================================
+// IDS LAB COMMANDER - Exécution des attaques du cookbook
+// =============================================================================
+//
+// DESCRIPTION:
+// Lance depuis le serveur une technique nmap cataloguée contre la cible
+// d'un lab (target_ip et ports dans labs.toml), au lieu de copier-coller
+// la commande du cookbook.
+//
+// DÉROULEMENT (POST /api/attacks):
+// 1. Un run est ouvert (runs.rs) avec la commande exacte exécutée
+// 2. nmap est lancé, chaque ligne de stdout/stderr est diffusée aux
+//    navigateurs abonnés à GET /api/runs/:id/output (SSE)
+// 3. À la sortie du processus, le run est clos avec son code de retour
+//
+// BINAIRE NMAP:
+// Variable NMAP_BIN (défaut: "nmap", cherché dans le PATH). Les scans SYN,
+// fragmentés ou avec decoys exigent les droits root: lancer le commander
+// avec sudo ou donner les capabilities à nmap (voir /setup).
+//
+// =============================================================================
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::broadcast;
+
+/// Nombre d'exécutions terminées dont la sortie reste consultable.
+const KEEP_FINISHED: usize = 20;
+
+/// Technique d'attaque du cookbook.
+#[derive(Debug, Serialize)]
+pub struct Technique {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Options nmap (la cible et les ports sont ajoutés à l'exécution)
+    pub args: &'static [&'static str],
+    pub description: &'static str,
+}
+
+/// Catalogue des techniques exécutables.
+pub const TECHNIQUES: &[Technique] = &[
+    Technique { id: "syn", name: "SYN Scan", args: &["-sS"], description: "Scan de base (détecté niveau ≥2)" },
+    Technique { id: "connect", name: "TCP Connect", args: &["-sT"], description: "Handshake complet (détecté niveau ≥2)" },
+    Technique { id: "fragment", name: "Fragmentation IP", args: &["-sS", "-f"], description: "Évite niveau ≤3" },
+    Technique { id: "mtu8", name: "MTU 8", args: &["-sS", "--mtu", "8"], description: "Fragments de 8 octets (évite niveau ≤4)" },
+    Technique { id: "decoys", name: "Decoys", args: &["-sS", "-D", "RND:10"], description: "Brouille la source" },
+    Technique { id: "source-port-53", name: "Source port 53", args: &["-sS", "--source-port", "53"], description: "Port DNS, souvent autorisé" },
+    Technique { id: "timing-t0", name: "Timing T0", args: &["-sS", "-T0"], description: "Très lent, évite les seuils (plusieurs minutes)" },
+];
+
+/// Corps de POST /api/attacks.
+#[derive(Debug, Deserialize)]
+pub struct AttackRequest {
+    /// Nom du lab dans labs.toml
+    pub lab: String,
+    pub technique: String,
+}
+
+/// Recherche une technique par son identifiant.
+pub fn technique(id: &str) -> Option<&'static Technique> {
+    TECHNIQUES.iter().find(|t| t.id == id)
+}
+
+/// Binaire nmap à exécuter (réglage nmap_bin, voir config.rs).
+pub fn nmap_bin() -> PathBuf {
+    crate::config::get().nmap_bin.clone()
+}
+
+/// Arguments complets d'une technique contre une cible.
+/// Le scan est limité aux ports exposés par la cible (durée des scans T0).
+pub fn nmap_args(technique: &Technique, target: IpAddr, ports: &str) -> Vec<String> {
+    let mut args: Vec<String> = technique.args.iter().map(|a| a.to_string()).collect();
+    if !ports.is_empty() {
+        args.extend(["-p".to_string(), ports.to_string()]);
+    }
+    args.push(target.to_string());
+    args
+}
+
+/// Événement de sortie d'une exécution (SSE `line` ou `exit`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum OutputEvent {
+    Line { text: String },
+    Exit { exit_code: Option<i32> },
+}
+
+/// Sortie d'une exécution: lignes déjà émises et diffusion des suivantes.
+struct Execution {
+    events: Vec<OutputEvent>,
+    finished: bool,
+    tx: broadcast::Sender<OutputEvent>,
+}
+
+/// Sorties des exécutions en cours et récentes, par identifiant de run
+/// (ou de job pour les opérations sur les labs).
+#[derive(Clone, Default)]
+pub struct Executions {
+    inner: Arc<Mutex<HashMap<u64, Execution>>>,
+}
+
+impl Executions {
+    /// Déclare une nouvelle exécution (avant de lancer le processus).
+    pub fn register(&self, run_id: u64) {
+        let execution = Execution { events: Vec::new(), finished: false, tx: broadcast::channel(256).0 };
+        self.inner.lock().unwrap().insert(run_id, execution);
+    }
+
+    /// Vrai si le processus du run tourne encore.
+    pub fn is_running(&self, run_id: u64) -> bool {
+        self.inner.lock().unwrap().get(&run_id).is_some_and(|e| !e.finished)
+    }
+
+    /// Événements déjà émis, et récepteur des suivants si l'exécution est en cours.
+    /// None si le run n'a pas de sortie en mémoire.
+    pub fn subscribe(&self, run_id: u64) -> Option<(Vec<OutputEvent>, Option<broadcast::Receiver<OutputEvent>>)> {
+        let map = self.inner.lock().unwrap();
+        let execution = map.get(&run_id)?;
+        let rx = (!execution.finished).then(|| execution.tx.subscribe());
+        Some((execution.events.clone(), rx))
+    }
+
+    /// Ajoute un événement à la sortie d'une exécution et le diffuse.
+    pub fn push(&self, run_id: u64, event: OutputEvent) {
+        let mut map = self.inner.lock().unwrap();
+        if let Some(execution) = map.get_mut(&run_id) {
+            // Erreur = aucun navigateur abonné, la ligne reste dans l'historique
+            let _ = execution.tx.send(event.clone());
+            execution.events.push(event);
+        }
+    }
+
+    /// Émet l'événement de fin et oublie les exécutions terminées les plus anciennes.
+    pub fn finish(&self, run_id: u64, exit_code: Option<i32>) {
+        let mut map = self.inner.lock().unwrap();
+        if let Some(execution) = map.get_mut(&run_id) {
+            // Sous le même verrou que `subscribe`: un abonné voit l'événement
+            // de fin soit dans l'historique, soit dans son récepteur
+            let event = OutputEvent::Exit { exit_code };
+            let _ = execution.tx.send(event.clone());
+            execution.events.push(event);
+            execution.finished = true;
+        }
+        let mut finished: Vec<u64> = map.iter().filter(|(_, e)| e.finished).map(|(id, _)| *id).collect();
+        if finished.len() > KEEP_FINISHED {
+            finished.sort_unstable();
+            for id in &finished[..finished.len() - KEEP_FINISHED] {
+                map.remove(id);
+            }
+        }
+    }
+}
+
+/// Exécute le programme et diffuse sa sortie ligne par ligne.
+/// Retourne le code de retour (None si le processus n'a pas pu démarrer
+/// ou a été tué par un signal). L'événement de fin est émis par `finish`.
+pub async fn execute(executions: &Executions, run_id: u64, program: &Path, args: &[String]) -> Option<i32> {
+    let mut command = Command::new(program);
+    command.args(args);
+    execute_command(executions, run_id, command).await
+}
+
+/// Comme `execute`, pour une commande déjà préparée (répertoire de travail,
+/// environnement...). Utilisé aussi par les jobs des labs (jobs.rs).
+pub async fn execute_command(executions: &Executions, id: u64, mut command: Command) -> Option<i32> {
+    let program = command.as_std().get_program().to_string_lossy().into_owned();
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let mut child = match child {
+        Ok(c) => c,
+        Err(e) => {
+            let text = format!("Failed to start {}: {}", program, e);
+            executions.push(id, OutputEvent::Line { text });
+            return None;
+        }
+    };
+
+    let stdout = child.stdout.take().map(|s| forward_lines(executions.clone(), id, s));
+    let stderr = child.stderr.take().map(|s| forward_lines(executions.clone(), id, s));
+    if let Some(task) = stdout {
+        let _ = task.await;
+    }
+    if let Some(task) = stderr {
+        let _ = task.await;
+    }
+
+    match child.wait().await {
+        Ok(status) => status.code(),
+        Err(e) => {
+            executions.push(id, OutputEvent::Line { text: format!("Error waiting for process: {}", e) });
+            None
+        }
+    }
+}
+
+fn forward_lines<R>(executions: Executions, run_id: u64, reader: R) -> tokio::task::JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(text)) = lines.next_line().await {
+            executions.push(run_id, OutputEvent::Line { text });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn stub_nmap_output_and_exit_code_are_recorded() {
+        let dir = std::env::temp_dir().join(format!("ids-attack-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stub = dir.join("nmap");
+        std::fs::write(&stub, "#!/bin/sh\necho \"Starting Nmap $*\"\necho \"done\"\nexit 3\n").unwrap();
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let executions = Executions::default();
+        executions.register(1);
+        let args = nmap_args(technique("mtu8").unwrap(), "172.29.0.100".parse().unwrap(), "21,22,80,8080");
+        let code = execute(&executions, 1, &stub, &args).await;
+        executions.finish(1, code);
+
+        assert_eq!(code, Some(3));
+        let (events, rx) = executions.subscribe(1).unwrap();
+        assert!(rx.is_none());
+        let lines: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                OutputEvent::Line { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines, ["Starting Nmap -sS --mtu 8 -p 21,22,80,8080 172.29.0.100", "done"]);
+        assert!(matches!(events.last(), Some(OutputEvent::Exit { exit_code: Some(3) })));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
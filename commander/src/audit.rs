@@ -0,0 +1,185 @@
+// =============================================================================
+// IDS LAB COMMANDER - Journal d'audit des actions de pilotage
+// =============================================================================
+//
+// DESCRIPTION:
+// Chaque action qui modifie un lab est enregistrée (store.rs, table
+// audit_log) avec son auteur, son adresse source, ses paramètres, son
+// résultat et sa date: qui a passé Suricata au niveau 1 avant l'examen ?
+//
+// ACTIONS:
+//   container.start / container.stop / container.restart   /start|stop|restart/:id
+//   rules.apply                                            /apply/:lab/:level
+//   rules.rollback                                         /api/rules/:lab/rollback/:id
+//   logs.reset                                             /reset-logs/:lab
+//   lab.start / lab.stop / lab.rebuild                     POST /api/jobs, /lab/start|stop/:lab
+//   lab.stop_all                                           POST /api/jobs/stop-all (lab "*"),
+//                                                          suivi d'un lab.stop par lab
+//   attack.start                                           POST /api/attacks
+//   run.start                                              POST /api/runs
+//   matrix.start                                           POST /api/matrix
+//
+// RÉSULTATS:
+//   success   action effectuée (ou job docker compose lancé / mis en file)
+//   denied    lab hors des labs du compte (403)
+//   failed    lab occupé (409), erreur Docker ou système de fichiers
+//
+// Le journal est en ajout seul: des triggers SQLite refusent UPDATE et
+// DELETE, et la purge de rétention ne le concerne pas.
+//
+// EXEMPLES:
+//   /api/audit?lab=suricata&action=rules.apply&since=2026-02-28T08:00:00Z
+//   /api/audit/export > audit.jsonl      (une entrée JSON par ligne, de la plus ancienne)
+//
+// =============================================================================
+
+use crate::auth::Identity;
+use crate::error::ApiError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// Taille de page par défaut et maximale.
+const DEFAULT_LIMIT: usize = 200;
+const MAX_LIMIT: usize = 1000;
+
+/// Résultat d'une action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Success,
+    Denied,
+    Failed,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::Denied => "denied",
+            Outcome::Failed => "failed",
+        }
+    }
+}
+
+impl std::str::FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "success" => Ok(Outcome::Success),
+            "denied" => Ok(Outcome::Denied),
+            "failed" => Ok(Outcome::Failed),
+            _ => Err(format!("unknown outcome {:?}", s)),
+        }
+    }
+}
+
+/// Une action de pilotage.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEntry {
+    pub time: DateTime<Utc>,
+    /// Compte (auth.rs), "local" sans authentification
+    pub actor: String,
+    pub source_ip: String,
+    pub action: String,
+    pub lab: String,
+    /// Paramètres de la requête (container, niveau, opération...)
+    pub params: serde_json::Value,
+    pub outcome: Outcome,
+    /// Message d'erreur, ou précision sur le succès ("job #12 queued")
+    pub detail: Option<String>,
+}
+
+impl AuditEntry {
+    /// Entrée d'une action de `user` depuis `client`, d'après le résultat
+    /// renvoyé au client (détail du succès ou erreur).
+    pub fn new(
+        user: &Identity,
+        client: SocketAddr,
+        action: &str,
+        lab: &str,
+        params: serde_json::Value,
+        result: Result<Option<String>, &ApiError>,
+    ) -> AuditEntry {
+        let (outcome, detail) = match result {
+            Ok(detail) => (Outcome::Success, detail),
+            Err(e @ ApiError::Forbidden(_)) => (Outcome::Denied, Some(e.message())),
+            Err(e) => (Outcome::Failed, Some(e.message())),
+        };
+        AuditEntry {
+            time: Utc::now(),
+            actor: user.name.clone(),
+            source_ip: client.ip().to_string(),
+            action: action.to_string(),
+            lab: lab.to_string(),
+            params,
+            outcome,
+            detail,
+        }
+    }
+}
+
+/// Entrée accompagnée de son numéro d'enregistrement.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    pub id: u64,
+    #[serde(flatten)]
+    pub entry: AuditEntry,
+}
+
+/// Paramètres de GET /api/audit et /api/audit/export.
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub actor: Option<String>,
+    pub lab: Option<String>,
+    pub action: Option<String>,
+    pub outcome: Option<Outcome>,
+    /// Ignoré par l'export (toutes les entrées)
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+/// Export JSON lines: une entrée par ligne.
+pub fn to_jsonl(records: &[AuditRecord]) -> String {
+    records
+        .iter()
+        .filter_map(|r| serde_json::to_string(r).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Role;
+
+    #[test]
+    fn entries_record_outcome_and_export_as_json_lines() {
+        let user = Identity { name: "alice".into(), role: Role::Student, labs: vec!["suricata".into()] };
+        let client: SocketAddr = "10.0.0.7:51234".parse().unwrap();
+        let params = serde_json::json!({ "level": "1" });
+
+        let ok = AuditEntry::new(&user, client, "rules.apply", "suricata", params.clone(), Ok(None));
+        assert_eq!((ok.actor.as_str(), ok.source_ip.as_str(), ok.outcome), ("alice", "10.0.0.7", Outcome::Success));
+
+        let denied = ApiError::Forbidden("User alice is not allowed on lab snort".into());
+        let entry = AuditEntry::new(&user, client, "rules.apply", "snort", params, Err(&denied));
+        assert_eq!(entry.outcome, Outcome::Denied);
+        assert_eq!(entry.detail.as_deref(), Some("User alice is not allowed on lab snort"));
+
+        let records = [AuditRecord { id: 1, entry: ok }, AuditRecord { id: 2, entry }];
+        let export = to_jsonl(&records);
+        let lines: Vec<serde_json::Value> = export.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["outcome"], "denied");
+        assert_eq!(lines[0]["params"]["level"], "1");
+    }
+}
@@ -0,0 +1,650 @@
+// =============================================================================
+// IDS LAB COMMANDER - Comptes, sessions et rôles
+// =============================================================================
+//
+// DESCRIPTION:
+// Les comptes locaux et les tokens d'API sont déclarés dans users_file
+// (config.rs, voir users.example.toml): mots de passe hachés en Argon2id,
+// tokens stockés sous forme d'empreinte SHA-256. Le fichier se remplit avec
+// `ids-commander add-user` et `ids-commander add-token`.
+//
+// IDENTIFICATION (middleware `require`, sur toutes les routes):
+// - Authorization: Bearer <token>   scripts et API
+// - cookie ids_session               navigateur, après POST /login
+//
+// RÔLES:
+//   viewer      dashboard, alertes, rapports (GET)
+//   student     + appliquer un niveau de règles (ou revenir à une version
+//               précédente), lancer attaques, runs et matrices sur ses labs
+//               (labs du compte: aucun si la liste est vide, "*" = tous)
+//   instructor  + cycle de vie des labs et containers, reset des logs,
+//               journal d'audit (/api/audit): tout
+//
+// Publics: /login, /static, /api/health. Sans utilisateur identifié, une
+// page renvoie vers /login et l'API répond 401; un rôle insuffisant donne 403.
+//
+// Sans users_file, l'authentification est désactivée: chaque requête est
+// traitée comme un instructor local (le serveur n'écoute alors qu'en local).
+//
+// CSRF (requêtes autres que GET/HEAD):
+// - un en-tête Origin d'un autre site que Host est refusé (403)
+// - avec une session (ou sans authentification), l'en-tête X-CSRF-Token doit
+//   porter le token de la session (token du serveur sans authentification),
+//   fourni par GET /api/me et ajouté par dashboard.js à chaque hx-post
+// - un token d'API (Bearer) n'est jamais envoyé par un navigateur: pas de
+//   token CSRF; POST /login (pas encore de session) n'a que le contrôle Origin
+//
+// =============================================================================
+
+use crate::error::ApiError;
+use crate::labs::Registry;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, Method};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Nom du cookie de session.
+pub const SESSION_COOKIE: &str = "ids_session";
+/// Durée d'une session sans nouvelle connexion.
+const SESSION_TTL: Duration = Duration::from_secs(12 * 3600);
+
+/// En-tête du token CSRF (dashboard.js, scripts sans token d'API).
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Routes POST ouvertes aux étudiants (préfixes).
+const STUDENT_ROUTES: &[&str] = &["/apply/", "/api/rules/", "/api/attacks", "/api/runs", "/api/matrix"];
+/// Routes de lecture réservées aux instructeurs (journal d'audit).
+const INSTRUCTOR_READ_ROUTES: &[&str] = &["/api/audit"];
+/// Entrée de `labs` qui ouvre tous les labs à un étudiant.
+pub const ALL_LABS: &str = "*";
+
+static ACCOUNTS: OnceLock<Option<Accounts>> = OnceLock::new();
+static SESSIONS: Mutex<BTreeMap<String, Session>> = Mutex::new(BTreeMap::new());
+/// Token CSRF sans authentification (un par démarrage du serveur).
+static LOCAL_CSRF: OnceLock<String> = OnceLock::new();
+
+/// Rôle d'un compte, du moins au plus privilégié.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Student,
+    Instructor,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Student => "student",
+            Role::Instructor => "instructor",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "student" => Ok(Role::Student),
+            "instructor" => Ok(Role::Instructor),
+            _ => Err(format!("unknown role {:?} (viewer, student, instructor)", s)),
+        }
+    }
+}
+
+/// Compte de users_file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UserEntry {
+    name: String,
+    role: Role,
+    /// Hash Argon2id (format PHC)
+    password: String,
+    #[serde(default)]
+    labs: Vec<String>,
+}
+
+/// Token d'API de users_file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokenEntry {
+    user: String,
+    name: String,
+    /// "sha256:<hex>"
+    hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AccountsFile {
+    #[serde(default)]
+    user: Vec<UserEntry>,
+    #[serde(default)]
+    token: Vec<TokenEntry>,
+}
+
+/// Comptes et tokens chargés au démarrage.
+#[derive(Debug)]
+pub struct Accounts {
+    users: Vec<UserEntry>,
+    tokens: Vec<TokenEntry>,
+}
+
+/// Utilisateur d'une requête (extension posée par `require`).
+#[derive(Debug, Clone, Serialize)]
+pub struct Identity {
+    pub name: String,
+    pub role: Role,
+    /// Labs d'un étudiant (vide: aucun, "*": tous)
+    pub labs: Vec<String>,
+}
+
+/// Token CSRF attendu pour la requête (extension posée par `require`).
+#[derive(Debug, Clone)]
+pub struct CsrfToken(pub String);
+
+struct Session {
+    identity: Identity,
+    csrf: String,
+    expires: Instant,
+}
+
+impl Accounts {
+    /// Lit users_file; None s'il n'existe pas (authentification désactivée).
+    pub fn load(path: &Path, registry: &Registry) -> Result<Option<Accounts>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let accounts = Accounts::parse(&content, registry).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        for user in accounts.users.iter().filter(|u| u.role == Role::Student && u.labs.is_empty()) {
+            eprintln!("⚠ Student {} has no labs (labs = [\"*\"] for all labs)", user.name);
+        }
+        Ok(Some(accounts))
+    }
+
+    fn parse(content: &str, registry: &Registry) -> Result<Accounts, String> {
+        let file: AccountsFile = toml::from_str(content).map_err(|e| e.to_string())?;
+        for (i, user) in file.user.iter().enumerate() {
+            if file.user[..i].iter().any(|u| u.name == user.name) {
+                return Err(format!("duplicate user {:?}", user.name));
+            }
+            if PasswordHash::new(&user.password).is_err() {
+                return Err(format!("user {}: password is not an Argon2 hash (use ids-commander add-user)", user.name));
+            }
+            if let Some(lab) = user.labs.iter().find(|l| !is_known_lab(registry, l)) {
+                return Err(format!("user {}: unknown lab {:?}", user.name, lab));
+            }
+        }
+        if let Some(token) = file.token.iter().find(|t| !file.user.iter().any(|u| u.name == t.user)) {
+            return Err(format!("token {}: unknown user {:?}", token.name, token.user));
+        }
+        Ok(Accounts { users: file.user, tokens: file.token })
+    }
+
+    fn user(&self, name: &str) -> Option<&UserEntry> {
+        self.users.iter().find(|u| u.name == name)
+    }
+}
+
+/// Lab de labs.toml ou "*".
+fn is_known_lab(registry: &Registry, lab: &str) -> bool {
+    lab == ALL_LABS || registry.get(lab).is_some()
+}
+
+impl UserEntry {
+    fn identity(&self) -> Identity {
+        Identity { name: self.name.clone(), role: self.role, labs: self.labs.clone() }
+    }
+}
+
+impl Identity {
+    /// Identité de toutes les requêtes quand l'authentification est désactivée.
+    fn local() -> Identity {
+        Identity { name: "local".into(), role: Role::Instructor, labs: vec![] }
+    }
+
+    /// Un étudiant n'agit que sur ses labs (aucun si la liste est vide,
+    /// tous avec "*"); un instructor sur tous.
+    pub fn check_lab(&self, lab: &str) -> Result<(), ApiError> {
+        let allowed = self.role == Role::Instructor || self.labs.iter().any(|l| l == ALL_LABS || l == lab);
+        match allowed {
+            true => Ok(()),
+            false => Err(ApiError::Forbidden(format!("User {} is not allowed on lab {}", self.name, lab))),
+        }
+    }
+
+    /// Auteur d'une opération (verrous des labs): le compte, sinon l'adresse.
+    pub fn client(&self, addr: SocketAddr) -> String {
+        match enabled() {
+            true => self.name.clone(),
+            false => addr.ip().to_string(),
+        }
+    }
+}
+
+/// Installe les comptes chargés au démarrage (None: pas d'authentification).
+pub fn init(accounts: Option<Accounts>) {
+    let _ = ACCOUNTS.set(accounts);
+}
+
+fn accounts() -> Option<&'static Accounts> {
+    ACCOUNTS.get().and_then(Option::as_ref)
+}
+
+/// Vrai si users_file existe.
+pub fn enabled() -> bool {
+    accounts().is_some()
+}
+
+/// Hash Argon2id d'un mot de passe (format PHC, sel aléatoire).
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|h| Argon2::default().verify_password(password.as_bytes(), &h).is_ok())
+}
+
+/// Chaîne hexadécimale aléatoire de `bytes` octets (tokens, sessions).
+fn random_hex(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn token_hash(token: &str) -> String {
+    format!("sha256:{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Nouveau token d'API et son empreinte à stocker.
+pub fn new_token() -> (String, String) {
+    let token = format!("ids_{}", random_hex(32));
+    let hash = token_hash(&token);
+    (token, hash)
+}
+
+/// Vérifie un compte et ouvre une session. Retourne l'identifiant de session.
+pub fn login(name: &str, password: &str) -> Option<String> {
+    let user = accounts()?.user(name)?;
+    if !verify_password(password, &user.password) {
+        return None;
+    }
+    let id = random_hex(32);
+    let mut sessions = SESSIONS.lock().unwrap();
+    sessions.retain(|_, s| s.expires > Instant::now());
+    let session = Session { identity: user.identity(), csrf: random_hex(32), expires: Instant::now() + SESSION_TTL };
+    sessions.insert(id.clone(), session);
+    Some(id)
+}
+
+/// Ferme la session du cookie de la requête.
+pub fn logout(headers: &HeaderMap) {
+    if let Some(id) = session_cookie(headers) {
+        SESSIONS.lock().unwrap().remove(&id);
+    }
+}
+
+/// En-tête Set-Cookie d'une session (vide et expiré pour la fermer).
+pub fn session_cookie_header(id: Option<&str>) -> String {
+    match id {
+        Some(id) => format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}",
+            SESSION_COOKIE,
+            id,
+            SESSION_TTL.as_secs()
+        ),
+        None => format!("{}=; Path=/; HttpOnly; SameSite=Strict; Max-Age=0", SESSION_COOKIE),
+    }
+}
+
+fn session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|c| c.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('=').map(str::to_string))
+}
+
+/// Utilisateur de la requête (token d'API, sinon cookie de session) et
+/// token CSRF attendu (None pour un token d'API).
+fn authenticate(accounts: &Accounts, headers: &HeaderMap) -> Option<(Identity, Option<String>)> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if let Some(token) = bearer {
+        let hash = token_hash(token.trim());
+        let entry = accounts.tokens.iter().find(|t| t.hash == hash)?;
+        return accounts.user(&entry.user).map(|u| (u.identity(), None));
+    }
+    let id = session_cookie(headers)?;
+    let sessions = SESSIONS.lock().unwrap();
+    sessions
+        .get(&id)
+        .filter(|s| s.expires > Instant::now())
+        .map(|s| (s.identity.clone(), Some(s.csrf.clone())))
+}
+
+fn local_csrf() -> String {
+    LOCAL_CSRF.get_or_init(|| random_hex(32)).clone()
+}
+
+/// Vrai si l'en-tête Origin (posé par les navigateurs) désigne un autre
+/// site que celui du serveur.
+fn cross_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+    origin.split_once("://").map(|(_, authority)| authority) != host
+}
+
+/// Vrai si la requête porte le token CSRF attendu (comparaison en temps constant).
+fn csrf_matches(headers: &HeaderMap, expected: &str) -> bool {
+    let Some(sent) = headers.get(CSRF_HEADER).map(|v| v.as_bytes()) else {
+        return false;
+    };
+    sent.len() == expected.len() && sent.iter().zip(expected.as_bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Rôle minimal d'une route (None: publique).
+pub fn required_role(method: &Method, path: &str) -> Option<Role> {
+    if path == "/login" || path == "/api/health" || path.starts_with("/static/") {
+        return None;
+    }
+    if INSTRUCTOR_READ_ROUTES.iter().any(|r| path.starts_with(r)) {
+        return Some(Role::Instructor);
+    }
+    if method == Method::GET || method == Method::HEAD || path == "/logout" {
+        return Some(Role::Viewer);
+    }
+    match STUDENT_ROUTES.iter().any(|r| path.starts_with(r)) {
+        true => Some(Role::Student),
+        false => Some(Role::Instructor),
+    }
+}
+
+/// Middleware: identifie l'utilisateur, applique le rôle de la route et
+/// la protection CSRF, puis pose l'`Identity` (et le `CsrfToken`) dans les
+/// extensions de la requête.
+pub async fn require(mut request: Request, next: Next) -> Response {
+    let required = required_role(request.method(), request.uri().path());
+    let state_changing = !matches!(*request.method(), Method::GET | Method::HEAD);
+    if state_changing && cross_origin(request.headers()) {
+        return ApiError::Forbidden("Cross-origin request refused".into()).into_response();
+    }
+    let (identity, csrf) = match accounts() {
+        None => (Some(Identity::local()), Some(local_csrf())),
+        Some(accounts) => match authenticate(accounts, request.headers()) {
+            Some((identity, csrf)) => (Some(identity), csrf),
+            None => (None, None),
+        },
+    };
+    if let Some(csrf) = csrf.clone() {
+        request.extensions_mut().insert(CsrfToken(csrf));
+    }
+
+    match (identity, required) {
+        (identity, None) => {
+            if let Some(identity) = identity {
+                request.extensions_mut().insert(identity);
+            }
+            next.run(request).await
+        }
+        (Some(identity), Some(role)) if identity.role >= role => {
+            if let Some(expected) = csrf.filter(|_| state_changing) {
+                if !csrf_matches(request.headers(), &expected) {
+                    return ApiError::Forbidden("Missing or invalid CSRF token (X-CSRF-Token header)".into())
+                        .into_response();
+                }
+            }
+            request.extensions_mut().insert(identity);
+            next.run(request).await
+        }
+        (Some(identity), Some(role)) => ApiError::Forbidden(format!(
+            "{} requires the {} role (user {} is {})",
+            request.uri().path(),
+            role.as_str(),
+            identity.name,
+            identity.role.as_str()
+        ))
+        .into_response(),
+        // Pages: vers le formulaire de connexion; API: 401
+        (None, Some(_)) if request.method() == Method::GET && !request.uri().path().starts_with("/api/") => {
+            Redirect::to("/login").into_response()
+        }
+        (None, Some(_)) => ApiError::Unauthorized("Login or API token required".into()).into_response(),
+    }
+}
+
+/// Mot de passe de `add-user`, lu sur stdin (invite sur stderr si terminal).
+pub fn read_password() -> Result<String, String> {
+    use std::io::IsTerminal;
+    if std::io::stdin().is_terminal() {
+        eprint!("Password (visible): ");
+    }
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).map_err(|e| e.to_string())?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Ajoute un bloc TOML à users_file (créé avec un en-tête s'il n'existe pas).
+fn append(path: &Path, block: &str) -> Result<(), String> {
+    let new = !path.exists();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let header = "# Comptes du commander (voir users.example.toml)\n# Géré par ids-commander add-user / add-token\n";
+    let content = if new { format!("{}{}", header, block) } else { block.to_string() };
+    file.write_all(content.as_bytes()).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+/// `ids-commander add-user`: ajoute un compte à users_file.
+pub fn add_user(path: &Path, registry: &Registry, name: &str, role: &str, labs: &[String], password: &str) -> Result<(), String> {
+    let role: Role = role.parse()?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        return Err(format!("user name {:?} must be letters, digits, '-', '_' or '.'", name));
+    }
+    if let Some(accounts) = Accounts::load(path, registry)? {
+        if accounts.user(name).is_some() {
+            return Err(format!("user {:?} already exists in {}", name, path.display()));
+        }
+    }
+    if let Some(lab) = labs.iter().find(|l| !is_known_lab(registry, l)) {
+        return Err(format!("unknown lab {:?}", lab));
+    }
+    if password.is_empty() {
+        return Err("empty password".into());
+    }
+    let block = format!(
+        "\n[[user]]\nname = {:?}\nrole = \"{}\"\nlabs = {:?}\npassword = \"{}\"\n",
+        name,
+        role.as_str(),
+        labs,
+        hash_password(password)?
+    );
+    append(path, &block)
+}
+
+/// `ids-commander add-token`: crée un token pour un compte et retourne sa
+/// valeur en clair (seule son empreinte est écrite dans users_file).
+pub fn add_token(path: &Path, registry: &Registry, user: &str, name: &str) -> Result<String, String> {
+    let accounts = Accounts::load(path, registry)?.ok_or_else(|| format!("{} not found (add-user first)", path.display()))?;
+    if accounts.user(user).is_none() {
+        return Err(format!("unknown user {:?}", user));
+    }
+    let (token, hash) = new_token();
+    append(path, &format!("\n[[token]]\nuser = {:?}\nname = {:?}\nhash = \"{}\"\n", user, name, hash))?;
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> Registry {
+        Registry::parse(
+            "[[lab]]\nname = \"suricata\"\ntitle = \"S\"\ncompose_dir = \"suricata-lab\"\nsubnet = \"172.29.0.0/24\"\n\
+             [[lab]]\nname = \"snort\"\ntitle = \"N\"\ncompose_dir = \"snort-lab\"\nsubnet = \"172.30.0.0/24\"",
+        )
+        .unwrap()
+    }
+
+    /// users_file propre à un test (les tests tournent en parallèle).
+    fn users_file(test: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("ids-users-{}-{}.toml", test, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn add_user_validates_accounts() {
+        let registry = registry();
+        let path = users_file("add");
+
+        add_user(&path, &registry, "alice", "student", &["suricata".into()], "s3cret").unwrap();
+        assert!(add_user(&path, &registry, "alice", "viewer", &[], "x").is_err());
+        assert!(add_user(&path, &registry, "bob", "student", &["zeek".into()], "x").is_err());
+        assert!(add_user(&path, &registry, "bob", "admin", &[], "x").is_err());
+        assert!(add_user(&path, &registry, "bob/..", "viewer", &[], "x").is_err());
+        assert!(add_user(&path, &registry, "bob", "viewer", &[], "").is_err());
+
+        let accounts = Accounts::load(&path, &registry).unwrap().unwrap();
+        let alice = accounts.user("alice").unwrap();
+        assert!(verify_password("s3cret", &alice.password) && !verify_password("guess", &alice.password));
+        assert!(accounts.user("bob").is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn token_auth() {
+        let registry = registry();
+        let path = users_file("token");
+        add_user(&path, &registry, "alice", "student", &["suricata".into()], "s3cret").unwrap();
+        let token = add_token(&path, &registry, "alice", "ci").unwrap();
+        assert!(add_token(&path, &registry, "mallory", "ci").is_err());
+        let accounts = Accounts::load(&path, &registry).unwrap().unwrap();
+
+        let (identity, csrf) = authenticate(&accounts, &bearer(&token)).unwrap();
+        assert_eq!((identity.name.as_str(), identity.role), ("alice", Role::Student));
+        assert_eq!(identity.labs, vec!["suricata".to_string()]);
+        assert!(csrf.is_none());
+
+        // Seule l'empreinte est stockée; un token inconnu n'identifie personne
+        assert!(!std::fs::read_to_string(&path).unwrap().contains(&token));
+        assert!(authenticate(&accounts, &bearer("ids_forged")).is_none());
+        assert!(authenticate(&accounts, &HeaderMap::new()).is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn route_roles() {
+        assert_eq!(required_role(&Method::GET, "/login"), None);
+        assert_eq!(required_role(&Method::GET, "/static/css/dashboard.css"), None);
+        assert_eq!(required_role(&Method::GET, "/api/alerts"), Some(Role::Viewer));
+        assert_eq!(required_role(&Method::POST, "/logout"), Some(Role::Viewer));
+        assert_eq!(required_role(&Method::POST, "/apply/suricata/3"), Some(Role::Student));
+        assert_eq!(required_role(&Method::POST, "/api/runs/4/stop"), Some(Role::Student));
+        assert_eq!(required_role(&Method::POST, "/api/rules/suricata/rollback/3"), Some(Role::Student));
+        assert_eq!(required_role(&Method::POST, "/api/jobs"), Some(Role::Instructor));
+        assert_eq!(required_role(&Method::POST, "/restart/abc"), Some(Role::Instructor));
+        assert_eq!(required_role(&Method::GET, "/api/audit/export"), Some(Role::Instructor));
+    }
+
+    #[test]
+    fn student_lab_scoping() {
+        let registry = registry();
+        let path = users_file("labs");
+        add_user(&path, &registry, "alice", "student", &["suricata".into()], "x").unwrap();
+        add_user(&path, &registry, "bob", "student", &[], "x").unwrap();
+        add_user(&path, &registry, "carol", "student", &[ALL_LABS.into()], "x").unwrap();
+        add_user(&path, &registry, "prof", "instructor", &[], "x").unwrap();
+        let accounts = Accounts::load(&path, &registry).unwrap().unwrap();
+        let identity = |name: &str| accounts.user(name).unwrap().identity();
+
+        assert!(identity("alice").check_lab("suricata").is_ok());
+        assert!(identity("alice").check_lab("snort").is_err());
+        // Liste vide: aucun lab; "*": tous les labs
+        assert!(identity("bob").check_lab("suricata").is_err());
+        assert!(identity("bob").check_lab("snort").is_err());
+        assert!(identity("carol").check_lab("suricata").is_ok());
+        assert!(identity("carol").check_lab("snort").is_ok());
+        // Un instructor n'a pas besoin de liste
+        assert!(identity("prof").check_lab("snort").is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn csrf_protection() {
+        // Origin d'un autre site refusé; même origine ou pas d'Origin (curl) accepté
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, "localhost:3000".parse().unwrap());
+        assert!(!cross_origin(&headers));
+        headers.insert(header::ORIGIN, "http://localhost:3000".parse().unwrap());
+        assert!(!cross_origin(&headers));
+        for origin in ["https://evil.example", "http://localhost:3001", "null"] {
+            headers.insert(header::ORIGIN, origin.parse().unwrap());
+            assert!(cross_origin(&headers), "{}", origin);
+        }
+
+        // Cookie de session: X-CSRF-Token absent ou différent refusé
+        let registry = registry();
+        let path = users_file("csrf");
+        add_user(&path, &registry, "alice", "student", &["suricata".into()], "x").unwrap();
+        let token = add_token(&path, &registry, "alice", "ci").unwrap();
+        let accounts = Accounts::load(&path, &registry).unwrap().unwrap();
+        let identity = accounts.user("alice").unwrap().identity();
+        let session = Session { identity, csrf: "abc123".into(), expires: Instant::now() + SESSION_TTL };
+        SESSIONS.lock().unwrap().insert("csrf-test".into(), session);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, format!("{}=csrf-test", SESSION_COOKIE).parse().unwrap());
+        let (_, expected) = authenticate(&accounts, &headers).unwrap();
+        let expected = expected.expect("a session requires a CSRF token");
+        assert_eq!(expected, "abc123");
+        assert!(!csrf_matches(&headers, &expected));
+        headers.insert(CSRF_HEADER, "abc124".parse().unwrap());
+        assert!(!csrf_matches(&headers, &expected));
+        headers.insert(CSRF_HEADER, "abc12".parse().unwrap());
+        assert!(!csrf_matches(&headers, &expected));
+        headers.insert(CSRF_HEADER, "abc123".parse().unwrap());
+        assert!(csrf_matches(&headers, &expected));
+
+        // Token d'API: pas de token CSRF attendu, même avec un cookie de session
+        let mut headers = bearer(&token);
+        headers.insert(header::COOKIE, format!("{}=csrf-test", SESSION_COOKIE).parse().unwrap());
+        let (_, expected) = authenticate(&accounts, &headers).unwrap();
+        assert!(expected.is_none());
+
+        SESSIONS.lock().unwrap().remove("csrf-test");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,429 @@
+// =============================================================================
+// IDS LAB COMMANDER - Configuration du serveur
+// =============================================================================
+//
+// DESCRIPTION:
+// Chaque réglage est résolu par couches, la dernière l'emporte:
+//   1. valeurs par défaut
+//   2. fichier TOML (--config, COMMANDER_CONFIG, sinon ./commander.toml s'il existe)
+//   3. variables d'environnement
+//   4. options de la ligne de commande
+//
+// RÉGLAGES:
+//   clé TOML        variable         option           défaut
+//   project_root    PROJECT_ROOT     --project-root   ..  (lancé depuis commander/)
+//   listen_addr     LISTEN_ADDR      --listen         127.0.0.1
+//   port            LISTEN_PORT      --port           3000
+//   static_dir      STATIC_DIR       --static-dir     {project_root}/commander/static
+//   docker_socket   DOCKER_SOCKET    --docker-socket  /var/run/docker.sock
+//   users_file      USERS_FILE       --users-file     {project_root}/commander/users.toml
+//   labs_config     LABS_CONFIG      --labs-config    {project_root}/commander/labs.toml
+//   alerts_db       ALERTS_DB        --alerts-db      {project_root}/commander/data/commander.db
+//   alert_retention_days
+//                   ALERT_RETENTION_DAYS  --alert-retention-days  30
+//   alert_max_rows  ALERT_MAX_ROWS   --alert-max-rows 1000000
+//   nmap_bin        NMAP_BIN         --nmap-bin       nmap (cherché dans le PATH)
+//   reload_wait_secs
+//                   RELOAD_WAIT_SECS --reload-wait-secs  selon la méthode de rechargement du lab
+//   matrix_technique_timeout_secs
+//                   MATRIX_TECHNIQUE_TIMEOUT_SECS  --matrix-technique-timeout-secs  120
+//   matrix_settle_secs
+//                   MATRIX_SETTLE_SECS  --matrix-settle-secs  3
+//
+// SOUS-COMMANDES (sans démarrer le serveur):
+//   ids-commander preflight [--json]   vérifications de la machine hôte (preflight.rs)
+//   ids-commander add-user NAME --role ROLE [--lab LAB]...
+//                                      ajoute un compte à users_file (mot de passe lu sur stdin)
+//   ids-commander add-token USER [--name LABEL]
+//                                      crée un token d'API (affiché une seule fois)
+//
+// Par défaut le serveur n'écoute que sur la machine locale: il pilote
+// Docker, l'exposer au réseau (--listen 0.0.0.0) doit être un choix.
+// Sans users_file, l'authentification est désactivée (auth.rs) et seule
+// une adresse locale est acceptée.
+//
+// Chemins relatifs: project_root et docker_socket du fichier TOML partent
+// du répertoire du fichier; static_dir, users_file, labs_config et
+// alerts_db partent toujours de project_root, quelle que soit la couche.
+// Les chemins sont vérifiés au démarrage (voir `Config::resolve`): une
+// racine, un répertoire statique ou un labs.toml introuvable empêche le
+// démarrage, tout comme un socket Docker absent pour le serveur.
+//
+// =============================================================================
+
+use bollard::Docker;
+use clap::{Args, Parser, Subcommand};
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Délai des requêtes à l'API Docker (secondes).
+const DOCKER_TIMEOUT_SECS: u64 = 120;
+
+/// Options de la ligne de commande.
+#[derive(Debug, Parser)]
+#[command(name = "ids-commander", version, about = "IDS Lab Commander - Dashboard web pour piloter les laboratoires IDS")]
+pub struct Cli {
+    /// Fichier de configuration TOML
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub settings: Settings,
+
+    /// Sans sous-commande: démarre le serveur
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Sous-commandes de la ligne de commande.
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Vérifie la machine hôte (Docker, compose, outils, réseaux, ports) puis quitte
+    Preflight {
+        /// Résultat en JSON (format de GET /api/preflight)
+        #[arg(long)]
+        json: bool,
+    },
+    /// Ajoute un compte au fichier des utilisateurs (mot de passe lu sur stdin)
+    AddUser {
+        name: String,
+        /// viewer, student ou instructor
+        #[arg(long)]
+        role: String,
+        /// Lab autorisé à un étudiant (répétable; "*" = tous les labs)
+        #[arg(long = "lab", value_name = "LAB")]
+        labs: Vec<String>,
+    },
+    /// Crée un token d'API pour un compte existant
+    AddToken {
+        user: String,
+        /// Libellé du token (script, CI...)
+        #[arg(long, default_value = "api")]
+        name: String,
+    },
+}
+
+/// Une couche de configuration: chaque champ absent laisse la valeur
+/// de la couche précédente.
+#[derive(Debug, Default, Clone, Args, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// Racine du projet (répertoires *-lab, commander/)
+    #[arg(long, value_name = "DIR")]
+    pub project_root: Option<PathBuf>,
+
+    /// Adresse d'écoute du serveur HTTP
+    #[arg(long = "listen", value_name = "ADDR")]
+    pub listen_addr: Option<IpAddr>,
+
+    /// Port du serveur HTTP
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// Fichiers statiques du dashboard (CSS, JS)
+    #[arg(long, value_name = "DIR")]
+    pub static_dir: Option<PathBuf>,
+
+    /// Socket Unix du démon Docker
+    #[arg(long, value_name = "PATH")]
+    pub docker_socket: Option<PathBuf>,
+
+    /// Comptes et tokens d'API (absent: authentification désactivée)
+    #[arg(long, value_name = "FILE")]
+    pub users_file: Option<PathBuf>,
+
+    /// Registre des labs
+    #[arg(long, value_name = "FILE")]
+    pub labs_config: Option<PathBuf>,
+
+    /// Base SQLite (alertes, exécutions, historique des règles)
+    #[arg(long, value_name = "FILE")]
+    pub alerts_db: Option<PathBuf>,
+
+    /// Durée de conservation des alertes (jours)
+    #[arg(long, value_name = "DAYS")]
+    pub alert_retention_days: Option<u32>,
+
+    /// Nombre maximal d'alertes conservées
+    #[arg(long, value_name = "ROWS")]
+    pub alert_max_rows: Option<u64>,
+
+    /// Binaire nmap des attaques
+    #[arg(long, value_name = "PATH")]
+    pub nmap_bin: Option<PathBuf>,
+
+    /// Attente du rechargement de l'IDS après un changement de règles (secondes)
+    #[arg(long, value_name = "SECS")]
+    pub reload_wait_secs: Option<u64>,
+
+    /// Durée maximale d'une technique de la matrice (secondes)
+    #[arg(long, value_name = "SECS")]
+    pub matrix_technique_timeout_secs: Option<u64>,
+
+    /// Attente après une technique avant de compter les alertes (secondes)
+    #[arg(long, value_name = "SECS")]
+    pub matrix_settle_secs: Option<u64>,
+}
+
+impl Settings {
+    /// Superpose `over` à cette couche.
+    fn merge(self, over: Settings) -> Settings {
+        Settings {
+            project_root: over.project_root.or(self.project_root),
+            listen_addr: over.listen_addr.or(self.listen_addr),
+            port: over.port.or(self.port),
+            static_dir: over.static_dir.or(self.static_dir),
+            docker_socket: over.docker_socket.or(self.docker_socket),
+            users_file: over.users_file.or(self.users_file),
+            labs_config: over.labs_config.or(self.labs_config),
+            alerts_db: over.alerts_db.or(self.alerts_db),
+            alert_retention_days: over.alert_retention_days.or(self.alert_retention_days),
+            alert_max_rows: over.alert_max_rows.or(self.alert_max_rows),
+            nmap_bin: over.nmap_bin.or(self.nmap_bin),
+            reload_wait_secs: over.reload_wait_secs.or(self.reload_wait_secs),
+            matrix_technique_timeout_secs: over.matrix_technique_timeout_secs.or(self.matrix_technique_timeout_secs),
+            matrix_settle_secs: over.matrix_settle_secs.or(self.matrix_settle_secs),
+        }
+    }
+
+    /// Couche du fichier TOML; project_root et docker_socket relatifs partent
+    /// du répertoire du fichier (les autres chemins: de project_root).
+    fn from_file(path: &Path) -> Result<Settings, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let mut settings: Settings = toml::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        for p in [&mut settings.project_root, &mut settings.docker_socket].into_iter().flatten() {
+            if p.is_relative() {
+                *p = base.join(&*p);
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Couche des variables d'environnement (`var` lit une variable).
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Settings, String> {
+        fn parse<T: std::str::FromStr>(key: &str, value: Option<String>) -> Result<Option<T>, String> {
+            value
+                .map(|v| v.parse().map_err(|_| format!("Invalid {}: {:?}", key, v)))
+                .transpose()
+        }
+        Ok(Settings {
+            project_root: var("PROJECT_ROOT").map(PathBuf::from),
+            listen_addr: parse("LISTEN_ADDR", var("LISTEN_ADDR"))?,
+            port: parse("LISTEN_PORT", var("LISTEN_PORT"))?,
+            static_dir: var("STATIC_DIR").map(PathBuf::from),
+            docker_socket: var("DOCKER_SOCKET").map(PathBuf::from),
+            users_file: var("USERS_FILE").map(PathBuf::from),
+            labs_config: var("LABS_CONFIG").map(PathBuf::from),
+            alerts_db: var("ALERTS_DB").map(PathBuf::from),
+            alert_retention_days: parse("ALERT_RETENTION_DAYS", var("ALERT_RETENTION_DAYS"))?,
+            alert_max_rows: parse("ALERT_MAX_ROWS", var("ALERT_MAX_ROWS"))?,
+            nmap_bin: var("NMAP_BIN").map(PathBuf::from),
+            reload_wait_secs: parse("RELOAD_WAIT_SECS", var("RELOAD_WAIT_SECS"))?,
+            matrix_technique_timeout_secs: parse(
+                "MATRIX_TECHNIQUE_TIMEOUT_SECS",
+                var("MATRIX_TECHNIQUE_TIMEOUT_SECS"),
+            )?,
+            matrix_settle_secs: parse("MATRIX_SETTLE_SECS", var("MATRIX_SETTLE_SECS"))?,
+        })
+    }
+}
+
+/// Configuration résolue et validée.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub project_root: PathBuf,
+    pub listen: SocketAddr,
+    pub static_dir: PathBuf,
+    pub docker_socket: PathBuf,
+    pub users_file: PathBuf,
+    pub labs_config: PathBuf,
+    pub alerts_db: PathBuf,
+    pub alert_retention_days: u32,
+    pub alert_max_rows: u64,
+    pub nmap_bin: PathBuf,
+    /// Absent: délai par défaut de la méthode de rechargement du lab
+    pub reload_wait: Option<Duration>,
+    pub matrix_technique_timeout: Duration,
+    pub matrix_settle: Duration,
+}
+
+impl Config {
+    /// Résout les couches (défauts, fichier, environnement, CLI) puis valide.
+    pub fn resolve(cli: Cli) -> Result<Config, String> {
+        let env = |key: &str| std::env::var(key).ok();
+        let file = match cli.config.or_else(|| env("COMMANDER_CONFIG").map(PathBuf::from)) {
+            Some(path) => Settings::from_file(&path)?,
+            None if Path::new("commander.toml").is_file() => Settings::from_file(Path::new("commander.toml"))?,
+            None => Settings::default(),
+        };
+        let settings = file.merge(Settings::from_env(env)?).merge(cli.settings);
+        Config::from_settings(settings)
+    }
+
+    /// Complète avec les valeurs par défaut et vérifie les chemins.
+    fn from_settings(settings: Settings) -> Result<Config, String> {
+        let root = settings.project_root.unwrap_or_else(|| PathBuf::from(".."));
+        let project_root = root
+            .canonicalize()
+            .map_err(|e| format!("project_root {}: {} (use --project-root or PROJECT_ROOT)", root.display(), e))?;
+        if !project_root.is_dir() {
+            return Err(format!("project_root {} is not a directory", project_root.display()));
+        }
+
+        let static_dir = project_root.join(settings.static_dir.unwrap_or_else(|| PathBuf::from("commander/static")));
+        if !static_dir.is_dir() {
+            return Err(format!("static_dir {} is not a directory (use --static-dir or STATIC_DIR)", static_dir.display()));
+        }
+
+        let users_file = project_root.join(settings.users_file.unwrap_or_else(|| PathBuf::from("commander/users.toml")));
+
+        let labs_config = project_root.join(settings.labs_config.unwrap_or_else(|| PathBuf::from("commander/labs.toml")));
+        if !labs_config.is_file() {
+            return Err(format!("labs_config {} not found (use --labs-config or LABS_CONFIG)", labs_config.display()));
+        }
+
+        let alerts_db = project_root.join(settings.alerts_db.unwrap_or_else(|| PathBuf::from("commander/data/commander.db")));
+
+        Ok(Config {
+            listen: SocketAddr::new(
+                settings.listen_addr.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                settings.port.unwrap_or(3000),
+            ),
+            static_dir,
+            docker_socket: settings.docker_socket.unwrap_or_else(|| PathBuf::from("/var/run/docker.sock")),
+            users_file,
+            labs_config,
+            alerts_db,
+            alert_retention_days: settings.alert_retention_days.unwrap_or(30),
+            alert_max_rows: settings.alert_max_rows.unwrap_or(1_000_000),
+            nmap_bin: settings.nmap_bin.unwrap_or_else(|| PathBuf::from("nmap")),
+            reload_wait: settings.reload_wait_secs.map(Duration::from_secs),
+            matrix_technique_timeout: Duration::from_secs(settings.matrix_technique_timeout_secs.unwrap_or(120)),
+            matrix_settle: Duration::from_secs(settings.matrix_settle_secs.unwrap_or(3)),
+            project_root,
+        })
+    }
+
+    /// URL du dashboard affichée au démarrage.
+    pub fn url(&self) -> String {
+        if self.listen.ip().is_unspecified() {
+            format!("http://localhost:{}", self.listen.port())
+        } else {
+            format!("http://{}", self.listen)
+        }
+    }
+}
+
+/// Installe la configuration résolue au démarrage.
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// Configuration du serveur. Résolue par `init` au démarrage.
+pub fn get() -> &'static Config {
+    CONFIG.get().expect("configuration not initialized")
+}
+
+/// Client Docker sur le socket configuré.
+pub fn docker() -> Result<Docker, bollard::errors::Error> {
+    let socket = get().docker_socket.to_string_lossy();
+    Docker::connect_with_socket(&socket, DOCKER_TIMEOUT_SECS, bollard::API_DEFAULT_VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(root: Option<&str>, port: Option<u16>) -> Settings {
+        Settings { project_root: root.map(PathBuf::from), port, ..Default::default() }
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let dir = std::env::temp_dir().join(format!("ids-config-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("commander/static")).unwrap();
+        std::fs::write(dir.join("commander/labs.toml"), "").unwrap();
+        std::fs::write(
+            dir.join("commander.toml"),
+            "port = 4000\nlisten_addr = \"0.0.0.0\"\nproject_root = \".\"\nalert_retention_days = 7\nmatrix_settle_secs = 1\n",
+        )
+        .unwrap();
+
+        let file = Settings::from_file(&dir.join("commander.toml")).unwrap();
+        let env = Settings::from_env(|k| match k {
+            "LISTEN_PORT" => Some("5000".to_string()),
+            "ALERT_RETENTION_DAYS" => Some("14".to_string()),
+            "NMAP_BIN" => Some("/opt/nmap/bin/nmap".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let config = Config::from_settings(file.clone().merge(env.clone())).unwrap();
+        assert_eq!(config.listen, "0.0.0.0:5000".parse().unwrap());
+        assert_eq!(config.project_root, dir.canonicalize().unwrap());
+        assert_eq!(config.url(), "http://localhost:5000");
+        assert_eq!(config.alert_retention_days, 14);
+        assert_eq!(config.alert_max_rows, 1_000_000);
+        assert_eq!(config.nmap_bin, PathBuf::from("/opt/nmap/bin/nmap"));
+        assert_eq!(config.matrix_settle, Duration::from_secs(1));
+        assert_eq!(config.matrix_technique_timeout, Duration::from_secs(120));
+        assert_eq!(config.reload_wait, None);
+
+        let config = Config::from_settings(file.merge(env).merge(layer(None, Some(6000)))).unwrap();
+        assert_eq!(config.listen.port(), 6000);
+        assert_eq!(config.docker_socket, PathBuf::from("/var/run/docker.sock"));
+        assert_eq!(config.users_file, dir.canonicalize().unwrap().join("commander/users.toml"));
+        assert_eq!(config.labs_config, dir.canonicalize().unwrap().join("commander/labs.toml"));
+        assert_eq!(config.alerts_db, dir.canonicalize().unwrap().join("commander/data/commander.db"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shipped_config_paths_are_relative_to_project_root() {
+        // commander.toml livré, exemples décommentés, dans <tmp>/commander/
+        let dir = std::env::temp_dir().join(format!("ids-config-shipped-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("commander/static")).unwrap();
+        std::fs::write(dir.join("commander/labs.toml"), "").unwrap();
+        let shipped = std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("commander.toml")).unwrap();
+        let uncommented = ["static_dir", "users_file", "labs_config", "alerts_db"]
+            .iter()
+            .fold(shipped.clone(), |toml, key| toml.replace(&format!("# {} =", key), &format!("{} =", key)));
+        assert_ne!(uncommented, shipped);
+        std::fs::write(dir.join("commander/commander.toml"), uncommented).unwrap();
+
+        let config = Config::from_settings(Settings::from_file(&dir.join("commander/commander.toml")).unwrap()).unwrap();
+        let root = dir.canonicalize().unwrap();
+        assert_eq!(config.project_root, root);
+        assert_eq!(config.static_dir, root.join("commander/static"));
+        assert_eq!(config.users_file, root.join("commander/users.toml"));
+        assert_eq!(config.labs_config, root.join("commander/labs.toml"));
+        assert_eq!(config.alerts_db, root.join("commander/data/commander.db"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        assert!(Settings::from_env(|k| (k == "LISTEN_PORT").then(|| "http".to_string())).is_err());
+        assert!(Settings::from_env(|k| (k == "LISTEN_ADDR").then(|| "localhost".to_string())).is_err());
+        let err = Config::from_settings(layer(Some("/nonexistent/ids-lab"), None)).unwrap_err();
+        assert!(err.contains("project_root"));
+        assert!(Settings::from_env(|k| (k == "ALERT_RETENTION_DAYS").then(|| "a month".to_string())).is_err());
+        assert!(Settings::from_env(|k| (k == "MATRIX_SETTLE_SECS").then(|| "-1".to_string())).is_err());
+        let err = Config::from_settings(layer(Some("/"), None)).unwrap_err();
+        assert!(err.contains("static_dir"));
+
+        // Racine valide mais sans labs.toml: refusée au démarrage
+        let dir = std::env::temp_dir().join(format!("ids-config-nolabs-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("commander/static")).unwrap();
+        let err = Config::from_settings(layer(dir.to_str(), None)).unwrap_err();
+        assert!(err.contains("labs_config"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
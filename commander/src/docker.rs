@@ -0,0 +1,221 @@
+// =============================================================================
+// IDS LAB COMMANDER - État Docker (API bollard)
+// =============================================================================
+//
+// DESCRIPTION:
+// Containers et réseaux des labs lus directement sur l'API Docker (socket
+// configuré dans config.rs) au lieu d'analyser la sortie de `docker ps`,
+// `docker inspect` et `docker network ls`. Le binaire docker n'est plus
+// nécessaire au commander, sauf pour `docker compose` (start/stop des labs).
+//
+// Les containers sont rattachés à leur lab par les labels docker compose
+// (voir labs.rs): projet → lab, label ids-lab.role → rôle.
+//
+// Processus et logs d'un container servent aux sondes de readiness.rs.
+//
+// =============================================================================
+
+use crate::config;
+use crate::labs::{self, Role, SERVICE_LABEL};
+use bollard::container::{ListContainersOptions, LogOutput, LogsOptions, TopOptions};
+use bollard::network::ListNetworksOptions;
+use bollard::Docker;
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Adresse d'un container sur un réseau Docker.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkAddress {
+    pub network: String,
+    pub ip: String,
+}
+
+/// Container d'un lab (GET /api/status).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContainerInfo {
+    pub name: String,
+    pub id: String,
+    pub image: String,
+    /// Lab du registre (projet docker compose)
+    pub lab: String,
+    /// Service docker compose
+    pub service: Option<String>,
+    /// Rôle déclaré par le label ids-lab.role
+    pub role: Option<Role>,
+    /// created, running, exited...
+    pub state: String,
+    /// Texte de Docker: "Up 5 minutes (healthy)", "Exited (0) 2 hours ago"
+    pub status: String,
+    /// starting, healthy, unhealthy (None sans healthcheck)
+    pub health: Option<String>,
+    /// Code de sortie du dernier arrêt (None si le container tourne)
+    pub exit_code: Option<i64>,
+    /// Première IP, "-" si aucune (affichage du dashboard)
+    pub ip: String,
+    pub networks: Vec<NetworkAddress>,
+    pub labels: HashMap<String, String>,
+}
+
+impl ContainerInfo {
+    pub fn is_running(&self) -> bool {
+        self.state == "running"
+    }
+
+    pub fn short_id(&self) -> &str {
+        &self.id[..12.min(self.id.len())]
+    }
+}
+
+/// Réseau Docker (bridges des labs).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkInfo {
+    pub name: String,
+    pub id: String,
+    pub driver: String,
+    /// Sous-réseaux IPAM ("172.29.0.0/24")
+    pub subnets: Vec<String>,
+}
+
+/// Containers appartenant aux labs du registre (arrêtés compris).
+pub async fn lab_containers() -> Result<Vec<ContainerInfo>, bollard::errors::Error> {
+    let docker = config::docker()?;
+    let options = ListContainersOptions::<String> { all: true, ..Default::default() };
+    let summaries = docker.list_containers(Some(options)).await?;
+
+    let mut containers: Vec<ContainerInfo> = summaries
+        .into_iter()
+        .filter_map(|c| {
+            let name = c.names?.first()?.trim_start_matches('/').to_string();
+            let labels = c.labels.unwrap_or_default();
+            let lab = labs::registry().lab_of(&labels)?.name.clone();
+            let mut networks: Vec<NetworkAddress> = c
+                .network_settings
+                .and_then(|n| n.networks)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(network, endpoint)| {
+                    let ip = endpoint.ip_address.filter(|ip| !ip.is_empty())?;
+                    Some(NetworkAddress { network, ip })
+                })
+                .collect();
+            // Le réseau du lab avant le bridge par défaut de Docker
+            networks.sort_by_key(|n| (n.network == "bridge", n.network.clone()));
+            Some(ContainerInfo {
+                ip: networks.first().map_or_else(|| "-".to_string(), |n| n.ip.clone()),
+                name,
+                id: c.id.unwrap_or_default(),
+                image: c.image.unwrap_or_default(),
+                lab,
+                service: labels.get(SERVICE_LABEL).cloned(),
+                role: Role::from_labels(&labels),
+                state: c.state.unwrap_or_default(),
+                status: c.status.unwrap_or_default(),
+                health: None,
+                exit_code: None,
+                networks,
+                labels,
+            })
+        })
+        .collect();
+
+    // Santé et code de sortie ne sont donnés que par inspect
+    let inspections = futures_util::future::join_all(
+        containers.iter().map(|c| docker.inspect_container(&c.id, None)),
+    )
+    .await;
+    for (container, inspection) in containers.iter_mut().zip(inspections) {
+        let Some(state) = inspection.ok().and_then(|i| i.state) else { continue };
+        container.health = state
+            .health
+            .and_then(|h| h.status)
+            .map(|s| s.to_string())
+            .filter(|s| !s.is_empty() && s != "none");
+        if !container.is_running() {
+            container.exit_code = state.exit_code;
+        }
+    }
+
+    containers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(containers)
+}
+
+/// Lignes de commande des processus d'un container (`docker top`).
+pub async fn processes(container: &str) -> Result<Vec<String>, bollard::errors::Error> {
+    let docker = config::docker()?;
+    let top = docker.top_processes(container, None::<TopOptions<String>>).await?;
+    // La dernière colonne de `ps -ef` est la commande (CMD)
+    Ok(top
+        .processes
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|p| p.last().cloned())
+        .collect())
+}
+
+/// Sortie (stdout et stderr) d'un container depuis son dernier démarrage,
+/// limitée aux `tail` dernières lignes.
+pub async fn logs_since_start(container: &str, tail: usize) -> Result<String, bollard::errors::Error> {
+    let docker = config::docker()?;
+    let started = docker
+        .inspect_container(container, None)
+        .await?
+        .state
+        .and_then(|s| s.started_at)
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+        .map_or(0, |t| t.timestamp());
+    let options = LogsOptions::<String> {
+        stdout: true,
+        stderr: true,
+        since: started,
+        tail: tail.to_string(),
+        ..Default::default()
+    };
+    let mut stream = docker.logs(container, Some(options));
+    let mut output = String::new();
+    while let Some(chunk) = stream.next().await {
+        if let LogOutput::StdOut { message } | LogOutput::StdErr { message } | LogOutput::Console { message } = chunk? {
+            output.push_str(&String::from_utf8_lossy(&message));
+        }
+    }
+    Ok(output)
+}
+
+/// Réseaux bridge de Docker.
+pub async fn bridges() -> Result<Vec<NetworkInfo>, bollard::errors::Error> {
+    let docker = config::docker()?;
+    let networks = docker.list_networks(None::<ListNetworksOptions<String>>).await?;
+    let mut bridges: Vec<NetworkInfo> = networks
+        .into_iter()
+        .filter(|n| n.driver.as_deref() == Some("bridge"))
+        .map(|n| NetworkInfo {
+            name: n.name.unwrap_or_default(),
+            id: n.id.unwrap_or_default(),
+            driver: n.driver.unwrap_or_default(),
+            subnets: n
+                .ipam
+                .and_then(|i| i.config)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|c| c.subnet)
+                .collect(),
+        })
+        .collect();
+    bridges.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(bridges)
+}
+
+/// Vérifie que l'API Docker répond sur le socket configuré.
+/// Retourne: (ok, message) avec la version du démon ou l'erreur.
+pub async fn check_access() -> (bool, String) {
+    async fn version(docker: Docker) -> Result<String, bollard::errors::Error> {
+        Ok(docker.version().await?.version.unwrap_or_default())
+    }
+    match config::docker() {
+        Ok(docker) => match version(docker).await {
+            Ok(v) => (true, format!("Docker {} accessible sans sudo", v)),
+            Err(e) => (false, e.to_string()),
+        },
+        Err(e) => (false, e.to_string()),
+    }
+}
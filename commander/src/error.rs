@@ -0,0 +1,72 @@
+// =============================================================================
+// IDS LAB COMMANDER - Erreurs de l'API JSON
+// =============================================================================
+//
+// Toutes les routes /api/* renvoient leurs erreurs sous la même forme:
+//   { "error": "message lisible" }
+// avec le code HTTP correspondant (400, 401, 403, 404, 409, 500...).
+//
+// =============================================================================
+
+use crate::locks::LabLockState;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+/// Erreur renvoyée par un handler d'API.
+#[derive(Debug)]
+pub enum ApiError {
+    /// Paramètre invalide (400)
+    BadRequest(String),
+    /// Ni session ni token d'API valide (401)
+    Unauthorized(String),
+    /// Rôle ou lab non autorisé pour l'utilisateur (403)
+    Forbidden(String),
+    /// Ressource inconnue (404)
+    NotFound(String),
+    /// Action incompatible avec l'état courant (409)
+    Conflict(String),
+    /// Lab verrouillé par une autre opération (409, avec le détenteur du verrou)
+    Locked(LabLockState),
+    /// Erreur interne: base de données, Docker, système de fichiers (500)
+    Internal(String),
+}
+
+impl ApiError {
+    /// Message renvoyé au client (aussi enregistré par le journal d'audit).
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::BadRequest(m)
+            | ApiError::Unauthorized(m)
+            | ApiError::Forbidden(m)
+            | ApiError::NotFound(m)
+            | ApiError::Conflict(m)
+            | ApiError::Internal(m) => m.clone(),
+            ApiError::Locked(lock) => lock.message(),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ApiError {
+    fn from(e: rusqlite::Error) -> Self {
+        ApiError::Internal(format!("Database error: {}", e))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(m) => (StatusCode::BAD_REQUEST, m),
+            ApiError::Unauthorized(m) => (StatusCode::UNAUTHORIZED, m),
+            ApiError::Forbidden(m) => (StatusCode::FORBIDDEN, m),
+            ApiError::NotFound(m) => (StatusCode::NOT_FOUND, m),
+            ApiError::Conflict(m) => (StatusCode::CONFLICT, m),
+            ApiError::Locked(lock) => {
+                let body = serde_json::json!({ "error": lock.message(), "lock": lock });
+                return (StatusCode::CONFLICT, Json(body)).into_response();
+            }
+            ApiError::Internal(m) => (StatusCode::INTERNAL_SERVER_ERROR, m),
+        };
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
@@ -0,0 +1,157 @@
+// =============================================================================
+// IDS LAB COMMANDER - Chronologie des containers (événements Docker)
+// =============================================================================
+//
+// DESCRIPTION:
+// Le flux d'événements Docker suivi par snapshot.rs est aussi enregistré
+// (store.rs) pour les containers des labs: démarrage, arrêt (code de
+// sortie), signal reçu (SIGHUP du reloader Snort, USR2 de Suricata...),
+// santé et OOM. Une alerte manquante se corrèle ainsi avec un redémarrage
+// ou un crash de l'IDS.
+//
+// EXEMPLE:
+//   /api/events?lab=snort&action=die&since=2026-02-28T10:00:00Z
+//
+// =============================================================================
+
+use crate::labs::Registry;
+use bollard::models::{EventMessage, EventMessageTypeEnum};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Actions Docker enregistrées (les autres, exec_*, attach..., sont ignorées).
+const RECORDED_ACTIONS: &[&str] = &["start", "die", "kill", "health_status", "oom"];
+
+/// Taille de page par défaut et maximale.
+const DEFAULT_LIMIT: usize = 200;
+const MAX_LIMIT: usize = 1000;
+
+/// Événement d'un container de lab.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DockerEvent {
+    pub time: DateTime<Utc>,
+    pub container: String,
+    pub lab: String,
+    /// start, die, kill, health_status, oom
+    pub action: String,
+    /// "exit code 137", "signal 1 (SIGHUP)", "unhealthy"...
+    pub detail: Option<String>,
+}
+
+/// Événement accompagné de son identifiant d'enregistrement.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventRecord {
+    pub id: u64,
+    #[serde(flatten)]
+    pub event: DockerEvent,
+}
+
+/// Paramètres de GET /api/events.
+#[derive(Debug, Default, Deserialize)]
+pub struct EventQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub container: Option<String>,
+    pub lab: Option<String>,
+    pub action: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl EventQuery {
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+impl DockerEvent {
+    /// Convertit un message du flux Docker. None si l'événement ne concerne
+    /// pas un container d'un lab ou n'est pas une action enregistrée.
+    pub fn from_message(message: &EventMessage, registry: &Registry) -> Option<DockerEvent> {
+        if message.typ != Some(EventMessageTypeEnum::CONTAINER) {
+            return None;
+        }
+        // "health_status: healthy" → action health_status, détail healthy
+        let raw = message.action.as_deref()?;
+        let (action, suffix) = match raw.split_once(':') {
+            Some((action, rest)) => (action.trim(), Some(rest.trim().to_string())),
+            None => (raw, None),
+        };
+        if !RECORDED_ACTIONS.contains(&action) {
+            return None;
+        }
+
+        let attributes = message.actor.as_ref()?.attributes.as_ref()?;
+        let container = attributes.get("name")?.clone();
+        // Les attributs de l'événement reprennent les labels du container
+        let lab = registry.lab_of(attributes)?;
+
+        let detail = match action {
+            "die" => attributes.get("exitCode").map(|c| format!("exit code {}", c)),
+            "kill" => attributes.get("signal").map(|s| signal_label(s)),
+            _ => suffix.filter(|s| !s.is_empty()),
+        };
+        let time = match (message.time_nano, message.time) {
+            (Some(ns), _) => DateTime::from_timestamp_micros(ns / 1000)?,
+            (None, Some(s)) => DateTime::from_timestamp(s, 0)?,
+            _ => return None,
+        };
+
+        Some(DockerEvent { time, container, lab: lab.name.clone(), action: action.to_string(), detail })
+    }
+}
+
+/// "1" → "signal 1 (SIGHUP)". Docker donne le numéro du signal.
+fn signal_label(signal: &str) -> String {
+    let name = match signal {
+        "1" => "SIGHUP",
+        "2" => "SIGINT",
+        "9" => "SIGKILL",
+        "10" => "SIGUSR1",
+        "12" => "SIGUSR2",
+        "15" => "SIGTERM",
+        _ => return format!("signal {}", signal),
+    };
+    format!("signal {} ({})", signal, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labs::PROJECT_LABEL;
+    use bollard::models::EventActor;
+    use std::collections::HashMap;
+
+    fn message(action: &str, name: &str, attributes: &[(&str, &str)]) -> EventMessage {
+        let mut attrs = HashMap::from([("name".to_string(), name.to_string()), (PROJECT_LABEL.to_string(), "snort-lab".to_string())]);
+        attrs.extend(attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        EventMessage {
+            typ: Some(EventMessageTypeEnum::CONTAINER),
+            action: Some(action.into()),
+            actor: Some(EventActor { id: Some("abc".into()), attributes: Some(attrs) }),
+            time_nano: Some(1_772_273_880_123_456_789),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn converts_lab_container_events() {
+        let registry = Registry::parse(
+            "[[lab]]\nname = \"snort\"\ntitle = \"SNORT Lab\"\ncompose_dir = \"snort-lab\"\nsubnet = \"172.28.0.0/24\"",
+        )
+        .unwrap();
+
+        let kill = DockerEvent::from_message(&message("kill", "snort_ids", &[("signal", "1")]), &registry).unwrap();
+        assert_eq!(kill.lab, "snort");
+        assert_eq!(kill.detail.as_deref(), Some("signal 1 (SIGHUP)"));
+        assert_eq!(kill.time.timestamp_micros(), 1_772_273_880_123_456);
+
+        let die = DockerEvent::from_message(&message("die", "snort_ids", &[("exitCode", "137")]), &registry).unwrap();
+        assert_eq!(die.detail.as_deref(), Some("exit code 137"));
+
+        let health = DockerEvent::from_message(&message("health_status: unhealthy", "snort_ids", &[]), &registry).unwrap();
+        assert_eq!((health.action.as_str(), health.detail.as_deref()), ("health_status", Some("unhealthy")));
+
+        assert!(DockerEvent::from_message(&message("exec_start: ps", "snort_ids", &[]), &registry).is_none());
+        assert!(DockerEvent::from_message(&message("die", "snort_ids", &[(PROJECT_LABEL, "webapp")]), &registry).is_none());
+    }
+}
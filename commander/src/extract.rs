@@ -0,0 +1,191 @@
+// =============================================================================
+// IDS LAB COMMANDER - Extracteurs typés des paramètres de route
+// =============================================================================
+//
+// DESCRIPTION:
+// Les segments d'URL des actions du dashboard sont validés avant d'arriver
+// aux handlers, au lieu d'être passés tels quels à Docker ou au système de
+// fichiers:
+// - LabParam:        /reset-logs/:lab           lab du registre
+// - TemplateParams:  /apply/:lab/:level         lab + niveau 1-5 ou template
+//                                               personnalisé présent
+// - RuleVersionParams: /api/rules/:lab/history/:id,
+//                    /api/rules/:lab/rollback/:id  lab + ID numérique de version
+// - EngineParam:     /api/alerts/:ids           snort, suricata, zeek
+// - LabContainer:    /start|stop|restart/:id,   container d'un lab (nom, ID
+//                    /logs/:id                  complet ou préfixe ≥ 12)
+//
+// ERREURS (format JSON de error.rs, affichées en toast par le dashboard):
+// - 400: segment mal formé (niveau vide, hors 1-5, caractères interdits)
+// - 404: lab, template, moteur ou container inconnu
+//
+// Un container Docker sans rapport avec les labs n'est jamais accessible,
+// même si son ID est connu.
+//
+// =============================================================================
+
+use crate::alerts::Engine;
+use crate::docker::{self, ContainerInfo};
+use crate::error::ApiError;
+use crate::labs::{self, Lab};
+use crate::templates::{self, Level};
+use crate::{config, snapshot};
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Path};
+use axum::http::request::Parts;
+
+/// Longueur minimale d'un préfixe d'ID de container (ID court de Docker).
+const MIN_ID_PREFIX: usize = 12;
+
+/// Lit les segments de la route (400 si leur nombre ou leur forme ne convient pas).
+async fn segments<T, S>(parts: &mut Parts, state: &S) -> Result<T, ApiError>
+where
+    T: serde::de::DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    Path::<T>::from_request_parts(parts, state)
+        .await
+        .map(|Path(value)| value)
+        .map_err(|e| ApiError::BadRequest(e.body_text()))
+}
+
+fn find_lab(name: &str) -> Result<&'static Lab, ApiError> {
+    labs::registry().get(name).ok_or_else(|| ApiError::NotFound(format!("Unknown lab {:?}", name)))
+}
+
+/// Lab du registre désigné par le seul segment de la route.
+pub struct LabParam(pub &'static Lab);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for LabParam {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let name: String = segments(parts, state).await?;
+        find_lab(&name).map(LabParam)
+    }
+}
+
+/// Lab et niveau de POST /apply/:lab/:level, template vérifié sur disque.
+pub struct TemplateParams {
+    pub lab: &'static Lab,
+    pub level: Level,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for TemplateParams {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let (lab, level): (String, String) = segments(parts, state).await?;
+        let lab = find_lab(&lab)?;
+        let level = level.parse::<Level>().map_err(ApiError::BadRequest)?;
+        if lab.template_dir.is_none() {
+            return Err(ApiError::NotFound(format!("Lab {} has no rule templates", lab.name)));
+        }
+        if templates::find_template(&config::get().project_root, lab, &level).is_none() {
+            return Err(ApiError::NotFound(format!("No level {} template for {}", level, lab.name)));
+        }
+        Ok(TemplateParams { lab, level })
+    }
+}
+
+/// Lab et ID de version de /api/rules/:lab/history/:id et .../rollback/:id.
+/// L'existence de la version est vérifiée par le handler (store).
+pub struct RuleVersionParams {
+    pub lab: &'static Lab,
+    pub id: u64,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RuleVersionParams {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let (lab, id): (String, String) = segments(parts, state).await?;
+        let lab = find_lab(&lab)?;
+        let id = id.parse().map_err(|_| ApiError::BadRequest(format!("Invalid version id {:?}", id)))?;
+        Ok(RuleVersionParams { lab, id })
+    }
+}
+
+/// Moteur IDS désigné par le seul segment de la route.
+pub struct EngineParam(pub Engine);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for EngineParam {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let name: String = segments(parts, state).await?;
+        name.parse().map(EngineParam).map_err(ApiError::NotFound)
+    }
+}
+
+/// Container d'un lab désigné par son nom ou son ID.
+pub struct LabContainer(pub ContainerInfo);
+
+impl LabContainer {
+    /// Vrai si `id` désigne ce container: nom exact, ID complet ou préfixe.
+    fn matches(container: &ContainerInfo, id: &str) -> bool {
+        container.name == id || (id.len() >= MIN_ID_PREFIX && container.id.starts_with(id))
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for LabContainer {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let id: String = segments(parts, state).await?;
+        let valid = !id.is_empty()
+            && id.len() <= 128
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        if !valid {
+            return Err(ApiError::BadRequest(format!("Invalid container id {:?}", id)));
+        }
+
+        // Instantané d'abord, puis Docker pour un container créé depuis
+        if let Some(c) = snapshot::current().containers.iter().find(|c| Self::matches(c, &id)) {
+            return Ok(LabContainer(c.clone()));
+        }
+        let containers = docker::lab_containers()
+            .await
+            .map_err(|e| ApiError::Internal(format!("Docker error: {}", e)))?;
+        containers
+            .into_iter()
+            .find(|c| Self::matches(c, &id))
+            .map(LabContainer)
+            .ok_or_else(|| ApiError::NotFound(format!("No lab container {:?}", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn containers_match_by_name_or_id_prefix() {
+        let container = ContainerInfo {
+            name: "suricata_ids".into(),
+            id: "4f9c2d1e8a7b6c5d4e3f2a1b0c9d8e7f".into(),
+            image: "jasonish/suricata".into(),
+            lab: "suricata".into(),
+            service: None,
+            role: None,
+            state: "running".into(),
+            status: "Up 5 minutes".into(),
+            health: None,
+            exit_code: None,
+            ip: "-".into(),
+            networks: vec![],
+            labels: Default::default(),
+        };
+        assert!(LabContainer::matches(&container, "suricata_ids"));
+        assert!(LabContainer::matches(&container, container.short_id()));
+        assert!(LabContainer::matches(&container, &container.id));
+        // Préfixe trop court: ambigu, comme "docker start 4" sur un hôte chargé
+        assert!(!LabContainer::matches(&container, "4f9c"));
+        assert!(!LabContainer::matches(&container, "suricata"));
+    }
+}
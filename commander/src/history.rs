@@ -0,0 +1,134 @@
+// =============================================================================
+// IDS LAB COMMANDER - Historique des règles actives
+// =============================================================================
+//
+// DESCRIPTION:
+// Avant chaque remplacement du fichier de règles actif d'un lab (niveau
+// appliqué, matrice d'évasion, retour arrière), son contenu est conservé
+// dans la base (store.rs, table rule_versions) avec la date, le compte et
+// l'opération qui l'a remplacé: une retouche faite dans l'éditeur n'est
+// plus perdue au prochain clic sur un niveau.
+//
+// Deux versions successives identiques ne sont enregistrées qu'une fois.
+//
+// API:
+//   GET  /api/rules/:lab/history               versions, de la plus récente
+//   GET  /api/rules/:lab/history/:id           une version et son contenu
+//   GET  /api/rules/:lab/diff?from=4&to=7      diff unifié (défaut: dernière
+//                                              version -> fichier actuel)
+//   POST /api/rules/:lab/rollback/:id          remet une version en place
+//
+// "current" désigne le fichier de règles actif dans from/to.
+//
+// Les versions sont du texte: un fichier de règles qui n'est pas en UTF-8
+// valide est refusé (409) au lieu d'être conservé altéré, sans quoi un
+// retour arrière ne remettrait pas le contenu d'origine.
+//
+// =============================================================================
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::fmt;
+use std::str::FromStr;
+
+/// Une version enregistrée des règles actives d'un lab.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleVersion {
+    pub id: u64,
+    pub lab: String,
+    /// Date du remplacement
+    pub time: DateTime<Utc>,
+    /// Compte à l'origine du remplacement
+    pub actor: String,
+    /// Opération qui a remplacé ce contenu ("apply level 3", "rollback to #4"...)
+    pub reason: String,
+    pub sha256: String,
+    pub size: u64,
+    /// Contenu, seulement pour GET /api/rules/:lab/history/:id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// Côté d'un diff: une version enregistrée ou le fichier actuel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Version(u64),
+    Current,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Version(id) => write!(f, "#{}", id),
+            Side::Current => f.write_str("current"),
+        }
+    }
+}
+
+impl FromStr for Side {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "current" => Ok(Side::Current),
+            _ => s
+                .trim_start_matches('#')
+                .parse()
+                .map(Side::Version)
+                .map_err(|_| format!("Invalid version {:?} (expected an id or \"current\")", s)),
+        }
+    }
+}
+
+/// Paramètres de GET /api/rules/:lab/diff.
+#[derive(Debug, Default, Deserialize)]
+pub struct DiffQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Contenu d'un fichier de règles, refusé s'il n'est pas en UTF-8 valide.
+pub fn rules_text(name: &str, content: Vec<u8>) -> Result<String, String> {
+    String::from_utf8(content).map_err(|e| {
+        format!(
+            "{} is not valid UTF-8 (byte {}): fix the file before replacing it",
+            name,
+            e.utf8_error().valid_up_to()
+        )
+    })
+}
+
+/// Diff unifié (3 lignes de contexte) entre deux contenus de règles.
+/// Vide si les contenus sont identiques.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_name, new_name)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_sides_parse_and_render_unified_diffs() {
+        assert_eq!("4".parse::<Side>(), Ok(Side::Version(4)));
+        assert_eq!("#4".parse::<Side>(), Ok(Side::Version(4)));
+        assert_eq!("current".parse::<Side>(), Ok(Side::Current));
+        assert!("latest".parse::<Side>().is_err());
+
+        let old = "# Level 3\nalert tcp any any -> any any (sid:1;)\n";
+        let new = "# Level 3\nalert tcp any any -> any any (sid:1;)\nalert icmp any any -> any any (sid:2;)\n";
+        let diff = unified_diff(old, new, "suricata #4", "suricata current");
+        assert!(diff.starts_with("--- suricata #4\n+++ suricata current\n@@ -1,2 +1,3 @@\n"), "{}", diff);
+        assert!(diff.contains("\n+alert icmp any any -> any any (sid:2;)\n"));
+        assert_eq!(unified_diff(old, old, "a", "b"), "");
+
+        assert_eq!(rules_text("local.rules", b"# ok\n".to_vec()).as_deref(), Ok("# ok\n"));
+        let err = rules_text("local.rules", b"# caf\xe9\n".to_vec()).unwrap_err();
+        assert!(err.contains("local.rules is not valid UTF-8 (byte 5)"), "{}", err);
+    }
+}
@@ -0,0 +1,54 @@
+// =============================================================================
+// IDS LAB COMMANDER - Échappement HTML des pages générées
+// =============================================================================
+//
+// DESCRIPTION:
+// Le dashboard, la page de setup, les logs et les rapports sont générés
+// avec format!. Toute valeur qui ne vient pas du code (noms et IDs de
+// containers, sorties de commandes, logs, labs.toml, messages d'erreur)
+// passe par `escape` avant d'être insérée: un chemin HTTP comme
+// /<script>... journalisé par Zeek ne doit jamais s'exécuter dans le
+// navigateur de l'enseignant.
+//
+// Dans un attribut d'événement (onclick="..."), une valeur est d'abord
+// encodée en littéral JavaScript puis échappée (`js_attr`).
+//
+// =============================================================================
+
+/// Échappe un texte pour le contenu ou un attribut HTML.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Littéral JavaScript d'une chaîne, à placer dans un attribut HTML
+/// (onclick="submitJob({}, ...)").
+pub fn js_attr(text: &str) -> String {
+    escape(&serde_json::Value::from(text).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup_and_script_strings() {
+        assert_eq!(
+            escape(r#"GET /<script>alert("x")</script>?a=1&b='2'"#),
+            "GET /&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt;?a=1&amp;b=&#39;2&#39;"
+        );
+        assert_eq!(escape("snort-lab"), "snort-lab");
+        // Apostrophe et guillemets restent dans la chaîne JavaScript
+        assert_eq!(js_attr("Lab d'O\"Brien"), "&quot;Lab d&#39;O\\&quot;Brien&quot;");
+    }
+}
@@ -0,0 +1,254 @@
+// =============================================================================
+// IDS LAB COMMANDER - Jobs des opérations sur les labs
+// =============================================================================
+//
+// DESCRIPTION:
+// Démarrer, arrêter ou reconstruire un lab (docker compose) peut prendre
+// plusieurs minutes au premier build. Ces opérations sont soumises comme
+// jobs: la requête HTTP rend immédiatement le job, docker compose tourne en
+// tâche de fond et sa sortie est diffusée comme celle des attaques
+// (attack.rs, mêmes événements `line` / `exit`).
+//
+// ACTIONS:
+//   start    docker compose up -d --build --remove-orphans
+//   stop     docker compose down --remove-orphans
+//   rebuild  docker compose build --pull --no-cache
+//            puis docker compose up -d --force-recreate --remove-orphans
+//
+// API:
+//   POST /api/jobs {"lab": "snort", "action": "start"}
+//   POST /api/jobs/stop-all            un job stop par lab (mis en file si occupé)
+//   POST /lab/start/:lab, /lab/stop/:lab, /lab/stop-all
+//                                      anciennes routes, mêmes jobs
+//   GET  /api/jobs, /api/jobs/:id, /api/jobs/:id/output (SSE)
+//
+// Un job sur un lab occupé (locks.rs) est refusé, ou attend son tour avec
+// le statut queued si la requête contient "wait": true.
+//
+// Les jobs sont gardés en mémoire (les KEEP_FINISHED derniers terminés):
+// ils décrivent l'exécution en cours du serveur, pas un historique.
+//
+// =============================================================================
+
+use crate::attack::{self, Executions, OutputEvent};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::process::Command;
+
+/// Jobs terminés conservés pour GET /api/jobs.
+const KEEP_FINISHED: usize = 20;
+
+/// Opération docker compose sur un lab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobAction {
+    Start,
+    Stop,
+    Rebuild,
+}
+
+impl JobAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobAction::Start => "start",
+            JobAction::Stop => "stop",
+            JobAction::Rebuild => "rebuild",
+        }
+    }
+
+    /// Arguments de chaque étape `docker compose ...`.
+    fn steps(&self) -> &'static [&'static [&'static str]] {
+        match self {
+            JobAction::Start => &[&["compose", "up", "-d", "--build", "--remove-orphans"]],
+            JobAction::Stop => &[&["compose", "down", "--remove-orphans"]],
+            JobAction::Rebuild => &[
+                &["compose", "build", "--pull", "--no-cache"],
+                &["compose", "up", "-d", "--force-recreate", "--remove-orphans"],
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// En attente du verrou du lab
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// Opération sur un lab et son avancement.
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: u64,
+    pub lab: String,
+    pub action: JobAction,
+    pub status: JobStatus,
+    /// Étape en cours (1..=steps)
+    pub step: usize,
+    pub steps: usize,
+    /// Commande de l'étape en cours
+    pub command: String,
+    /// Cause de l'échec (code de retour et dernière ligne de docker compose)
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+/// Corps de POST /api/jobs.
+#[derive(Debug, Deserialize)]
+pub struct JobRequest {
+    pub lab: String,
+    pub action: JobAction,
+    /// Attendre la fin de l'opération en cours sur le lab au lieu d'un 409
+    #[serde(default)]
+    pub wait: bool,
+}
+
+/// Jobs en cours et récents, et leur sortie.
+#[derive(Clone, Default)]
+pub struct Jobs {
+    jobs: Arc<Mutex<BTreeMap<u64, Job>>>,
+    output: Executions,
+}
+
+impl Jobs {
+    /// Crée un job (running, ou queued en attente du verrou) et sa sortie.
+    pub fn create(&self, lab: &str, action: JobAction, queued: bool) -> Job {
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = jobs.keys().next_back().map_or(1, |id| id + 1);
+        let job = Job {
+            id,
+            lab: lab.to_string(),
+            action,
+            status: if queued { JobStatus::Queued } else { JobStatus::Running },
+            step: 1,
+            steps: action.steps().len(),
+            command: format!("docker {}", action.steps()[0].join(" ")),
+            error: None,
+            started_at: Utc::now(),
+            ended_at: None,
+        };
+        jobs.insert(id, job.clone());
+        self.output.register(id);
+
+        let mut finished: Vec<u64> = jobs
+            .values()
+            .filter(|j| !matches!(j.status, JobStatus::Queued | JobStatus::Running))
+            .map(|j| j.id)
+            .collect();
+        if finished.len() > KEEP_FINISHED {
+            finished.truncate(finished.len() - KEEP_FINISHED);
+            for id in finished {
+                jobs.remove(&id);
+            }
+        }
+        job
+    }
+
+    pub fn get(&self, id: u64) -> Option<Job> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Jobs du plus récent au plus ancien.
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().values().rev().cloned().collect()
+    }
+
+    /// Sortie du job (voir `Executions::subscribe`).
+    pub fn output(&self) -> &Executions {
+        &self.output
+    }
+
+    fn update(&self, id: u64, change: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            change(job);
+        }
+    }
+
+    /// Exécute les étapes docker compose du job dans `compose_dir`, jusqu'à
+    /// la première en échec. Retourne le code de retour de la dernière étape.
+    pub async fn run(&self, id: u64, action: JobAction, compose_dir: &Path) -> Option<i32> {
+        self.update(id, |job| job.status = JobStatus::Running);
+        let mut exit_code = Some(0);
+        for (i, args) in action.steps().iter().enumerate() {
+            let command_line = format!("docker {}", args.join(" "));
+            self.update(id, |job| {
+                job.step = i + 1;
+                job.command = command_line.clone();
+            });
+            self.output.push(id, OutputEvent::Line { text: format!("$ {}", command_line) });
+
+            let mut command = Command::new("docker");
+            command.args(*args).current_dir(compose_dir);
+            exit_code = attack::execute_command(&self.output, id, command).await;
+            if exit_code != Some(0) {
+                break;
+            }
+        }
+        exit_code
+    }
+
+    /// Pose le statut final puis émet l'événement `exit` de la sortie:
+    /// le navigateur relit alors un job terminé.
+    pub fn finish(&self, id: u64, exit_code: Option<i32>) {
+        let error = (exit_code != Some(0)).then(|| self.failure(id, exit_code));
+        self.update(id, |job| {
+            job.status = if error.is_none() { JobStatus::Succeeded } else { JobStatus::Failed };
+            job.error = error;
+            job.ended_at = Some(Utc::now());
+        });
+        self.output.finish(id, exit_code);
+    }
+
+    /// Message d'échec: code de retour et dernière ligne émise.
+    fn failure(&self, id: u64, exit_code: Option<i32>) -> String {
+        let last_line = self.output.subscribe(id).and_then(|(events, _)| {
+            events.into_iter().rev().find_map(|e| match e {
+                OutputEvent::Line { text } if !text.trim().is_empty() => Some(text),
+                _ => None,
+            })
+        });
+        let code = exit_code.map_or_else(|| "no exit code".to_string(), |c| format!("exit code {}", c));
+        match last_line {
+            Some(line) => format!("{}: {}", code, line),
+            None => code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn failed_step_sets_status_and_error() {
+        // Répertoire sans docker-compose.yml: docker compose échoue (ou docker est absent)
+        let dir = std::env::temp_dir().join(format!("ids-jobs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let jobs = Jobs::default();
+        let job = jobs.create("snort", JobAction::Rebuild, true);
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!((job.id, job.step, job.steps), (1, 1, 2));
+        let code = jobs.run(job.id, JobAction::Rebuild, &dir).await;
+        assert_ne!(code, Some(0));
+        jobs.finish(job.id, code);
+
+        let job = jobs.get(job.id).unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.step, 1);
+        assert!(job.error.is_some() && job.ended_at.is_some());
+        let (events, rx) = jobs.output().subscribe(job.id).unwrap();
+        assert!(rx.is_none());
+        assert!(matches!(events.first(), Some(OutputEvent::Line { text }) if text.starts_with("$ docker compose build")));
+        assert_eq!(jobs.create("zeek", JobAction::Stop, false).id, 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,367 @@
+// =============================================================================
+// IDS LAB COMMANDER - Registre des labs (labs.toml)
+// =============================================================================
+//
+// DESCRIPTION:
+// Les labs (noms, containers, IPs, chemins des règles, éditeurs...) sont
+// décrits dans commander/labs.toml au lieu d'être répétés en dur dans les
+// handlers. Le registre est chargé et validé une fois au démarrage, puis
+// consulté via `registry()` par les handlers et le dashboard.
+//
+// EMPLACEMENT:
+// Variable LABS_CONFIG, sinon commander/labs.toml sous la racine du projet.
+//
+// DÉCOUVERTE DES CONTAINERS:
+// Un container appartient à un lab par son label com.docker.compose.project
+// (posé par docker compose), jamais par son nom: un container "target" sans
+// rapport avec les labs n'apparaît pas dans le dashboard. Son rôle (ids,
+// target, editor, reloader, siem) vient du label ids-lab.role déclaré dans
+// les docker-compose.yml des labs.
+//
+// =============================================================================
+
+use crate::alerts::{Engine, LogFormat};
+use crate::tail::LogSource;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+/// Projet docker compose du container (posé par docker compose).
+pub const PROJECT_LABEL: &str = "com.docker.compose.project";
+/// Service docker compose du container.
+pub const SERVICE_LABEL: &str = "com.docker.compose.service";
+/// Rôle du container dans son lab (déclaré dans les docker-compose.yml).
+pub const ROLE_LABEL: &str = "ids-lab.role";
+
+/// Rôle d'un container dans son lab (label ids-lab.role).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Moteur IDS (Snort, Suricata, Zeek)
+    Ids,
+    /// Serveur cible des attaques
+    Target,
+    /// Éditeur de règles web (filebrowser)
+    Editor,
+    /// Recharge l'IDS quand les règles changent
+    Reloader,
+    /// Stockage et visualisation des alertes (Elasticsearch, Kibana, EveBox...)
+    Siem,
+}
+
+impl Role {
+    /// Rôle déclaré par les labels du container (None si absent ou inconnu).
+    pub fn from_labels(labels: &HashMap<String, String>) -> Option<Role> {
+        match labels.get(ROLE_LABEL)?.as_str() {
+            "ids" => Some(Role::Ids),
+            "target" => Some(Role::Target),
+            "editor" => Some(Role::Editor),
+            "reloader" => Some(Role::Reloader),
+            "siem" => Some(Role::Siem),
+            _ => None,
+        }
+    }
+}
+
+/// Rechargement de l'IDS par le container *_reloader après un changement de règles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReloadMethod {
+    Sighup,
+    Sigusr2,
+    Restart,
+}
+
+impl ReloadMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReloadMethod::Sighup => "SIGHUP",
+            ReloadMethod::Sigusr2 => "SIGUSR2",
+            ReloadMethod::Restart => "restart",
+        }
+    }
+
+    /// Délai laissé au reloader avant que les nouvelles règles soient actives.
+    pub fn default_delay(&self) -> Duration {
+        match self {
+            // Redémarrage complet du container (Zeek)
+            ReloadMethod::Restart => Duration::from_secs(20),
+            _ => Duration::from_secs(5),
+        }
+    }
+}
+
+/// Fichier d'alertes candidat d'un lab.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertLog {
+    pub path: PathBuf,
+    pub format: LogFormat,
+}
+
+/// Port publié sur l'hôte par un service du lab sans URL dans labs.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostPort {
+    pub port: u16,
+    pub service: String,
+}
+
+/// Un lab du registre (voir labs.toml pour la description des champs).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lab {
+    pub name: String,
+    pub title: String,
+    #[serde(default)]
+    pub icon: String,
+    pub engine: Option<Engine>,
+    pub compose_dir: PathBuf,
+    pub compose_project: Option<String>,
+    pub ids_container: Option<String>,
+    pub target_container: Option<String>,
+    pub target_ip: Option<IpAddr>,
+    pub subnet: String,
+    #[serde(default)]
+    pub ports: Vec<u16>,
+    pub description: Option<String>,
+    pub active_rules: Option<PathBuf>,
+    pub template_dir: Option<PathBuf>,
+    pub reload: Option<ReloadMethod>,
+    pub editor_url: Option<String>,
+    pub dashboard_url: Option<String>,
+    #[serde(default)]
+    pub host_ports: Vec<HostPort>,
+    pub logs_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub alert_logs: Vec<AlertLog>,
+    pub card_background: Option<String>,
+}
+
+impl Lab {
+    pub fn compose_dir(&self, root: &Path) -> PathBuf {
+        root.join(&self.compose_dir)
+    }
+
+    /// Fichier de règles actif (None pour un lab sans IDS).
+    pub fn rules_path(&self, root: &Path) -> Option<PathBuf> {
+        self.active_rules.as_ref().map(|p| root.join(p))
+    }
+
+    pub fn template_dir(&self, root: &Path) -> Option<PathBuf> {
+        self.template_dir.as_ref().map(|p| root.join(p))
+    }
+
+    pub fn logs_dir(&self, root: &Path) -> Option<PathBuf> {
+        self.logs_dir.as_ref().map(|p| root.join(p))
+    }
+
+    /// Nom du projet docker compose: compose_project, sinon le nom du
+    /// répertoire compose_dir (règle de docker compose).
+    pub fn compose_project(&self) -> String {
+        self.compose_project.clone().unwrap_or_else(|| {
+            self.compose_dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default()
+                .chars()
+                .filter(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-' || *c == '_')
+                .collect()
+        })
+    }
+
+    /// Ports de la cible au format nmap ("21,22,80,8080").
+    pub fn port_list(&self) -> String {
+        self.ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",")
+    }
+
+    /// Ports publiés sur l'hôte (vérifiés par preflight.rs): port explicite
+    /// de editor_url et dashboard_url, puis host_ports.
+    pub fn published_ports(&self) -> Vec<(u16, String)> {
+        [("rule editor", &self.editor_url), ("dashboard", &self.dashboard_url)]
+            .into_iter()
+            .filter_map(|(service, url)| Some((url_port(url.as_deref()?)?, service.to_string())))
+            .chain(self.host_ports.iter().map(|p| (p.port, p.service.clone())))
+            .collect()
+    }
+
+    /// Ligne d'information de la carte du dashboard.
+    pub fn card_description(&self) -> String {
+        self.description
+            .clone()
+            .unwrap_or_else(|| format!("Ports: {}", self.ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")))
+    }
+}
+
+/// Port explicite d'une URL ("http://localhost:8081/..." -> 8081).
+fn url_port(url: &str) -> Option<u16> {
+    let authority = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = authority.split(['/', '?', '#']).next()?;
+    host.rsplit_once(':')?.1.parse().ok()
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegistryFile {
+    lab: Vec<Lab>,
+}
+
+/// Ensemble des labs déclarés.
+#[derive(Debug)]
+pub struct Registry {
+    labs: Vec<Lab>,
+}
+
+impl Registry {
+    /// Lit et valide un fichier labs.toml.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Self::parse(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let file: RegistryFile = toml::from_str(content).map_err(|e| e.to_string())?;
+        let registry = Registry { labs: file.lab };
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.labs.is_empty() {
+            return Err("no [[lab]] declared".into());
+        }
+        for (i, lab) in self.labs.iter().enumerate() {
+            let valid_name = !lab.name.is_empty()
+                && lab.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+            if !valid_name {
+                return Err(format!("lab name {:?} must be lowercase letters, digits, '-' or '_'", lab.name));
+            }
+            if self.labs[..i].iter().any(|l| l.name == lab.name) {
+                return Err(format!("duplicate lab name {:?}", lab.name));
+            }
+            if self.labs[..i].iter().any(|l| l.compose_project() == lab.compose_project()) {
+                return Err(format!("lab {}: compose project {:?} already used", lab.name, lab.compose_project()));
+            }
+            let (ip, prefix) = lab.subnet.split_once('/').unwrap_or((&lab.subnet, ""));
+            if ip.parse::<IpAddr>().is_err() || prefix.parse::<u8>().map_or(true, |p| p > 128) {
+                return Err(format!("lab {}: invalid subnet {:?} (expected a.b.c.d/n)", lab.name, lab.subnet));
+            }
+            if lab.active_rules.is_some() != lab.template_dir.is_some() {
+                return Err(format!("lab {}: active_rules and template_dir go together", lab.name));
+            }
+            if lab.active_rules.is_some() && lab.engine.is_none() {
+                return Err(format!("lab {}: rule templates require an engine", lab.name));
+            }
+            if !lab.alert_logs.is_empty() && lab.engine.is_none() {
+                return Err(format!("lab {}: alert_logs require an engine", lab.name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn labs(&self) -> &[Lab] {
+        &self.labs
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Lab> {
+        self.labs.iter().find(|l| l.name == name)
+    }
+
+    /// Lab d'un container d'après ses labels docker compose.
+    pub fn lab_of(&self, labels: &HashMap<String, String>) -> Option<&Lab> {
+        let project = labels.get(PROJECT_LABEL)?;
+        self.labs.iter().find(|l| &l.compose_project() == project)
+    }
+
+    /// Premier lab utilisant un moteur IDS (alertes, runs).
+    pub fn for_engine(&self, engine: Engine) -> Option<&Lab> {
+        self.labs.iter().find(|l| l.engine == Some(engine))
+    }
+
+    /// Fichiers d'alertes à suivre (tail.rs), un jeu de candidats par lab.
+    pub fn log_sources(&self, root: &Path) -> Vec<LogSource> {
+        self.labs
+            .iter()
+            .filter(|lab| lab.engine.is_some() && !lab.alert_logs.is_empty())
+            .map(|lab| LogSource {
+                lab: lab.name.clone(),
+                candidates: lab.alert_logs.iter().map(|l| (root.join(&l.path), l.format)).collect(),
+            })
+            .collect()
+    }
+}
+
+/// Installe le registre chargé au démarrage.
+pub fn init(registry: Registry) {
+    let _ = REGISTRY.set(registry);
+}
+
+/// Registre des labs. Chargé par `init` au démarrage du serveur.
+pub fn registry() -> &'static Registry {
+    REGISTRY.get().expect("lab registry not initialized")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_registry_is_valid() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("labs.toml");
+        let registry = Registry::load(&path).unwrap();
+        let names: Vec<_> = registry.labs().iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["snort", "suricata", "zeek", "kibana"]);
+
+        let suricata = registry.for_engine(Engine::Suricata).unwrap();
+        assert_eq!(suricata.target_ip, Some("172.29.0.100".parse().unwrap()));
+        assert_eq!(suricata.port_list(), "21,22,80,8080");
+        assert_eq!(suricata.compose_project(), "suricata-lab");
+        assert_eq!(registry.log_sources(Path::new("/p")).len(), 3);
+        let ports = |name: &str| -> Vec<u16> {
+            registry.get(name).unwrap().published_ports().iter().map(|(p, _)| *p).collect()
+        };
+        assert_eq!(ports("suricata"), [8082, 5636]);
+        assert_eq!(ports("kibana"), [5601, 9200]);
+        assert_eq!(url_port("http://localhost"), None);
+    }
+
+    #[test]
+    fn rejects_inconsistent_labs() {
+        let lab = |extra: &str| {
+            format!(
+                "[[lab]]\nname = \"x\"\ntitle = \"X\"\ncompose_dir = \"x-lab\"\nsubnet = \"10.0.0.0/24\"\n{}",
+                extra
+            )
+        };
+        assert!(Registry::parse(&lab("")).is_ok());
+        assert!(Registry::parse(&lab("active_rules = \"r\"\ntemplate_dir = \"t\"")).unwrap_err().contains("engine"));
+        assert!(Registry::parse(&lab("active_rules = \"r\"")).is_err());
+        assert!(Registry::parse(&lab("colour = \"red\"")).is_err());
+        assert!(Registry::parse(&format!("{}\n{}", lab(""), lab(""))).unwrap_err().contains("duplicate"));
+        let other = lab("").replace("name = \"x\"", "name = \"y\"");
+        assert!(Registry::parse(&format!("{}\n{}", lab(""), other)).unwrap_err().contains("compose project"));
+        assert!(Registry::parse(&lab("").replace("10.0.0.0/24", "10.0.0.0")).is_err());
+    }
+
+    #[test]
+    fn containers_are_matched_by_compose_labels() {
+        let registry = Registry::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("labs.toml")).unwrap();
+        let labels = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+
+        let ids = labels(&[(PROJECT_LABEL, "snort-lab"), (SERVICE_LABEL, "snort-engine"), (ROLE_LABEL, "ids")]);
+        assert_eq!(registry.lab_of(&ids).map(|l| l.name.as_str()), Some("snort"));
+        assert_eq!(Role::from_labels(&ids), Some(Role::Ids));
+
+        // Un container nommé "target" hors des projets des labs est ignoré
+        assert!(registry.lab_of(&labels(&[(PROJECT_LABEL, "webapp"), (ROLE_LABEL, "target")])).is_none());
+        assert!(registry.lab_of(&labels(&[])).is_none());
+        assert_eq!(Role::from_labels(&labels(&[(ROLE_LABEL, "database")])), None);
+    }
+}
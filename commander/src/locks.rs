@@ -0,0 +1,203 @@
+// =============================================================================
+// IDS LAB COMMANDER - Verrous des labs
+// =============================================================================
+//
+// DESCRIPTION:
+// Une seule opération à la fois par lab: deux onglets (ou deux étudiants)
+// ne peuvent plus lancer `start` et `stop` du même lab en même temps, ni
+// arrêter un lab au milieu d'une matrice d'évasion.
+//
+// OPÉRATIONS VERROUILLÉES:
+// - jobs docker compose (start, stop, rebuild)
+// - matrice d'évasion (pendant toute sa durée)
+// - application d'un niveau de règles, reset des logs
+// - start/stop/restart d'un container du lab
+// - attaque nmap (POST /api/attacks, jusqu'à la fin du scan)
+//
+// CONFLIT:
+// Une opération sur un lab occupé est refusée (409, avec le détenteur du
+// verrou et la file d'attente), ou mise en file d'attente si elle le
+// demande (jobs avec "wait": true). La file est servie dans l'ordre
+// d'arrivée, avant toute nouvelle demande.
+//
+// =============================================================================
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+/// Opération qui détient (ou attend) le verrou d'un lab.
+#[derive(Debug, Clone, Serialize)]
+pub struct LockHolder {
+    /// "job #3 start", "matrix #2", "apply level 4"...
+    pub operation: String,
+    /// Client à l'origine de l'opération (adresse IP)
+    pub client: String,
+    pub since: DateTime<Utc>,
+}
+
+/// État du verrou d'un lab (GET /api/locks, corps des réponses 409).
+#[derive(Debug, Clone, Serialize)]
+pub struct LabLockState {
+    pub lab: String,
+    pub holder: Option<LockHolder>,
+    pub queue: Vec<LockHolder>,
+}
+
+impl LabLockState {
+    /// Message des réponses 409.
+    pub fn message(&self) -> String {
+        match &self.holder {
+            Some(h) => format!(
+                "Lab {} is busy: {} (by {} since {})",
+                self.lab,
+                h.operation,
+                h.client,
+                h.since.format("%H:%M:%S")
+            ),
+            None => format!("Lab {} is busy: {} operation(s) queued", self.lab, self.queue.len()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct LabLock {
+    /// File FIFO de tokio: les attentes sont servies dans l'ordre
+    mutex: Arc<tokio::sync::Mutex<()>>,
+    holder: Option<LockHolder>,
+    queue: Vec<(u64, LockHolder)>,
+}
+
+/// Verrous de tous les labs, créés à la première opération.
+#[derive(Clone, Default)]
+pub struct LabLocks {
+    labs: Arc<Mutex<BTreeMap<String, LabLock>>>,
+    tickets: Arc<AtomicU64>,
+}
+
+/// Verrou détenu; relâché (et le détenteur effacé) à sa destruction.
+pub struct LabGuard {
+    lab: String,
+    locks: LabLocks,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl LabGuard {
+    /// Précise l'opération une fois son identifiant connu (job, matrice).
+    pub fn set_operation(&self, operation: String) {
+        if let Some(holder) = self.locks.labs.lock().unwrap().get_mut(&self.lab).and_then(|l| l.holder.as_mut()) {
+            holder.operation = operation;
+        }
+    }
+}
+
+impl Drop for LabGuard {
+    fn drop(&mut self) {
+        // Effacé avant de relâcher le mutex: le suivant pose son propre détenteur
+        if let Some(lock) = self.locks.labs.lock().unwrap().get_mut(&self.lab) {
+            lock.holder = None;
+        }
+    }
+}
+
+impl LabLocks {
+    /// Prend le verrou si le lab est libre et sans file d'attente.
+    pub fn try_acquire(&self, lab: &str, operation: &str, client: &str) -> Result<LabGuard, LabLockState> {
+        let mut labs = self.labs.lock().unwrap();
+        let lock = labs.entry(lab.to_string()).or_default();
+        let guard = match lock.queue.is_empty() {
+            true => lock.mutex.clone().try_lock_owned().ok(),
+            false => None,
+        };
+        match guard {
+            Some(guard) => {
+                lock.holder = Some(holder(operation, client));
+                Ok(LabGuard { lab: lab.to_string(), locks: self.clone(), _guard: guard })
+            }
+            None => Err(state(lab, lock)),
+        }
+    }
+
+    /// Attend le verrou derrière les opérations déjà en file.
+    pub async fn acquire(&self, lab: &str, operation: &str, client: &str) -> LabGuard {
+        let ticket = self.tickets.fetch_add(1, Ordering::SeqCst);
+        let mutex = {
+            let mut labs = self.labs.lock().unwrap();
+            let lock = labs.entry(lab.to_string()).or_default();
+            lock.queue.push((ticket, holder(operation, client)));
+            lock.mutex.clone()
+        };
+        let guard = mutex.lock_owned().await;
+
+        let mut labs = self.labs.lock().unwrap();
+        let lock = labs.entry(lab.to_string()).or_default();
+        if let Some(pos) = lock.queue.iter().position(|(t, _)| *t == ticket) {
+            let (_, mut holder) = lock.queue.remove(pos);
+            holder.since = Utc::now();
+            lock.holder = Some(holder);
+        }
+        LabGuard { lab: lab.to_string(), locks: self.clone(), _guard: guard }
+    }
+
+    /// Labs verrouillés ou avec une file d'attente.
+    pub fn states(&self) -> Vec<LabLockState> {
+        self.labs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, lock)| lock.holder.is_some() || !lock.queue.is_empty())
+            .map(|(lab, lock)| state(lab, lock))
+            .collect()
+    }
+}
+
+fn holder(operation: &str, client: &str) -> LockHolder {
+    LockHolder { operation: operation.to_string(), client: client.to_string(), since: Utc::now() }
+}
+
+fn state(lab: &str, lock: &LabLock) -> LabLockState {
+    LabLockState {
+        lab: lab.to_string(),
+        holder: lock.holder.clone(),
+        queue: lock.queue.iter().map(|(_, h)| h.clone()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn conflicting_operations_are_rejected_or_queued_in_order() {
+        let locks = LabLocks::default();
+        let guard = locks.try_acquire("suricata", "job #1 start", "10.0.0.1").unwrap();
+        guard.set_operation("job #1 start (build)".into());
+
+        let busy = locks.try_acquire("suricata", "job #2 stop", "10.0.0.2").err().unwrap();
+        assert_eq!(busy.holder.unwrap().operation, "job #1 start (build)");
+        assert!(locks.try_acquire("snort", "apply level 3", "10.0.0.2").is_ok());
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for (i, op) in ["matrix #1", "job #3 stop"].into_iter().enumerate() {
+            let (waiter, tx) = (locks.clone(), tx.clone());
+            tokio::spawn(async move {
+                let _guard = waiter.acquire("suricata", op, "10.0.0.3").await;
+                tx.send(op).unwrap();
+            });
+            // Laisser la tâche entrer dans la file avant la suivante
+            while locks.states().iter().find(|s| s.lab == "suricata").unwrap().queue.len() <= i {
+                tokio::task::yield_now().await;
+            }
+        }
+        // La file passe avant une nouvelle demande
+        drop(guard);
+        assert!(locks.try_acquire("suricata", "job #4 start", "10.0.0.1").is_err());
+        assert_eq!(rx.recv().await, Some("matrix #1"));
+        assert_eq!(rx.recv().await, Some("job #3 stop"));
+        assert!(locks.try_acquire("suricata", "job #5 start", "10.0.0.1").is_ok());
+        assert!(locks.states().is_empty());
+    }
+}
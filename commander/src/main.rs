@@ -27,34 +27,236 @@
 //
 // =============================================================================
 
+mod alerts;
+mod attack;
+mod audit;
+mod auth;
+mod config;
+mod docker;
+mod error;
+mod events;
+mod extract;
+mod history;
+mod html;
+mod jobs;
+mod labs;
+mod locks;
+mod matrix;
+mod preflight;
+mod query;
+mod readiness;
+mod report;
+mod runs;
+mod snapshot;
+mod store;
+mod tail;
+mod templates;
+
+use alerts::Engine;
+use attack::{AttackRequest, Executions, OutputEvent, Technique};
+use audit::{AuditEntry, AuditQuery, AuditRecord};
+use auth::{Accounts, CsrfToken, Identity};
 use axum::{
-    extract::Path,
-    response::Html,
+    extract::{rejection::{JsonRejection, QueryRejection}, ConnectInfo, Form, Path, Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
     routing::{get, post},
-    Router,
+    Extension, Json, Router,
 };
+use clap::Parser;
+use config::{Cli, Config};
+use docker::ContainerInfo;
+use error::ApiError;
+use events::{EventQuery, EventRecord};
+use extract::{EngineParam, LabContainer, LabParam, RuleVersionParams, TemplateParams};
+use history::{DiffQuery, RuleVersion, Side};
+use html::{escape, js_attr};
+use jobs::{Job, JobAction, JobRequest, Jobs};
+use labs::{Lab, Registry, Role};
+use locks::{LabGuard, LabLockState, LabLocks};
+use matrix::{Matrix, MatrixCell, MatrixRequest, MatrixStatus, MatrixTiming};
+use query::{AlertPage, AlertQuery, AlertRecord};
+use report::{ReportFormat, ReportQuery};
+use runs::{Run, RunStart, RunSummary};
 use bollard::container::{LogsOptions, StartContainerOptions, StopContainerOptions};
-use bollard::Docker;
-use std::collections::HashMap;
+use std::convert::Infallible;
 use std::fs;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::process::Command;
-use futures_util::StreamExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use futures_util::{Stream, StreamExt};
+use store::{AlertStore, Retention};
+use tail::AlertTailer;
+use tokio::sync::broadcast;
 use tower_http::services::ServeDir;
 
-/// Retourne le chemin racine du projet.
-/// Peut être surchargé via la variable d'environnement PROJECT_ROOT.
+/// Retourne le chemin racine du projet (project_root, voir config.rs).
 fn get_project_root() -> PathBuf {
-    std::env::var("PROJECT_ROOT")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| PathBuf::from("/home/rvv/Bureau/Rust.Nmap.Network"))
+    config::get().project_root.clone()
+}
+
+/// État partagé entre les handlers Axum.
+#[derive(Clone)]
+struct AppState {
+    /// Lecteur des fichiers d'alertes (offsets mémorisés entre deux lectures)
+    tailer: Arc<Mutex<AlertTailer>>,
+    /// Historique persistant des alertes (SQLite)
+    store: Arc<AlertStore>,
+    /// Diffusion des nouvelles alertes aux clients SSE (/api/alerts/stream)
+    alerts_tx: broadcast::Sender<AlertRecord>,
+    /// Sortie des attaques lancées depuis le dashboard (attack.rs)
+    executions: Executions,
+    /// Vrai pendant un job de matrice d'évasion (un seul à la fois)
+    matrix_busy: Arc<AtomicBool>,
+    /// Opérations docker compose sur les labs (jobs.rs)
+    jobs: Jobs,
+    /// Une opération à la fois par lab (locks.rs)
+    locks: LabLocks,
 }
 
 /// Point d'entrée principal de l'application.
-/// Configure les routes et démarre le serveur HTTP sur le port 3000.
+/// Configure les routes et démarre le serveur HTTP (127.0.0.1:3000 par défaut).
 #[tokio::main]
 async fn main() {
-    let static_dir = get_project_root().join("commander/static");
+    // Configuration: défauts, fichier TOML, environnement puis options
+    let mut cli = Cli::parse();
+    let command = cli.command.take();
+    // preflight --json: stdout réservé au JSON
+    let quiet = matches!(command, Some(config::Command::Preflight { json: true }));
+    match Config::resolve(cli) {
+        Ok(config) => config::init(config),
+        Err(e) => {
+            eprintln!("Configuration error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    let config = config::get();
+    if !quiet {
+        println!("✓ Project root: {:?}", config.project_root);
+    }
+    // Socket Docker: requis par le serveur; preflight le diagnostique,
+    // add-user et add-token n'en ont pas besoin
+    if !config.docker_socket.exists() {
+        match command {
+            None => {
+                eprintln!(
+                    "Docker socket {:?} not found: start Docker or use --docker-socket / DOCKER_SOCKET",
+                    config.docker_socket
+                );
+                std::process::exit(1);
+            }
+            Some(config::Command::Preflight { .. }) if !quiet => {
+                eprintln!("⚠ Docker socket {:?} not found", config.docker_socket);
+            }
+            _ => {}
+        }
+    }
+
+    // Registre des labs: un labs.toml invalide empêche le démarrage
+    match Registry::load(&config.labs_config) {
+        Ok(registry) => {
+            if !quiet {
+                println!("✓ {} labs loaded from {:?}", registry.labs().len(), config.labs_config);
+            }
+            labs::init(registry);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Sous-commandes: exécutées sans démarrer le serveur
+    match command {
+        Some(config::Command::Preflight { json }) => {
+            let report = preflight::run().await;
+            match json {
+                true => println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default()),
+                false => report.print(),
+            }
+            std::process::exit(if report.status == preflight::Status::Fail { 1 } else { 0 });
+        }
+        Some(config::Command::AddUser { name, role, labs }) => {
+            let result = auth::read_password()
+                .and_then(|password| auth::add_user(&config.users_file, labs::registry(), &name, &role, &labs, &password));
+            match result {
+                Ok(()) => println!("✓ User {} ({}) added to {:?}", name, role, config.users_file),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(config::Command::AddToken { user, name }) => {
+            match auth::add_token(&config.users_file, labs::registry(), &user, &name) {
+                Ok(token) => println!("✓ Token {} created for {} (shown only once):\n{}", name, user, token),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        None => {}
+    }
+
+    // Comptes du dashboard: sans users_file, pas d'authentification
+    match Accounts::load(&config.users_file, labs::registry()) {
+        Ok(accounts) => {
+            match &accounts {
+                Some(_) => println!("✓ Authentication enabled ({:?})", config.users_file),
+                None => println!("⚠ No {:?}: authentication disabled", config.users_file),
+            }
+            auth::init(accounts);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    if !auth::enabled() && !config.listen.ip().is_loopback() {
+        eprintln!(
+            "Refusing to listen on {} without accounts: create {:?} with `ids-commander add-user`",
+            config.listen, config.users_file
+        );
+        std::process::exit(1);
+    }
+
+    let store = match AlertStore::open(&config.alerts_db) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Cannot open alert database {:?}: {}", config.alerts_db, e);
+            std::process::exit(1);
+        }
+    };
+
+    // Reprendre la lecture des logs là où l'exécution précédente s'était arrêtée
+    let mut tailer = AlertTailer::new(labs::registry().log_sources(&get_project_root()));
+    tailer.restore(store.positions().unwrap_or_default());
+    if let Ok(n @ 1..) = store.fail_interrupted_matrices() {
+        println!("⚠ {} evasion matrix job(s) interrupted by the last shutdown", n);
+    }
+
+    let state = AppState {
+        tailer: Arc::new(Mutex::new(tailer)),
+        store: Arc::new(store),
+        alerts_tx: broadcast::channel(1024).0,
+        executions: Executions::default(),
+        matrix_busy: Arc::new(AtomicBool::new(false)),
+        jobs: Jobs::default(),
+        locks: LabLocks::default(),
+    };
+    // État Docker tenu à jour en tâche de fond (snapshot.rs)
+    snapshot::init();
+    snapshot::refresh().await;
+    tokio::spawn(snapshot::run_poller(state.store.clone()));
+    tokio::spawn(readiness::run_prober());
+    tokio::spawn(ingest_alerts(state.clone()));
+    tokio::spawn(purge_alerts(state.store.clone(), Retention::from_config(config)));
 
     // Configuration des routes Axum
     // Chaque route correspond à une action ou une page
@@ -62,6 +264,8 @@ async fn main() {
         // Pages HTML
         .route("/", get(dashboard))                      // Dashboard principal
         .route("/setup", get(setup_page))                // Guide d'installation
+        .route("/login", get(login_page).post(login))    // Connexion (auth.rs)
+        .route("/logout", post(logout))                  // Déconnexion
 
         // Actions sur les containers (appelées via HTMX)
         .route("/start/:id", post(start_container))     // Démarrer un container
@@ -69,67 +273,81 @@ async fn main() {
         .route("/restart/:id", post(restart_container)) // Redémarrer un container
 
         // Gestion des templates de règles
-        .route("/apply/:ids/:level", post(apply_template)) // Appliquer niveau 1-5
+        .route("/apply/:lab/:level", post(apply_template)) // Appliquer niveau 1-5
+        .route("/api/rules/:lab/history", get(api_rules_history)) // Versions des règles actives
+        .route("/api/rules/:lab/history/:id", get(api_rules_version)) // Contenu d'une version
+        .route("/api/rules/:lab/diff", get(api_rules_diff)) // Diff unifié entre versions
+        .route("/api/rules/:lab/rollback/:id", post(api_rules_rollback)) // Remettre une version
 
         // Logs et diagnostics
         .route("/logs/:id", get(get_logs))              // Voir les logs d'un container
-        .route("/reset-logs/:ids", post(reset_logs))    // Réinitialiser les logs
+        .route("/reset-logs/:lab", post(reset_logs))    // Réinitialiser les logs
         .route("/system-info", get(system_info))         // Infos système
         .route("/api/status", get(api_status))           // Status JSON pour polling
+        .route("/api/alerts", get(api_alerts_search))    // Recherche d'alertes (tous IDS)
+        .route("/api/alerts/stream", get(api_alerts_stream)) // Alertes en direct (SSE)
         .route("/api/alerts/:ids", get(api_alerts))      // Alertes IDS en JSON
         .route("/api/health", get(api_health))           // Health check pour tests
-
-        // Gestion des labs complets (docker compose up/down)
-        .route("/lab/start/:lab", post(start_lab))      // Démarrer snort/suricata/zeek
-        .route("/lab/stop/:lab", post(stop_lab))        // Arrêter un lab
-        .route("/lab/stop-all", post(stop_all_labs))    // Arrêter TOUS les labs
+        .route("/api/preflight", get(api_preflight))     // Vérifications de la machine hôte
+        .route("/api/events", get(api_events))           // Chronologie des containers
+
+        // Sessions d'attaque et alertes associées
+        .route("/api/runs", get(api_runs).post(api_run_start)) // Lister / démarrer un run
+        .route("/api/runs/:id", get(api_run))            // Run + alertes par SID
+        .route("/api/runs/:id/stop", post(api_run_stop)) // Clore un run
+        .route("/api/runs/:id/alerts", get(api_run_alerts)) // Alertes du run
+        .route("/api/runs/:id/output", get(api_run_output)) // Sortie nmap en direct (SSE)
+        .route("/api/attacks", get(api_attacks).post(api_attack_start)) // Techniques / lancer une attaque
+        .route("/api/matrix", get(api_matrices).post(api_matrix_start)) // Matrices d'évasion
+        .route("/api/matrix/:id", get(api_matrix))       // Détail d'une matrice
+        .route("/api/report/:format", get(api_report))   // Rapport html/md/json/csv
+
+        // Gestion des labs complets (docker compose en tâche de fond)
+        .route("/api/jobs", get(api_jobs).post(api_job_start)) // Lister / lancer start|stop|rebuild
+        .route("/api/jobs/stop-all", post(api_jobs_stop_all)) // Un job stop par lab
+        .route("/lab/start/:lab", post(start_lab))      // Anciennes routes: job start / stop
+        .route("/lab/stop/:lab", post(stop_lab))
+        .route("/lab/stop-all", post(api_jobs_stop_all))
+        .route("/api/jobs/:id", get(api_job))            // Statut d'un job
+        .route("/api/jobs/:id/output", get(api_job_output)) // Sortie docker compose (SSE)
+        .route("/api/locks", get(api_locks))             // Labs occupés et files d'attente
+        .route("/api/me", get(api_me))                   // Utilisateur connecté et rôle
+        .route("/api/audit", get(api_audit))             // Journal des actions de pilotage
+        .route("/api/audit/export", get(api_audit_export)) // Export JSON lines du journal
 
         // Fichiers statiques (CSS, JS)
-        .nest_service("/static", ServeDir::new(static_dir));
+        .nest_service("/static", ServeDir::new(&config.static_dir))
+        // Rôle requis par chaque route (auth.rs)
+        .layer(axum::middleware::from_fn(auth::require))
+        .with_state(state);
 
     // Démarrage du serveur HTTP
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    let listener = match tokio::net::TcpListener::bind(config.listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Cannot listen on {}: {} (use --listen / --port)", config.listen, e);
+            std::process::exit(1);
+        }
+    };
     println!("\n╔══════════════════════════════════════════════════════════════╗");
     println!("║           IDS Lab Commander - Academic Edition               ║");
     println!("╠══════════════════════════════════════════════════════════════╣");
-    println!("║  Dashboard:    http://localhost:3000                         ║");
-    println!("║  Setup Guide:  http://localhost:3000/setup                   ║");
+    println!("║  Dashboard:    {:<46}║", config.url());
+    println!("║  Setup Guide:  {:<46}║", format!("{}/setup", config.url()));
     println!("╚══════════════════════════════════════════════════════════════╝\n");
-    axum::serve(listener, app).await.unwrap();
+    // Adresse du client: détenteur affiché des verrous des labs
+    if let Err(e) = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await {
+        eprintln!("Server error: {}", e);
+        std::process::exit(1);
+    }
 }
 
 // ============================================================================
 // FONCTIONS D'INFORMATION SYSTÈME
 // ============================================================================
 // Ces fonctions collectent des informations sur l'environnement Docker
-// pour les afficher dans le dashboard.
-
-/// Récupère la liste des réseaux Docker (bridges).
-/// Retourne: Vec<(nom, id, driver)>
-/// Utilisé pour afficher les réseaux dans le dashboard.
-fn get_docker_bridges() -> Vec<(String, String, String)> {
-    let output = Command::new("docker")
-        .args(["network", "ls", "--format", "{{.Name}}\t{{.ID}}\t{{.Driver}}"])
-        .output();
-
-    match output {
-        Ok(o) => {
-            String::from_utf8_lossy(&o.stdout)
-                .lines()
-                .filter(|l| l.contains("bridge") || l.contains("snort") || l.contains("suricata") || l.contains("zeek"))
-                .map(|l| {
-                    let parts: Vec<&str> = l.split('\t').collect();
-                    (
-                        parts.get(0).unwrap_or(&"").to_string(),
-                        parts.get(1).unwrap_or(&"").to_string(),
-                        parts.get(2).unwrap_or(&"").to_string(),
-                    )
-                })
-                .collect()
-        }
-        Err(_) => vec![],
-    }
-}
+// pour les afficher dans le dashboard. Containers et réseaux viennent de
+// l'API Docker (docker.rs), les interfaces de l'hôte de `ip addr`.
 
 /// Récupère les interfaces réseau bridge avec leurs IPs.
 /// Utilise la commande `ip addr show` pour lister les interfaces.
@@ -164,100 +382,16 @@ fn get_bridge_interfaces() -> Vec<(String, String)> {
     }
 }
 
-/// Récupère les détails de tous les containers liés aux labs IDS.
-/// Filtre sur les noms contenant: snort, suricata, zeek, target, evebox, editor
-/// Retourne: Vec<HashMap> avec clés: name, status, image, id, ip
-fn get_container_details() -> Vec<HashMap<String, String>> {
-    let output = Command::new("docker")
-        .args(["ps", "-a", "--format", "{{.Names}}\t{{.Status}}\t{{.Image}}\t{{.ID}}"])
-        .output();
-
-    match output {
-        Ok(o) => {
-            String::from_utf8_lossy(&o.stdout)
-                .lines()
-                .filter(|l| {
-                    l.contains("snort") || l.contains("suricata") || l.contains("zeek")
-                    || l.contains("target") || l.contains("evebox") || l.contains("editor") || l.contains("reloader")
-                    // Kibana lab containers
-                    || l.contains("kibana") || l.contains("elasticsearch") || l.contains("filebeat")
-                })
-                .map(|l| {
-                    let parts: Vec<&str> = l.split('\t').collect();
-                    let mut map = HashMap::new();
-                    map.insert("name".into(), parts.get(0).unwrap_or(&"").to_string());
-                    map.insert("status".into(), parts.get(1).unwrap_or(&"").to_string());
-                    map.insert("image".into(), parts.get(2).unwrap_or(&"").to_string());
-                    map.insert("id".into(), parts.get(3).unwrap_or(&"").to_string());
-
-                    // Get IP
-                    let ip_output = Command::new("docker")
-                        .args(["inspect", "-f", "{{range .NetworkSettings.Networks}}{{.IPAddress}}{{end}}", parts.get(0).unwrap_or(&"")])
-                        .output();
-                    if let Ok(ip_out) = ip_output {
-                        let ip = String::from_utf8_lossy(&ip_out.stdout).trim().to_string();
-                        map.insert("ip".into(), if ip.is_empty() { "-".into() } else { ip });
-                    }
-                    map
-                })
-                .collect()
-        }
-        Err(_) => vec![],
-    }
-}
-
-/// Vérifie si Docker est accessible sans sudo.
-/// Retourne: (ok: bool, message: String)
-/// Si ok=false, le message contient l'erreur.
-fn check_docker_permissions() -> (bool, String) {
-    let output = Command::new("docker").args(["ps"]).output();
-    match output {
-        Ok(o) => {
-            if o.status.success() {
-                (true, "Docker accessible sans sudo".into())
-            } else {
-                (false, String::from_utf8_lossy(&o.stderr).to_string())
-            }
-        }
-        Err(e) => (false, e.to_string()),
-    }
-}
-
-/// Détecte le niveau de sécurité actuel d'un IDS.
-/// Lit la première ligne du fichier de règles pour identifier le niveau.
-/// Les templates contiennent un commentaire "Level X" ou le nom du niveau.
+/// Détecte le niveau de sécurité actuel d'un lab d'après le tampon du
+/// fichier de règles actif (voir templates.rs).
 ///
-/// Arguments:
-/// - ids: "snort", "suricata", ou "zeek"
-///
-/// Retourne: "1 - Minimal", "2 - Basic", ..., "5 - Paranoid", ou "Custom"
-fn get_current_rule_level(ids: &str) -> String {
-    let root = get_project_root();
-    let rules_path = match ids {
-        "snort" => root.join("snort-lab/config/local.rules"),
-        "suricata" => root.join("suricata-lab/rules/local.rules"),
-        "zeek" => root.join("zeek-lab/scripts/local.zeek"),
-        _ => return "?".into(),
-    };
-
-    if let Ok(content) = fs::read_to_string(&rules_path) {
-        let first_line = content.lines().next().unwrap_or("");
-        if first_line.contains("Level 1") || first_line.contains("Minimal") {
-            "1 - Minimal".into()
-        } else if first_line.contains("Level 2") || first_line.contains("Basic") {
-            "2 - Basic".into()
-        } else if first_line.contains("Level 3") || first_line.contains("Moderate") {
-            "3 - Moderate".into()
-        } else if first_line.contains("Level 4") || first_line.contains("Strict") {
-            "4 - Strict".into()
-        } else if first_line.contains("Level 5") || first_line.contains("Paranoid") {
-            "5 - Paranoid".into()
-        } else {
-            "Custom".into()
-        }
-    } else {
-        "N/A".into()
+/// Retourne: "1 - Minimal", ..., "5 - Paranoid", le nom d'un template
+/// personnalisé, suivi de ", modified" après une retouche, "Custom" ou "N/A"
+fn get_current_rule_level(lab: &Lab) -> String {
+    if lab.active_rules.is_none() {
+        return "?".into();
     }
+    templates::active_rules(&get_project_root(), lab).to_string()
 }
 
 // ============================================================================
@@ -270,48 +404,110 @@ fn get_current_rule_level(ids: &str) -> String {
 // - Cookbook d'attaques (Nmap, Scapy, Hping3)
 // - Informations système
 
+/// Cartes des niveaux de sécurité des labs qui ont des templates de règles.
+fn template_cards() -> String {
+    let mut template_cards = String::new();
+    for lab in labs::registry().labs().iter().filter(|l| l.active_rules.is_some()) {
+        let root = get_project_root();
+        // Template exact (id, auteur) ou retouché depuis son application
+        let active = templates::active_rules(&root, lab);
+        let template = match &active {
+            templates::ActiveRules::Template { meta, modified } => format!(
+                r#"
+                            <div class="template-id{}">{}{}</div>"#,
+                if *modified { " modified" } else { "" },
+                escape(&meta.id),
+                if meta.author.is_empty() { String::new() } else { format!(" · {}", escape(&meta.author)) }
+            ),
+            _ => String::new(),
+        };
+        template_cards.push_str(&format!(
+            r#"<div class="template-card">
+                            <h3>{} {}</h3>
+                            <div class="current">Actuel: {}</div>{}"#,
+            escape(&lab.icon),
+            escape(&lab.name.to_uppercase()),
+            escape(&active.to_string()),
+            template
+        ));
+        let manifest = templates::manifest(&root, lab).unwrap_or_default();
+        for (level, name, desc) in [
+            (1, "Minimal", "Très perméable"),
+            (2, "Basic", "Scans évidents"),
+            (3, "Moderate", "Équilibré"),
+            (4, "Strict", "Sensible"),
+            (5, "Paranoid", "Maximum"),
+        ] {
+            let title = manifest.iter().find(|m| m.level == Some(level)).map_or("", |m| m.description.as_str());
+            template_cards.push_str(&format!(
+                r#"
+                            <button class="level-btn level-{0}" hx-post="/apply/{1}/{0}" hx-target="body" title="{4}">{0} - {2} <span class="desc">{3}</span></button>"#,
+                level,
+                escape(&lab.name),
+                name,
+                desc,
+                escape(title)
+            ));
+        }
+        // Templates personnalisés déposés dans template_dir
+        for name in templates::custom_levels(&root, lab) {
+            template_cards.push_str(&format!(
+                r#"
+                            <button class="level-btn level-custom" hx-post="/apply/{0}/{1}" hx-target="body">{1} <span class="desc">Personnalisé</span></button>"#,
+                escape(&lab.name),
+                escape(&name)
+            ));
+        }
+        // Versions précédentes des règles (history.rs), chargées à la demande
+        template_cards.push_str(&format!(
+            r#"
+                            <div class="rule-history" data-lab="{0}">
+                                <button class="btn neutral" onclick="loadRuleHistory({1})">🕘 Historique</button>
+                                <div class="history-list"></div>
+                            </div>"#,
+            escape(&lab.name),
+            js_attr(&lab.name)
+        ));
+        template_cards.push_str("\n                        </div>\n                        ");
+    }
+    template_cards
+}
+
 /// Génère le dashboard HTML complet.
 /// Cette fonction collecte toutes les informations système et génère
 /// une page HTML avec HTMX pour les interactions.
 async fn dashboard() -> Html<String> {
-    let containers = get_container_details();
-    let bridges = get_docker_bridges();
-    let interfaces = get_bridge_interfaces();
-    let (docker_ok, _docker_msg) = check_docker_permissions();
+    let snapshot = snapshot::current();
+    let containers = &snapshot.containers;
+    let bridges = &snapshot.bridges;
+    let interfaces = blocking(get_bridge_interfaces).await;
+    let docker_ok = snapshot.docker_ok;
 
     // Build container tables
     let mut ids_rows = String::new();
     let mut target_rows = String::new();
     let mut service_rows = String::new();
 
-    // Détecter l'état des labs (un lab est "running" si AU MOINS UN container du lab tourne)
-    // On vérifie les préfixes: snort_, suricata_, zeek_ ou target_snort, target_suricata, target_zeek
-    let snort_running = containers.iter().any(|c| {
-        c.get("name").map(|n| n.starts_with("snort_") || n == "target_snort").unwrap_or(false)
-        && c.get("status").map(|s| s.contains("Up")).unwrap_or(false)
-    });
-    let suricata_running = containers.iter().any(|c| {
-        c.get("name").map(|n| n.starts_with("suricata_") || n == "target_suricata").unwrap_or(false)
-        && c.get("status").map(|s| s.contains("Up")).unwrap_or(false)
-    });
-    let zeek_running = containers.iter().any(|c| {
-        c.get("name").map(|n| n.starts_with("zeek_") || n == "target_zeek").unwrap_or(false)
-        && c.get("status").map(|s| s.contains("Up")).unwrap_or(false)
-    });
-    // Kibana lab: elasticsearch, kibana, filebeat
-    let kibana_running = containers.iter().any(|c| {
-        c.get("name").map(|n| n.starts_with("kibana_")).unwrap_or(false)
-        && c.get("status").map(|s| s.contains("Up")).unwrap_or(false)
-    });
-
-    for c in &containers {
-        let name = c.get("name").map(|s| s.as_str()).unwrap_or("");
-        let ip = c.get("ip").map(|s| s.as_str()).unwrap_or("-");
-        let status = c.get("status").map(|s| s.as_str()).unwrap_or("");
-        let id = c.get("id").map(|s| s.as_str()).unwrap_or("");
-        let short_id = &id[..12.min(id.len())];
+    let registry = labs::registry();
+    let running: Vec<bool> = registry.labs().iter().map(|lab| snapshot.lab_running(&lab.name)).collect();
 
-        let is_running = status.contains("Up");
+    for c in containers {
+        let name = escape(&c.name);
+        let short_id = escape(c.short_id());
+        // Une IP par réseau (un container peut être sur plusieurs bridges)
+        let ip = if c.networks.is_empty() {
+            "-".to_string()
+        } else {
+            c.networks.iter().map(|n| escape(&n.ip)).collect::<Vec<_>>().join("<br>")
+        };
+        // Santé (healthcheck) ou code de sortie du dernier arrêt
+        let detail = match (&c.health, c.exit_code) {
+            (Some(health), _) => format!(" ({})", escape(health)),
+            (None, Some(code)) => format!(" (exit {})", code),
+            _ => String::new(),
+        };
+
+        let is_running = c.is_running();
         let status_class = if is_running { "running" } else { "stopped" };
         let status_icon = if is_running { "●" } else { "○" };
 
@@ -330,27 +526,30 @@ async fn dashboard() -> Html<String> {
         );
 
         let row = format!(
-            r#"<tr class="{}" data-container="{}"><td>{}</td><td><code>{}</code></td><td>{} {}</td><td>{}</td></tr>"#,
+            r#"<tr class="{}" data-container="{}"><td>{}</td><td><code>{}</code></td><td>{} {}{}</td><td>{}</td></tr>"#,
             status_class, name, name, ip, status_icon,
             if is_running { "Running" } else { "Stopped" },
+            detail,
             action_btns
         );
 
-        if name.contains("_ids") || name == "snort" || name == "suricata" || name == "zeek" {
-            ids_rows.push_str(&row);
-        } else if name.contains("target") {
-            target_rows.push_str(&row);
-        } else {
-            service_rows.push_str(&row);
+        // Rôle déclaré par le label ids-lab.role des docker-compose.yml
+        match c.role {
+            Some(Role::Ids) => ids_rows.push_str(&row),
+            Some(Role::Target) => target_rows.push_str(&row),
+            _ => service_rows.push_str(&row),
         }
     }
 
     // Network info
     let mut network_html = String::new();
-    for (name, id, driver) in &bridges {
+    for bridge in bridges {
         network_html.push_str(&format!(
-            r#"<tr><td>{}</td><td><code>{}</code></td><td>{}</td></tr>"#,
-            name, &id[..12.min(id.len())], driver
+            r#"<tr><td>{}</td><td><code>{}</code></td><td>{}</td><td><code>{}</code></td></tr>"#,
+            escape(&bridge.name),
+            escape(&bridge.id[..12.min(bridge.id.len())]),
+            escape(&bridge.driver),
+            escape(&bridge.subnets.join(", "))
         ));
     }
 
@@ -358,96 +557,198 @@ async fn dashboard() -> Html<String> {
     for (iface, ip) in &interfaces {
         interfaces_html.push_str(&format!(
             r#"<tr><td><code>{}</code></td><td><code>{}</code></td></tr>"#,
-            iface, ip
+            escape(iface),
+            escape(ip)
         ));
     }
 
-    // Current levels
-    let snort_level = get_current_rule_level("snort");
-    let suricata_level = get_current_rule_level("suricata");
-    let zeek_level = get_current_rule_level("zeek");
-
     // Générer les lab cards avec boutons grisés selon l'état
-    // hx-indicator="closest .lab-card" active le spinner sur la carte parente
-    let lab_cards = format!(r#"
-        <div class="lab-card" data-lab="snort">
-            <h3 style="margin-bottom: 0.5rem;">🐷 SNORT Lab</h3>
-            <p style="font-size: 0.8rem; color: var(--text-secondary);">Cible: <code>172.28.0.100</code></p>
-            <p style="font-size: 0.75rem; color: var(--text-secondary);">Ports: 21, 22, 80, 8080</p>
-            <div style="margin-top: 0.75rem; display: flex; gap: 0.5rem;">
-                <button class="btn start {}" hx-post="/lab/start/snort" hx-target="body" hx-indicator="closest .lab-card" hx-confirm="Démarrer le lab SNORT ?" {}>▶ Start</button>
-                <button class="btn stop {}" hx-post="/lab/stop/snort" hx-target="body" hx-indicator="closest .lab-card" hx-confirm="Arrêter le lab SNORT ?" {}>■ Stop</button>
-            </div>
-            <span class="loading-msg">Please wait...</span>
-        </div>
-        <div class="lab-card" data-lab="suricata">
-            <h3 style="margin-bottom: 0.5rem;">🦊 SURICATA Lab</h3>
-            <p style="font-size: 0.8rem; color: var(--text-secondary);">Cible: <code>172.29.0.100</code></p>
-            <p style="font-size: 0.75rem; color: var(--text-secondary);">Ports: 21, 22, 80, 8080</p>
-            <div style="margin-top: 0.75rem; display: flex; gap: 0.5rem;">
-                <button class="btn start {}" hx-post="/lab/start/suricata" hx-target="body" hx-indicator="closest .lab-card" hx-confirm="Démarrer le lab SURICATA ?" {}>▶ Start</button>
-                <button class="btn stop {}" hx-post="/lab/stop/suricata" hx-target="body" hx-indicator="closest .lab-card" hx-confirm="Arrêter le lab SURICATA ?" {}>■ Stop</button>
-            </div>
-            <span class="loading-msg">Please wait...</span>
-        </div>
-        <div class="lab-card" data-lab="zeek">
-            <h3 style="margin-bottom: 0.5rem;">👁️ ZEEK Lab</h3>
-            <p style="font-size: 0.8rem; color: var(--text-secondary);">Cible: <code>172.30.0.100</code></p>
-            <p style="font-size: 0.75rem; color: var(--text-secondary);">Ports: 21, 22, 80, 8080</p>
+    // Les boutons soumettent un job (submitJob), le spinner suit son avancement
+    let mut lab_cards = String::new();
+    for (lab, &is_running) in registry.labs().iter().zip(&running) {
+        let address = match lab.target_ip {
+            Some(ip) => format!("Cible: <code>{}</code>", ip),
+            None => format!("Réseau: <code>{}</code>", escape(&lab.subnet)),
+        };
+        let background = lab
+            .card_background
+            .as_ref()
+            .map(|b| format!(r#" style="background: {};""#, escape(b)))
+            .unwrap_or_default();
+        let confirm = |verb: &str| js_attr(&format!("{} le lab {} ?", verb, lab.title));
+        lab_cards.push_str(&format!(r#"
+        <div class="lab-card" data-lab="{name}"{background}>
+            <h3 style="margin-bottom: 0.5rem;">{icon} {title}</h3>
+            <p style="font-size: 0.8rem; color: var(--text-secondary);">{address}</p>
+            <p style="font-size: 0.75rem; color: var(--text-secondary);">{description}</p>
+            <p class="lab-readiness"></p>
+            <p class="lab-lock"></p>
             <div style="margin-top: 0.75rem; display: flex; gap: 0.5rem;">
-                <button class="btn start {}" hx-post="/lab/start/zeek" hx-target="body" hx-indicator="closest .lab-card" hx-confirm="Démarrer le lab ZEEK ?" {}>▶ Start</button>
-                <button class="btn stop {}" hx-post="/lab/stop/zeek" hx-target="body" hx-indicator="closest .lab-card" hx-confirm="Arrêter le lab ZEEK ?" {}>■ Stop</button>
-            </div>
-            <span class="loading-msg">Please wait...</span>
-        </div>
-        <div class="lab-card" data-lab="kibana" style="background: linear-gradient(135deg, #1a1a40 0%, #2d1b4e 100%);">
-            <h3 style="margin-bottom: 0.5rem;">📊 KIBANA Lab</h3>
-            <p style="font-size: 0.8rem; color: var(--text-secondary);">ELK Stack: <code>172.31.0.0/24</code></p>
-            <p style="font-size: 0.75rem; color: var(--text-secondary);">Kibana: 5601, ES: 9200</p>
-            <div style="margin-top: 0.75rem; display: flex; gap: 0.5rem;">
-                <button class="btn start {}" hx-post="/lab/start/kibana" hx-target="body" hx-indicator="closest .lab-card" hx-confirm="Démarrer le lab KIBANA (ELK Stack) ?" {}>▶ Start</button>
-                <button class="btn stop {}" hx-post="/lab/stop/kibana" hx-target="body" hx-indicator="closest .lab-card" hx-confirm="Arrêter le lab KIBANA ?" {}>■ Stop</button>
+                <button class="btn start {start}" data-action="start" onclick="submitJob({name_js}, 'start', {confirm_start})" {start}>▶ Start</button>
+                <button class="btn stop {stop}" data-action="stop" onclick="submitJob({name_js}, 'stop', {confirm_stop})" {stop}>■ Stop</button>
+                <button class="btn neutral" data-action="rebuild" title="Reconstruire les images sans cache" onclick="submitJob({name_js}, 'rebuild', {confirm_rebuild})">⟳</button>
             </div>
             <span class="loading-msg">Please wait...</span>
         </div>"#,
-        // Snort
-        if snort_running { "disabled" } else { "" }, if snort_running { "disabled" } else { "" },
-        if snort_running { "" } else { "disabled" }, if snort_running { "" } else { "disabled" },
-        // Suricata
-        if suricata_running { "disabled" } else { "" }, if suricata_running { "disabled" } else { "" },
-        if suricata_running { "" } else { "disabled" }, if suricata_running { "" } else { "disabled" },
-        // Zeek
-        if zeek_running { "disabled" } else { "" }, if zeek_running { "disabled" } else { "" },
-        if zeek_running { "" } else { "disabled" }, if zeek_running { "" } else { "disabled" },
-        // Kibana
-        if kibana_running { "disabled" } else { "" }, if kibana_running { "disabled" } else { "" },
-        if kibana_running { "" } else { "disabled" }, if kibana_running { "" } else { "disabled" },
-    );
+            name = escape(&lab.name),
+            name_js = js_attr(&lab.name),
+            icon = escape(&lab.icon),
+            title = escape(&lab.title),
+            description = escape(&lab.card_description()),
+            confirm_start = confirm("Démarrer"),
+            confirm_stop = confirm("Arrêter"),
+            confirm_rebuild = confirm("Reconstruire (images sans cache)"),
+            start = if is_running { "disabled" } else { "" },
+            stop = if is_running { "" } else { "disabled" },
+        ));
+    }
 
-    // Générer les liens de visualisation dynamiques selon le lab actif
-    // Kibana: Dashboard centralisé (5601), Suricata: EveBox (5636), Snort: logs (8081), Zeek: logs (8083)
-    let visualizer_links = if kibana_running {
-        r#"<a href="http://localhost:5601" target="_blank" style="background: linear-gradient(135deg, #e8488a, #8a48e8);">📊 Kibana Dashboard ↗</a>
-           <a href="http://localhost:9200/_cat/indices?v" target="_blank">🔍 ES Indices ↗</a>"#.to_string()
-    } else if suricata_running {
-        r#"<a href="http://localhost:5636" target="_blank" style="background: var(--accent-green);">📊 EveBox (Suricata) ↗</a>
-           <a href="http://localhost:8082" target="_blank">📝 Rules Editor ↗</a>"#.to_string()
-    } else if snort_running {
-        r#"<a href="http://localhost:8081" target="_blank">📝 Snort Editor ↗</a>
-           <span style="opacity: 0.5; padding: 0.5rem 1rem;">📊 No visualizer</span>"#.to_string()
-    } else if zeek_running {
-        r#"<a href="http://localhost:8083" target="_blank">📝 Zeek Editor ↗</a>
-           <span style="opacity: 0.5; padding: 0.5rem 1rem;">📊 No visualizer</span>"#.to_string()
-    } else {
-        r#"<span style="opacity: 0.5; padding: 0.5rem 1rem;">🚫 No lab running</span>"#.to_string()
-    };
+    // Niveaux de sécurité des labs (lecture des règles actives et templates)
+    let template_cards = blocking(template_cards).await;
+
+    // Diagramme d'architecture: un sous-réseau par lab attaquable
+    let mut architecture = String::from("graph TB\n    subgraph LOCALHOST[\"🖥️ LOCALHOST - Attaquant\"]\n        NMAP[\"nmap / scapy / hping3\"]\n    end\n");
+    let mut links = String::new();
+    for lab in registry.labs().iter().filter(|l| l.ids_container.is_some() && l.target_container.is_some()) {
+        let key = lab.name.to_uppercase().replace('-', "_");
+        let ids = escape(lab.ids_container.as_deref().unwrap_or_default());
+        let target = escape(lab.target_container.as_deref().unwrap_or_default());
+        let target_ip = lab.target_ip.map(|ip| format!("<br/>{}", ip)).unwrap_or_default();
+        architecture.push_str(&format!(
+            "\n    subgraph {key}_NET[\"{name}_net {subnet}\"]\n        {key}_IDS[\"🛡️ {ids}\"]\n        TARGET_{key}[\"🎯 {target}{target_ip}\"]\n    end\n",
+            key = escape(&key),
+            name = escape(&lab.name),
+            subnet = escape(&lab.subnet),
+        ));
+        links.push_str(&format!(
+            "\n    NMAP -->|{name}| {key}_NET\n    {key}_IDS -.->|monitor| TARGET_{key}",
+            name = escape(&lab.name),
+            key = escape(&key)
+        ));
+    }
+    architecture.push_str(&links);
+
+    // Générer les liens de visualisation dynamiques selon les labs actifs
+    // (dashboard_url, sinon éditeur de règles)
+    let mut visualizer_links = String::new();
+    for (lab, _) in registry.labs().iter().zip(&running).filter(|(_, &r)| r) {
+        if let Some(url) = &lab.dashboard_url {
+            visualizer_links.push_str(&format!(
+                r#"<a href="{}" target="_blank" style="background: var(--accent-green);">📊 {} ↗</a>"#,
+                escape(url),
+                escape(&lab.title)
+            ));
+        }
+        if let Some(url) = &lab.editor_url {
+            visualizer_links.push_str(&format!(
+                r#"<a href="{}" target="_blank">📝 {} Editor ↗</a>"#,
+                escape(url),
+                escape(&lab.title)
+            ));
+        }
+    }
+    if visualizer_links.is_empty() {
+        visualizer_links = r#"<span style="opacity: 0.5; padding: 0.5rem 1rem;">🚫 No lab running</span>"#.to_string();
+    }
+
+    // Cible du cookbook: premier lab démarré, sinon premier lab avec une cible
+    let cookbook_target = registry
+        .labs()
+        .iter()
+        .zip(&running)
+        .find(|(l, &r)| r && l.target_ip.is_some())
+        .map(|(l, _)| l)
+        .or_else(|| registry.labs().iter().find(|l| l.target_ip.is_some()))
+        .and_then(|l| l.target_ip)
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "TARGET_IP".into());
+
+    // Labs attaquables (attaques et matrice d'évasion)
+    let lab_options: String = registry
+        .labs()
+        .iter()
+        .filter_map(|l| Some((l, l.engine?, l.target_ip?)))
+        .map(|(l, _, ip)| {
+            let selected = if l.target_ip.map(|t| t.to_string()) == Some(cookbook_target.clone()) { " selected" } else { "" };
+            format!(
+                r#"<option value="{}"{}>{} {} ({})</option>"#,
+                escape(&l.name),
+                selected,
+                escape(&l.icon),
+                escape(&l.title),
+                ip
+            )
+        })
+        .collect();
+
+    // Filtre de la chronologie des containers: tous les labs du registre
+    let event_lab_options: String = registry
+        .labs()
+        .iter()
+        .map(|l| format!(r#"<option value="{}">{} {}</option>"#, escape(&l.name), escape(&l.icon), escape(&l.title)))
+        .collect();
+
+    // Accès rapides: éditeurs de règles, visualiseurs et index Kibana par moteur
+    let editor_links: String = registry
+        .labs()
+        .iter()
+        .filter_map(|l| {
+            let url = l.editor_url.as_ref()?;
+            Some(format!(r#"<a href="{}" target="_blank">📝 {} Editor</a>"#, escape(url), escape(&l.title)))
+        })
+        .collect();
+    let tool_links: String = registry
+        .labs()
+        .iter()
+        .filter_map(|l| {
+            let url = l.dashboard_url.as_ref()?;
+            Some(format!(r#"<a href="{}" target="_blank">📊 {}</a>"#, escape(url), escape(&l.title)))
+        })
+        .collect();
+    // Index par moteur dans le Kibana du lab "kibana" (aucun lien sans ce lab)
+    let kibana_url = registry.get("kibana").and_then(|l| l.dashboard_url.as_deref());
+    let kibana_links: String = registry
+        .labs()
+        .iter()
+        .filter_map(|l| {
+            Some(format!(
+                r#"<a href="{}/app/discover#/?_g=()&_a=(index:'{}-*')" target="_blank">{} {} Logs</a>"#,
+                escape(kibana_url?.trim_end_matches('/')),
+                l.engine?,
+                escape(&l.icon),
+                escape(&l.title)
+            ))
+        })
+        .collect();
+
+    // Onglets du visualiseur d'alertes, un par moteur
+    let mut alert_tabs = String::new();
+    let mut alert_panels = String::new();
+    let mut engines: Vec<Engine> = vec![];
+    for lab in registry.labs() {
+        let Some(engine) = lab.engine.filter(|e| !engines.contains(e)) else { continue };
+        let active = if engines.is_empty() { " active" } else { "" };
+        engines.push(engine);
+        alert_tabs.push_str(&format!(
+            r#"<button class="tab{}" onclick="showAlertTab('{}')">{} {}</button>"#,
+            active,
+            engine,
+            escape(&lab.icon),
+            escape(&lab.title)
+        ));
+        alert_panels.push_str(&format!(
+            r#"<div id="alerts-{0}" class="alerts-panel{1}">
+                        <pre class="alerts-content" id="alerts-content-{0}">Cliquez sur Refresh pour charger les alertes...</pre>
+                    </div>"#,
+            engine, active
+        ));
+    }
 
-    // Find target IPs for attack cookbook
-    let suricata_target = containers.iter()
-        .find(|c| c.get("name").map(|n| n.contains("target_suricata")).unwrap_or(false))
-        .and_then(|c| c.get("ip"))
-        .map(|s| s.as_str())
-        .unwrap_or("172.29.0.100");
+    // Techniques du cookbook exécutables depuis le serveur
+    let technique_options: String = attack::TECHNIQUES
+        .iter()
+        .map(|t| format!(r#"<option value="{}">{} ({})</option>"#, escape(t.id), escape(t.name), escape(t.description)))
+        .collect();
 
     let html = format!(r##"<!DOCTYPE html>
 <html>
@@ -468,7 +769,8 @@ async fn dashboard() -> Html<String> {
         <nav class="nav">
             <a href="/" class="active">Dashboard</a>
             <a href="/setup">Setup Guide</a>
-            {14}
+            {visualizer_links}
+            <span id="user-info" class="user-info"></span>
         </nav>
     </div>
 
@@ -481,33 +783,7 @@ async fn dashboard() -> Html<String> {
                 </div>
                 <div class="card-body">
                     <div class="mermaid">
-graph TB
-    subgraph LOCALHOST["🖥️ LOCALHOST - Attaquant"]
-        NMAP["nmap / scapy / hping3"]
-    end
-
-    subgraph SNORT_NET["snort_net 172.28.0.0/24"]
-        SNORT_IDS["🛡️ snort_ids<br/>172.28.0.10"]
-        TARGET_SNORT["🎯 target_snort<br/>172.28.0.100"]
-    end
-
-    subgraph SURICATA_NET["suricata_net 172.29.0.0/24"]
-        SURICATA_IDS["🛡️ suricata_ids<br/>172.29.0.10"]
-        TARGET_SURICATA["🎯 target_suricata<br/>172.29.0.100"]
-    end
-
-    subgraph ZEEK_NET["zeek_net 172.30.0.0/24"]
-        ZEEK_IDS["🛡️ zeek_ids<br/>172.30.0.10"]
-        TARGET_ZEEK["🎯 target_zeek<br/>172.30.0.100"]
-    end
-
-    NMAP -->|br-snort| SNORT_NET
-    NMAP -->|br-suricata| SURICATA_NET
-    NMAP -->|br-zeek| ZEEK_NET
-
-    SNORT_IDS -.->|monitor| TARGET_SNORT
-    SURICATA_IDS -.->|monitor| TARGET_SURICATA
-    ZEEK_IDS -.->|monitor| TARGET_ZEEK
+{architecture}
                     </div>
                 </div>
             </div>
@@ -518,16 +794,15 @@ graph TB
                     <h2>🚀 Sélection du Lab</h2>
                 </div>
                 <div class="card-body">
-                    <div style="display: grid; grid-template-columns: repeat(4, 1fr); gap: 1rem;">
-                        {13}
+                    <div style="display: grid; grid-template-columns: repeat(auto-fit, minmax(180px, 1fr)); gap: 1rem;">
+                        {lab_cards}
                     </div>
                     <div style="margin-top: 1rem; text-align: center;">
-                        <button class="btn stop" style="padding: 10px 24px; font-size: 0.9rem;"
-                                hx-post="/lab/stop-all" hx-target="body" hx-indicator="this"
-                                hx-confirm="Arrêter TOUS les labs (Snort, Suricata, Zeek) ?">
+                        <button class="btn stop" style="padding: 10px 24px; font-size: 0.9rem;" onclick="stopAllLabs()">
                             🛑 Stop All Labs
                         </button>
                     </div>
+                    <div id="jobs-panel" class="jobs-panel"></div>
                     <div class="info-callout" style="margin-top: 1rem;">
                         <strong>Services sur chaque cible:</strong> HTTP (80), SSH (22), FTP (21), API (8080)<br>
                         <strong>Page web:</strong> <code>http://&lt;target_ip&gt;/</code> | <strong>FTP:</strong> anonymous login
@@ -539,12 +814,12 @@ graph TB
             <div class="card">
                 <div class="card-header">
                     <h2>🔒 IDS Engines</h2>
-                    <span class="status-badge {0}">{1}</span>
+                    <span class="status-badge {docker_class}">{docker_status}</span>
                 </div>
                 <div class="card-body">
                     <table>
                         <tr><th>Container</th><th>IP</th><th>Status</th><th>Actions</th></tr>
-                        {2}
+                        {ids_rows}
                     </table>
                 </div>
             </div>
@@ -560,7 +835,7 @@ graph TB
                     </p>
                     <table>
                         <tr><th>Container</th><th>IP</th><th>Status</th><th>Actions</th></tr>
-                        {3}
+                        {target_rows}
                     </table>
                 </div>
             </div>
@@ -575,33 +850,7 @@ graph TB
                         Sélectionnez un niveau pour chaque IDS. Les règles sont rechargées automatiquement.
                     </p>
                     <div class="templates-grid">
-                        <div class="template-card">
-                            <h3>🐷 SNORT</h3>
-                            <div class="current">Actuel: {4}</div>
-                            <button class="level-btn level-1" hx-post="/apply/snort/1" hx-target="body">1 - Minimal <span class="desc">Très perméable</span></button>
-                            <button class="level-btn level-2" hx-post="/apply/snort/2" hx-target="body">2 - Basic <span class="desc">Scans évidents</span></button>
-                            <button class="level-btn level-3" hx-post="/apply/snort/3" hx-target="body">3 - Moderate <span class="desc">Équilibré</span></button>
-                            <button class="level-btn level-4" hx-post="/apply/snort/4" hx-target="body">4 - Strict <span class="desc">Sensible</span></button>
-                            <button class="level-btn level-5" hx-post="/apply/snort/5" hx-target="body">5 - Paranoid <span class="desc">Maximum</span></button>
-                        </div>
-                        <div class="template-card">
-                            <h3>🦊 SURICATA</h3>
-                            <div class="current">Actuel: {5}</div>
-                            <button class="level-btn level-1" hx-post="/apply/suricata/1" hx-target="body">1 - Minimal <span class="desc">Très perméable</span></button>
-                            <button class="level-btn level-2" hx-post="/apply/suricata/2" hx-target="body">2 - Basic <span class="desc">Scans évidents</span></button>
-                            <button class="level-btn level-3" hx-post="/apply/suricata/3" hx-target="body">3 - Moderate <span class="desc">Équilibré</span></button>
-                            <button class="level-btn level-4" hx-post="/apply/suricata/4" hx-target="body">4 - Strict <span class="desc">Sensible</span></button>
-                            <button class="level-btn level-5" hx-post="/apply/suricata/5" hx-target="body">5 - Paranoid <span class="desc">Maximum</span></button>
-                        </div>
-                        <div class="template-card">
-                            <h3>👁️ ZEEK</h3>
-                            <div class="current">Actuel: {6}</div>
-                            <button class="level-btn level-1" hx-post="/apply/zeek/1" hx-target="body">1 - Minimal <span class="desc">Très perméable</span></button>
-                            <button class="level-btn level-2" hx-post="/apply/zeek/2" hx-target="body">2 - Basic <span class="desc">Scans évidents</span></button>
-                            <button class="level-btn level-3" hx-post="/apply/zeek/3" hx-target="body">3 - Moderate <span class="desc">Équilibré</span></button>
-                            <button class="level-btn level-4" hx-post="/apply/zeek/4" hx-target="body">4 - Strict <span class="desc">Sensible</span></button>
-                            <button class="level-btn level-5" hx-post="/apply/zeek/5" hx-target="body">5 - Paranoid <span class="desc">Maximum</span></button>
-                        </div>
+                        {template_cards}
                     </div>
                 </div>
             </div>
@@ -622,29 +871,37 @@ graph TB
                         <div class="attack-cookbook">
                             <h4>🔍 Scans de base</h4>
                             <pre><code># SYN Scan (détecté niveau ≥2)
-sudo nmap -sS {7}
+sudo nmap -sS {target}
 
 # Full TCP Connect (détecté niveau ≥2)
-nmap -sT {7}</code><button class="copy-btn" onclick="copyCode(this)">Copy</button></pre>
+nmap -sT {target}</code><button class="copy-btn" onclick="copyCode(this)">Copy</button></pre>
 
                             <h4>🥷 Techniques d'évasion</h4>
                             <pre><code># Fragmentation IP (évite niveau ≤3)
-sudo nmap -f {7}
+sudo nmap -f {target}
 
 # Fragmentation max (évite niveau ≤4)
-sudo nmap -f -f --mtu 8 {7}
+sudo nmap -f -f --mtu 8 {target}
 
 # Timing lent T0 (évite détection par seuil)
-sudo nmap -T0 {7}
+sudo nmap -T0 {target}
 
 # Decoys (brouille la source)
-sudo nmap -D RND:10 {7}
+sudo nmap -D RND:10 {target}
 
 # Idle/Zombie scan (pas de paquets directs)
-sudo nmap -sI zombie_host {7}
+sudo nmap -sI zombie_host {target}
 
 # Source port 53 (DNS - souvent autorisé)
-sudo nmap --source-port 53 {7}</code><button class="copy-btn" onclick="copyCode(this)">Copy</button></pre>
+sudo nmap --source-port 53 {target}</code><button class="copy-btn" onclick="copyCode(this)">Copy</button></pre>
+
+                            <h4>▶ Exécuter depuis le commander</h4>
+                            <div class="attack-runner">
+                                <select id="attack-lab">{lab_options}</select>
+                                <select id="attack-technique">{technique_options}</select>
+                                <button class="btn start" id="attack-run-btn" onclick="runAttack()">▶ Lancer</button>
+                            </div>
+                            <pre class="attack-output" id="attack-output">La sortie de nmap s'affichera ici.</pre>
                         </div>
                     </div>
 
@@ -653,7 +910,7 @@ sudo nmap --source-port 53 {7}</code><button class="copy-btn" onclick="copyCode(
                             <h4>🐍 Fragmentation manuelle</h4>
                             <pre><code>from scapy.all import *
 
-target = "{7}"
+target = "{target}"
 payload = "GET /evil HTTP/1.1\\r\\nHost: test\\r\\n\\r\\n"
 
 # Créer des fragments de 8 bytes
@@ -668,7 +925,7 @@ print(f"Envoyé {{len(frags)}} fragments")</code><button class="copy-btn" onclic
 
 # Paquets avec TTL variable (évite certaines signatures)
 for ttl in [1, 5, 64, 128, 255]:
-    pkt = IP(dst="{7}", ttl=ttl)/TCP(dport=80, flags="S")
+    pkt = IP(dst="{target}", ttl=ttl)/TCP(dport=80, flags="S")
     send(pkt, verbose=0)</code><button class="copy-btn" onclick="copyCode(this)">Copy</button></pre>
                         </div>
                     </div>
@@ -677,17 +934,62 @@ for ttl in [1, 5, 64, 128, 255]:
                         <div class="attack-cookbook">
                             <h4>⚡ Hping3 scans</h4>
                             <pre><code># SYN scan avec fragmentation
-sudo hping3 -S -f -p 80 {7}
+sudo hping3 -S -f -p 80 {target}
 
 # Timing aléatoire
-sudo hping3 -S -p 80 --rand-dest -i u10000 {7}
+sudo hping3 -S -p 80 --rand-dest -i u10000 {target}
 
 # Spoof source
-sudo hping3 -S -a 10.0.0.1 -p 80 {7}</code><button class="copy-btn" onclick="copyCode(this)">Copy</button></pre>
+sudo hping3 -S -a 10.0.0.1 -p 80 {target}</code><button class="copy-btn" onclick="copyCode(this)">Copy</button></pre>
                         </div>
                     </div>
                 </div>
             </div>
+
+            <!-- Evasion Matrix -->
+            <div class="card">
+                <div class="card-header">
+                    <h2>🧪 Matrice d'évasion</h2>
+                </div>
+                <div class="card-body">
+                    <p style="font-size: 0.8rem; color: var(--text-secondary); margin-bottom: 0.75rem;">
+                        Applique les niveaux 1 à 5 puis lance chaque technique du cookbook.
+                        Les règles actives sont restaurées à la fin du job.
+                    </p>
+                    <div class="attack-runner">
+                        <select id="matrix-lab">{lab_options}</select>
+                        <button class="btn start" id="matrix-run-btn" onclick="startMatrix()">▶ Lancer la matrice</button>
+                    </div>
+                    <div id="matrix-result" class="matrix-result">Aucune matrice mesurée.</div>
+                    <h4 style="font-size: 0.75rem; color: var(--text-secondary); margin: 0.75rem 0 0.5rem;">📄 Rapport (dernière matrice de chaque lab)</h4>
+                    <div class="quick-links">
+                        <a href="/api/report/html" target="_blank">HTML</a>
+                        <a href="/api/report/md">Markdown</a>
+                        <a href="/api/report/json">JSON</a>
+                        <a href="/api/report/csv">CSV</a>
+                    </div>
+                </div>
+            </div>
+
+            <!-- Docker Events Timeline -->
+            <div class="card">
+                <div class="card-header">
+                    <h2>🕒 Chronologie des containers</h2>
+                </div>
+                <div class="card-body">
+                    <p style="font-size: 0.8rem; color: var(--text-secondary); margin-bottom: 0.75rem;">
+                        Démarrages, arrêts, signaux (SIGHUP du reloader) et santé des containers:
+                        une alerte manquante s'explique souvent par un redémarrage de l'IDS.
+                    </p>
+                    <div class="attack-runner">
+                        <select id="events-lab" onchange="loadEvents()">
+                            <option value="">Tous les labs</option>{event_lab_options}
+                        </select>
+                        <button class="btn" onclick="loadEvents()">🔄</button>
+                    </div>
+                    <div id="events-timeline" class="events-timeline">Aucun événement enregistré.</div>
+                </div>
+            </div>
         </div>
 
         <!-- Sidebar -->
@@ -700,19 +1002,19 @@ sudo hping3 -S -a 10.0.0.1 -p 80 {7}</code><button class="copy-btn" onclick="cop
                 <div class="card-body">
                     <p style="font-size: 0.85rem; margin-bottom: 0.5rem;">
                         <strong>Docker:</strong>
-                        <span class="status-badge {8}">{9}</span>
+                        <span class="status-badge {network_class}">{network_status}</span>
                     </p>
 
                     <h4 style="font-size: 0.8rem; color: var(--text-secondary); margin: 1rem 0 0.5rem;">Réseaux Docker</h4>
                     <table style="font-size: 0.8rem;">
-                        <tr><th>Network</th><th>ID</th><th>Driver</th></tr>
-                        {10}
+                        <tr><th>Network</th><th>ID</th><th>Driver</th><th>Subnet</th></tr>
+                        {network_html}
                     </table>
 
                     <h4 style="font-size: 0.8rem; color: var(--text-secondary); margin: 1rem 0 0.5rem;">Interfaces Bridge</h4>
                     <table style="font-size: 0.8rem;">
                         <tr><th>Interface</th><th>IP/CIDR</th></tr>
-                        {11}
+                        {interfaces_html}
                     </table>
                 </div>
             </div>
@@ -725,23 +1027,17 @@ sudo hping3 -S -a 10.0.0.1 -p 80 {7}</code><button class="copy-btn" onclick="cop
                 <div class="card-body">
                     <h4 style="font-size: 0.75rem; color: var(--text-secondary); margin-bottom: 0.5rem;">Éditeurs de règles</h4>
                     <div class="quick-links">
-                        <a href="http://localhost:8081" target="_blank">📝 Snort Editor</a>
-                        <a href="http://localhost:8082" target="_blank">📝 Suricata Editor</a>
-                        <a href="http://localhost:8083" target="_blank">📝 Zeek Editor</a>
+                        {editor_links}
                     </div>
 
                     <h4 style="font-size: 0.75rem; color: var(--text-secondary); margin: 0.75rem 0 0.5rem;">📊 Dashboards IDS (Kibana)</h4>
                     <div class="quick-links">
-                        <a href="http://localhost:5601/app/discover#/?_g=()&_a=(index:'snort-*')" target="_blank" style="background: linear-gradient(135deg, #ff6b6b, #ee5a24);">🐷 Snort Logs</a>
-                        <a href="http://localhost:5601/app/discover#/?_g=()&_a=(index:'suricata-*')" target="_blank" style="background: linear-gradient(135deg, #f9ca24, #f0932b);">🦊 Suricata Logs</a>
-                        <a href="http://localhost:5601/app/discover#/?_g=()&_a=(index:'zeek-*')" target="_blank" style="background: linear-gradient(135deg, #6c5ce7, #a29bfe);">👁️ Zeek Logs</a>
+                        {kibana_links}
                     </div>
 
                     <h4 style="font-size: 0.75rem; color: var(--text-secondary); margin: 0.75rem 0 0.5rem;">Outils</h4>
                     <div class="quick-links">
-                        <a href="http://localhost:5636" target="_blank">📊 EveBox</a>
-                        <a href="http://localhost:5601" target="_blank" style="background: linear-gradient(135deg, #e8488a, #8a48e8);">📊 Kibana</a>
-                        <a href="http://localhost:9200" target="_blank">🔍 Elasticsearch</a>
+                        {tool_links}
                     </div>
                 </div>
             </div>
@@ -750,22 +1046,14 @@ sudo hping3 -S -a 10.0.0.1 -p 80 {7}</code><button class="copy-btn" onclick="cop
             <div class="card">
                 <div class="card-header">
                     <h2>🚨 Alertes IDS</h2>
+                    <span id="alerts-live" class="alerts-live">● LIVE</span>
                     <button class="btn neutral" onclick="refreshAlerts()" style="font-size: 0.75rem;">↻ Refresh</button>
                 </div>
                 <div class="card-body">
                     <div class="tabs">
-                        <button class="tab active" onclick="showAlertTab('snort')">🐷 Snort</button>
-                        <button class="tab" onclick="showAlertTab('suricata')">🦊 Suricata</button>
-                        <button class="tab" onclick="showAlertTab('zeek')">👁️ Zeek</button>
-                    </div>
-                    <div id="alerts-snort" class="alerts-panel active">
-                        <pre class="alerts-content" id="alerts-content-snort">Cliquez sur Refresh pour charger les alertes...</pre>
+                        {alert_tabs}
                     </div>
-                    <div id="alerts-suricata" class="alerts-panel">
-                        <pre class="alerts-content" id="alerts-content-suricata">Cliquez sur Refresh pour charger les alertes...</pre>
-                    </div>
-                    <div id="alerts-zeek" class="alerts-panel">
-                        <pre class="alerts-content" id="alerts-content-zeek">Cliquez sur Refresh pour charger les alertes...</pre>
+                    {alert_panels}
                     </div>
                 </div>
             </div>
@@ -778,7 +1066,7 @@ sudo hping3 -S -a 10.0.0.1 -p 80 {7}</code><button class="copy-btn" onclick="cop
                 <div class="card-body">
                     <table style="font-size: 0.8rem;">
                         <tr><th>Container</th><th>IP</th><th>Status</th><th></th></tr>
-                        {12}
+                        {service_rows}
                     </table>
                 </div>
             </div>
@@ -791,7 +1079,7 @@ sudo hping3 -S -a 10.0.0.1 -p 80 {7}</code><button class="copy-btn" onclick="cop
                 <div class="card-body">
                     <div class="info-callout">
                         <strong>Workflow:</strong><br>
-                        1. Démarrer un lab (snort/suricata/zeek)<br>
+                        1. Démarrer un lab<br>
                         2. Choisir un niveau de sécurité<br>
                         3. Lancer un scan nmap sur la cible<br>
                         4. Observer les alertes (EveBox/logs)<br>
@@ -806,21 +1094,11 @@ sudo hping3 -S -a 10.0.0.1 -p 80 {7}</code><button class="copy-btn" onclick="cop
     <script src="/static/js/dashboard.js"></script>
 </body>
 </html>"##,
-        if docker_ok { "ok" } else { "error" },
-        if docker_ok { "Docker OK" } else { "Docker Error" },
-        ids_rows,
-        target_rows,
-        snort_level,
-        suricata_level,
-        zeek_level,
-        suricata_target,  // Used in cookbook
-        if docker_ok { "ok" } else { "error" },
-        if docker_ok { "OK" } else { "Erreur" },
-        network_html,
-        interfaces_html,
-        service_rows,
-        lab_cards,  // {13} - Lab cards avec boutons grisés
-        visualizer_links  // {14} - Liens visualiseur dynamiques
+        docker_class = if docker_ok { "ok" } else { "error" },
+        docker_status = if docker_ok { "Docker OK" } else { "Docker Error" },
+        target = cookbook_target,
+        network_class = if docker_ok { "ok" } else { "error" },
+        network_status = if docker_ok { "OK" } else { "Erreur" },
     );
 
     Html(html)
@@ -837,8 +1115,90 @@ sudo hping3 -S -a 10.0.0.1 -p 80 {7}</code><button class="copy-btn" onclick="cop
 /// Génère la page du guide d'installation.
 /// Affiche le status Docker et les instructions de configuration.
 async fn setup_page() -> Html<String> {
-    let (docker_ok, docker_msg) = check_docker_permissions();
-    let username = std::env::var("USER").unwrap_or_else(|_| "user".into());
+    let snapshot = snapshot::current();
+    let (docker_ok, docker_msg) = (snapshot.docker_ok, &snapshot.docker_msg);
+    let username = escape(&std::env::var("USER").unwrap_or_else(|_| "user".into()));
+
+    // Vérifications de la machine hôte (preflight.rs), avec leur correction
+    let mut preflight_rows = String::new();
+    for check in preflight::run().await.checks {
+        let (class, label) = match check.status {
+            preflight::Status::Pass => ("ok", "pass"),
+            preflight::Status::Warn => ("warn", "warn"),
+            preflight::Status::Fail => ("error", "fail"),
+        };
+        preflight_rows.push_str(&format!(
+            r#"<tr><td><span class="status {}">{}</span></td><td><code>{}</code></td><td>{}{}</td></tr>"#,
+            class,
+            label,
+            escape(&check.name),
+            escape(&check.detail),
+            check
+                .remediation
+                .map(|r| format!(r#"<pre style="margin: 0.25rem 0 0;">{}</pre>"#, escape(&r)))
+                .unwrap_or_default()
+        ));
+    }
+
+    // Blocs propres aux labs, générés depuis labs.toml
+    let registry = labs::registry();
+    let root = get_project_root();
+    let mut lab_cards = String::new();
+    let mut down_commands = String::new();
+    for lab in registry.labs() {
+        let compose_dir = escape(&lab.compose_dir(&root).display().to_string());
+        lab_cards.push_str(&format!(
+            r#"
+    <div class="card">
+        <div class="card-body">
+            <h3>{}</h3>
+            <pre><code>cd {}
+docker compose up -d</code></pre>
+        </div>
+    </div>
+"#,
+            escape(&lab.title),
+            compose_dir
+        ));
+        down_commands.push_str(&format!("\ndocker compose -f {}/docker-compose.yml down", compose_dir));
+    }
+    let ids: Vec<(&Lab, String)> = registry
+        .labs()
+        .iter()
+        .filter_map(|l| Some((l, escape(l.ids_container.as_ref()?))))
+        .collect();
+    let containers: Vec<String> = registry
+        .labs()
+        .iter()
+        .flat_map(|l| [&l.ids_container, &l.target_container])
+        .flatten()
+        .map(|c| escape(c))
+        .collect();
+    let ids_logs = ids.iter().map(|(_, c)| format!("docker logs {}", c)).collect::<Vec<_>>().join("\n");
+    let useful_commands = format!(
+        "# Voir tous les containers du lab\ndocker ps -a | grep -E \"({})\"\n\n# Voir les logs d'un IDS\n{}\n\n# Redémarrer un container\ndocker restart {}\n\n# Arrêter tous les labs{}",
+        containers.join("|"),
+        ids.iter().map(|(_, c)| format!("docker logs -f {}", c)).collect::<Vec<_>>().join("\n"),
+        ids.first().map_or("&lt;container&gt;", |(_, c)| c.as_str()),
+        down_commands
+    );
+    let reload_commands = ids
+        .iter()
+        .filter_map(|(lab, container)| {
+            let command = match lab.reload? {
+                labs::ReloadMethod::Sighup => "docker kill -s SIGHUP",
+                labs::ReloadMethod::Sigusr2 => "docker kill -s USR2",
+                labs::ReloadMethod::Restart => "docker restart",
+            };
+            Some(format!("{} {}    # {}", command, container, escape(&lab.title)))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let target = registry
+        .labs()
+        .iter()
+        .find_map(|l| l.target_container.as_deref())
+        .map_or_else(|| "&lt;target&gt;".to_string(), escape);
 
     let html = format!(r##"<!DOCTYPE html>
 <html>
@@ -858,6 +1218,7 @@ async fn setup_page() -> Html<String> {
         .status {{ display: inline-block; padding: 4px 12px; border-radius: 20px; font-size: 0.85rem; font-weight: 500; }}
         .status.ok {{ background: rgba(34,197,94,0.2); color: var(--accent-green); }}
         .status.error {{ background: rgba(239,68,68,0.2); color: #ef4444; }}
+        .status.warn {{ background: rgba(234,179,8,0.2); color: var(--accent-yellow); }}
         .warning {{ background: rgba(234,179,8,0.15); border: 1px solid rgba(234,179,8,0.3); border-radius: 8px; padding: 1rem; margin: 1rem 0; }}
         .step {{ counter-increment: step; position: relative; padding-left: 3rem; margin: 1.5rem 0; }}
         .step::before {{ content: counter(step); position: absolute; left: 0; top: 0; width: 2rem; height: 2rem; background: var(--accent-blue); border-radius: 50%; display: flex; align-items: center; justify-content: center; font-weight: bold; font-size: 0.9rem; }}
@@ -874,8 +1235,13 @@ async fn setup_page() -> Html<String> {
     <div class="card">
         <div class="card-body">
             <h3>Status actuel</h3>
-            <p>Docker: <span class="status {}">{}</span></p>
-            <p style="font-size: 0.85rem; color: var(--text-secondary); margin-top: 0.5rem;">{}</p>
+            <p>Docker: <span class="status {docker_class}">{docker_status}</span></p>
+            <p style="font-size: 0.85rem; color: var(--text-secondary); margin-top: 0.5rem;">{docker_detail}</p>
+
+            <h3>Vérifications de la machine (<a href="/api/preflight">/api/preflight</a>, <code class="inline-code">ids-commander preflight</code>)</h3>
+            <table style="font-size: 0.8rem;">
+                <tbody>{preflight_rows}</tbody>
+            </table>
         </div>
     </div>
 
@@ -883,41 +1249,7 @@ async fn setup_page() -> Html<String> {
     <div class="card">
         <div class="card-body">
             <div class="mermaid">
-flowchart TB
-    subgraph HOST["🖥️ Machine Hôte"]
-        COMMANDER["Commander :3000"]
-        NMAP["Outils Attaque<br/>nmap, scapy, hping3"]
-    end
-
-    subgraph DOCKER["🐳 Docker Networks"]
-        subgraph SNORT_LAB["Snort Lab 172.28.0.0/24"]
-            S_IDS["snort_ids"]
-            S_TGT["target_snort"]
-            S_EDIT["snort_editor :8081"]
-        end
-
-        subgraph SURI_LAB["Suricata Lab 172.29.0.0/24"]
-            SU_IDS["suricata_ids"]
-            SU_TGT["target_suricata"]
-            SU_EDIT["suricata_editor :8082"]
-            EVEBOX["evebox :5636"]
-        end
-
-        subgraph ZEEK_LAB["Zeek Lab 172.30.0.0/24"]
-            Z_IDS["zeek_ids"]
-            Z_TGT["target_zeek"]
-            Z_EDIT["zeek_editor :8083"]
-        end
-    end
-
-    NMAP --> S_TGT
-    NMAP --> SU_TGT
-    NMAP --> Z_TGT
-
-    S_IDS -.-> S_TGT
-    SU_IDS -.-> SU_TGT
-    Z_IDS -.-> Z_TGT
-            </div>
+{diagram}            </div>
         </div>
     </div>
 
@@ -935,7 +1267,7 @@ flowchart TB
 
         <div class="step">
             <h3>Ajouter votre utilisateur au groupe</h3>
-            <pre><code>sudo usermod -aG docker {}</code></pre>
+            <pre><code>sudo usermod -aG docker {username}</code></pre>
         </div>
 
         <div class="step">
@@ -958,51 +1290,14 @@ flowchart TB
 sudo visudo
 
 # Ajouter ces lignes à la fin:
-{} ALL=(ALL) NOPASSWD: /usr/bin/docker
-{} ALL=(ALL) NOPASSWD: /usr/bin/nmap</code></pre>
+{username} ALL=(ALL) NOPASSWD: /usr/bin/docker
+{username} ALL=(ALL) NOPASSWD: /usr/bin/nmap</code></pre>
 
     <h2>3. Démarrage des Labs</h2>
-
-    <div class="card">
-        <div class="card-body">
-            <h3>Snort Lab</h3>
-            <pre><code>cd /home/rvv/Bureau/Rust.Nmap.Network/snort-lab
-docker compose up -d</code></pre>
-        </div>
-    </div>
-
-    <div class="card">
-        <div class="card-body">
-            <h3>Suricata Lab</h3>
-            <pre><code>cd /home/rvv/Bureau/Rust.Nmap.Network/suricata-lab
-docker compose up -d</code></pre>
-        </div>
-    </div>
-
-    <div class="card">
-        <div class="card-body">
-            <h3>Zeek Lab</h3>
-            <pre><code>cd /home/rvv/Bureau/Rust.Nmap.Network/zeek-lab
-docker compose up -d</code></pre>
-        </div>
-    </div>
-
+{lab_cards}
     <h2>4. Commandes utiles</h2>
 
-    <pre><code># Voir tous les containers du lab
-docker ps -a | grep -E "(snort|suricata|zeek|target|evebox)"
-
-# Voir les logs d'un IDS
-docker logs -f snort_ids
-docker logs -f suricata_ids
-
-# Redémarrer un container
-docker restart suricata_ids
-
-# Arrêter tous les labs
-docker compose -f /home/rvv/Bureau/Rust.Nmap.Network/snort-lab/docker-compose.yml down
-docker compose -f /home/rvv/Bureau/Rust.Nmap.Network/suricata-lab/docker-compose.yml down
-docker compose -f /home/rvv/Bureau/Rust.Nmap.Network/zeek-lab/docker-compose.yml down</code></pre>
+    <pre><code>{useful_commands}</code></pre>
 
     <h2>5. Dépannage</h2>
 
@@ -1010,7 +1305,7 @@ docker compose -f /home/rvv/Bureau/Rust.Nmap.Network/zeek-lab/docker-compose.yml
         <div class="card-body">
             <h3>L'IDS ne démarre pas</h3>
             <pre><code># Vérifier les logs
-docker logs snort_ids
+{ids_logs}
 
 # Problème de permissions
 sudo chmod 666 /var/run/docker.sock</code></pre>
@@ -1021,12 +1316,11 @@ sudo chmod 666 /var/run/docker.sock</code></pre>
         <div class="card-body">
             <h3>Les règles ne se rechargent pas</h3>
             <pre><code># Vérifier que le reloader fonctionne
-docker logs snort_reloader -f
+docker ps --filter name=reloader
+docker logs -f &lt;reloader&gt;
 
 # Forcer le rechargement manuel
-docker kill -s SIGHUP snort_ids    # Snort
-docker kill -s USR2 suricata_ids   # Suricata
-docker restart zeek_ids            # Zeek</code></pre>
+{reload_commands}</code></pre>
         </div>
     </div>
 
@@ -1037,10 +1331,10 @@ docker restart zeek_ids            # Zeek</code></pre>
 docker ps | grep target
 
 # Vérifier l'IP
-docker inspect target_suricata --format '{{{{range .NetworkSettings.Networks}}}}{{{{.IPAddress}}}}{{{{end}}}}'
+docker inspect {target} --format '{{{{range .NetworkSettings.Networks}}}}{{{{.IPAddress}}}}{{{{end}}}}'
 
 # Tester la connectivité
-ping -c 1 $(docker inspect target_suricata --format '{{{{range .NetworkSettings.Networks}}}}{{{{.IPAddress}}}}{{{{end}}}}')</code></pre>
+ping -c 1 $(docker inspect {target} --format '{{{{range .NetworkSettings.Networks}}}}{{{{.IPAddress}}}}{{{{end}}}}')</code></pre>
         </div>
     </div>
 
@@ -1089,15 +1383,53 @@ docker network prune -f</code></pre>
     <script src="/static/js/dashboard.js"></script>
 </body>
 </html>"##,
-        if docker_ok { "ok" } else { "error" },
-        if docker_ok { "Accessible sans sudo ✓" } else { "Nécessite configuration" },
-        if docker_ok { "Votre configuration Docker est correcte." } else { &docker_msg },
-        username, username, username
+        docker_class = if docker_ok { "ok" } else { "error" },
+        docker_status = if docker_ok { "Accessible sans sudo ✓" } else { "Nécessite configuration" },
+        docker_detail = if docker_ok { "Votre configuration Docker est correcte.".to_string() } else { escape(docker_msg) },
+        diagram = setup_diagram(registry),
     );
 
     Html(html)
 }
 
+/// Schéma mermaid des labs du registre: sous-réseau, IDS, cible et
+/// interfaces web (editor_url, dashboard_url).
+fn setup_diagram(registry: &labs::Registry) -> String {
+    let mut diagram = String::from(
+        r#"flowchart TB
+    subgraph HOST["🖥️ Machine Hôte"]
+        COMMANDER["Commander :3000"]
+        NMAP["Outils Attaque<br/>nmap, scapy, hping3"]
+    end
+
+    subgraph DOCKER["🐳 Docker Networks"]
+"#,
+    );
+    let mut links = String::new();
+    for (i, lab) in registry.labs().iter().enumerate() {
+        diagram.push_str(&format!("        subgraph LAB{}[\"{} {}\"]\n", i, escape(&lab.title), escape(&lab.subnet)));
+        if let Some(ids) = &lab.ids_container {
+            diagram.push_str(&format!("            IDS{}[\"{}\"]\n", i, escape(ids)));
+        }
+        if let Some(target) = &lab.target_container {
+            diagram.push_str(&format!("            TGT{}[\"{}\"]\n", i, escape(target)));
+            links.push_str(&format!("    NMAP --> TGT{}\n", i));
+            if lab.ids_container.is_some() {
+                links.push_str(&format!("    IDS{0} -.-> TGT{0}\n", i));
+            }
+        }
+        for (node, label, url) in [("EDIT", "Éditeur", &lab.editor_url), ("DASH", "Dashboard", &lab.dashboard_url)] {
+            if let Some(url) = url {
+                diagram.push_str(&format!("            {}{}[\"{} {}\"]\n", node, i, label, escape(url)));
+            }
+        }
+        diagram.push_str("        end\n");
+    }
+    diagram.push_str("    end\n\n");
+    diagram.push_str(&links);
+    diagram
+}
+
 // ============================================================================
 // ACTIONS SUR LES CONTAINERS
 // ============================================================================
@@ -1105,82 +1437,339 @@ docker network prune -f</code></pre>
 // Ils utilisent Bollard pour communiquer avec l'API Docker.
 // Après chaque action, ils renvoient le dashboard complet (pattern HTMX).
 
-/// Démarre un container Docker par son ID (court ou long).
-/// Appelé via POST /start/{id}
-async fn start_container(Path(id): Path<String>) -> Html<String> {
-    let docker = Docker::connect_with_local_defaults().unwrap();
-    let _ = docker.start_container(&id, None::<StartContainerOptions<String>>).await;
-    // Attendre que Docker mette à jour l'état
-    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-    dashboard().await
+/// Démarre un container d'un lab par son ID (court ou long) ou son nom.
+/// Appelé via POST /start/{id} (404 hors des labs, voir extract.rs)
+async fn start_container(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    LabContainer(container): LabContainer,
+) -> Response {
+    let _guard = match lock_container(&state, &user, client, "container.start", &container).await {
+        Ok(guard) => guard,
+        Err(lock) => return lab_busy(lock),
+    };
+    let result = async {
+        config::docker()?
+            .start_container(&container.id, None::<StartContainerOptions<String>>)
+            .await
+    }
+    .await;
+    container_action(&state, &user, client, "container.start", &container, result).await
 }
 
-/// Arrête un container Docker par son ID.
+/// Arrête un container d'un lab.
 /// Appelé via POST /stop/{id}
-async fn stop_container(Path(id): Path<String>) -> Html<String> {
-    let docker = Docker::connect_with_local_defaults().unwrap();
+async fn stop_container(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    LabContainer(container): LabContainer,
+) -> Response {
+    let _guard = match lock_container(&state, &user, client, "container.stop", &container).await {
+        Ok(guard) => guard,
+        Err(lock) => return lab_busy(lock),
+    };
     // Attendre jusqu'à 10 secondes pour l'arrêt
     let options = StopContainerOptions { t: 10 };
-    let _ = docker.stop_container(&id, Some(options)).await;
-    // Attendre que Docker mette à jour l'état
-    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-    dashboard().await
+    let result = async { config::docker()?.stop_container(&container.id, Some(options)).await }.await;
+    container_action(&state, &user, client, "container.stop", &container, result).await
 }
 
-/// Redémarre un container Docker par son ID.
+/// Redémarre un container d'un lab.
 /// Appelé via POST /restart/{id}
-async fn restart_container(Path(id): Path<String>) -> Html<String> {
-    let docker = Docker::connect_with_local_defaults().unwrap();
-    let _ = docker.restart_container(&id, None).await;
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-    dashboard().await
+async fn restart_container(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    LabContainer(container): LabContainer,
+) -> Response {
+    let _guard = match lock_container(&state, &user, client, "container.restart", &container).await {
+        Ok(guard) => guard,
+        Err(lock) => return lab_busy(lock),
+    };
+    let result = async { config::docker()?.restart_container(&container.id, None).await }.await;
+    container_action(&state, &user, client, "container.restart", &container, result).await
+}
+
+/// Verrou du lab d'un container pendant l'action: pas d'arrêt de l'IDS au
+/// milieu d'une matrice d'évasion ou d'un job. Un refus est audité.
+async fn lock_container(
+    state: &AppState,
+    user: &Identity,
+    client: SocketAddr,
+    action: &str,
+    container: &ContainerInfo,
+) -> Result<LabGuard, LabLockState> {
+    let operation = format!("{} {}", action, container.name);
+    let result = state.locks.try_acquire(&container.lab, &operation, &user.client(client));
+    if let Err(lock) = &result {
+        let params = serde_json::json!({ "container": container.name });
+        let error = ApiError::Locked(lock.clone());
+        audit(state, AuditEntry::new(user, client, action, &container.lab, params, Err(&error))).await;
+    }
+    result
+}
+
+/// Fin commune des actions sur un container: journal d'audit, relecture
+/// de l'état Docker et dashboard.
+async fn container_action(
+    state: &AppState,
+    user: &Identity,
+    client: SocketAddr,
+    action: &str,
+    container: &ContainerInfo,
+    result: Result<(), bollard::errors::Error>,
+) -> Response {
+    let result = result.map_err(|e| ApiError::Internal(format!("Docker error: {}", e)));
+    if let Err(e) = &result {
+        eprintln!("{} {}: {}", action, container.name, e.message());
+    }
+    let params = serde_json::json!({ "container": container.name });
+    audit(state, AuditEntry::new(user, client, action, &container.lab, params, result.as_ref().map(|_| None))).await;
+    snapshot::refresh().await;
+    dashboard().await.into_response()
 }
 
-/// Applique un template de règles à un IDS.
-/// Copie le fichier template levelX vers le fichier de règles actif.
-/// Le reloader détectera le changement et rechargera l'IDS.
+/// Applique un template de règles à un lab.
+/// Copie le fichier template levelX (template_dir) vers le fichier de
+/// règles actif (active_rules). Le reloader détectera le changement et
+/// rechargera l'IDS.
 ///
 /// Arguments:
-/// - ids: "snort", "suricata", ou "zeek"
-/// - level: "1" à "5"
+/// - lab: nom du lab dans labs.toml
+/// - level: "1" à "5" ou nom d'un template personnalisé (templates.rs)
 ///
-/// Chemins des templates:
-/// - commander/templates/snort/level{1-5}_*.rules
-/// - commander/templates/suricata/level{1-5}_*.rules
-/// - commander/templates/zeek/level{1-5}_*.zeek
-async fn apply_template(Path((ids, level)): Path<(String, String)>) -> Html<String> {
-    let root = get_project_root();
+/// 400 pour un niveau mal formé, 404 pour un lab ou un template inconnu,
+/// 500 si le fichier de règles ne peut pas être écrit, 409 si le lab est
+/// occupé (job docker compose, matrice d'évasion), 403 pour un étudiant
+/// hors de ses labs.
+async fn apply_template(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    TemplateParams { lab, level }: TemplateParams,
+) -> Response {
+    let result = {
+        let (state, user, level) = (state.clone(), user.clone(), level.clone());
+        blocking(move || install_level(&state, &user, client, lab, &level)).await
+    };
+    let params = serde_json::json!({ "level": level.to_string() });
+    audit(&state, AuditEntry::new(&user, client, "rules.apply", &lab.name, params, result.as_ref().map(|_| None))).await;
+
+    match result {
+        Ok(dest_file) => println!(
+            "✓ Applied {} level {} -> {:?} (reload: {})",
+            lab.name,
+            level,
+            dest_file,
+            lab.reload.map_or("none", |r| r.as_str())
+        ),
+        Err(ApiError::Locked(lock)) => return lab_busy(lock),
+        Err(e) => {
+            eprintln!("{}", e.message());
+            return e.into_response();
+        }
+    }
 
-    let (src_pattern, dest_file) = match ids.as_str() {
-        "snort" => (format!("level{}", level), root.join("snort-lab/config/local.rules")),
-        "suricata" => (format!("level{}", level), root.join("suricata-lab/rules/local.rules")),
-        "zeek" => (format!("level{}", level), root.join("zeek-lab/scripts/local.zeek")),
-        _ => return Html("<p>Unknown IDS</p>".to_string()),
+    dashboard().await.into_response()
+}
+
+/// Installe un niveau sous le verrou du lab. Retourne le fichier de règles écrit.
+fn install_level(
+    state: &AppState,
+    user: &Identity,
+    client: SocketAddr,
+    lab: &Lab,
+    level: &templates::Level,
+) -> Result<PathBuf, ApiError> {
+    user.check_lab(&lab.name)?;
+    let operation = format!("apply level {}", level);
+    let _guard = state
+        .locks
+        .try_acquire(&lab.name, &operation, &user.client(client))
+        .map_err(ApiError::Locked)?;
+    save_rules(state, lab, user, &operation)?;
+    templates::install(&get_project_root(), lab, level).map_err(ApiError::Internal)
+}
+
+/// Conserve les règles actives d'un lab dans l'historique avant leur
+/// remplacement (history.rs). Rien à conserver si le fichier n'existe pas;
+/// une erreur annule le remplacement.
+fn save_rules(state: &AppState, lab: &Lab, user: &Identity, reason: &str) -> Result<(), ApiError> {
+    let Some(path) = lab.rules_path(&get_project_root()) else { return Ok(()) };
+    let Some(content) = read_rules(&path)? else { return Ok(()) };
+    if let Some(version) = state.store.save_rule_version(&lab.name, &user.name, reason, &content)? {
+        println!("✓ Saved {} rules as version #{} ({})", lab.name, version.id, reason);
+    }
+    Ok(())
+}
+
+/// Enregistre une action dans le journal d'audit (audit.rs).
+/// Une erreur d'écriture n'annule pas l'action déjà effectuée.
+async fn audit(state: &AppState, entry: AuditEntry) {
+    let store = state.store.clone();
+    blocking(move || {
+        if let Err(e) = store.record_audit(&entry) {
+            eprintln!("Error recording audit entry {} on {}: {}", entry.action, entry.lab, e);
+        }
+    })
+    .await
+}
+
+/// Exécute un accès bloquant (SQLite, fichiers de règles ou de logs, `ip`)
+/// sur le pool dédié de tokio, hors des threads du runtime.
+async fn blocking<T, F>(work: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(work).await {
+        Ok(value) => value,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+/// Versions enregistrées des règles actives d'un lab, de la plus récente
+/// à la plus ancienne (sans leur contenu).
+/// Appelé via GET /api/rules/:lab/history
+async fn api_rules_history(
+    State(state): State<AppState>,
+    LabParam(lab): LabParam,
+) -> Result<Json<Vec<RuleVersion>>, ApiError> {
+    Ok(Json(blocking(move || state.store.rule_versions(&lab.name)).await?))
+}
+
+/// Une version des règles d'un lab, avec son contenu.
+/// Appelé via GET /api/rules/:lab/history/:id
+async fn api_rules_version(
+    State(state): State<AppState>,
+    RuleVersionParams { lab, id }: RuleVersionParams,
+) -> Result<Json<RuleVersion>, ApiError> {
+    Ok(Json(blocking(move || find_rule_version(&state, lab, id)).await?))
+}
+
+/// Diff unifié entre deux versions des règles d'un lab ("current": fichier actif).
+/// Appelé via GET /api/rules/:lab/diff?from=..&to=..
+///
+/// Par défaut: dernière version enregistrée -> fichier actif, soit ce qu'a
+/// changé le dernier niveau appliqué ou les retouches faites depuis.
+async fn api_rules_diff(
+    State(state): State<AppState>,
+    LabParam(lab): LabParam,
+    query: Result<Query<DiffQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let from = query.from.as_deref().map(str::parse::<Side>).transpose().map_err(ApiError::BadRequest)?;
+    let to = query.to.as_deref().unwrap_or("current").parse::<Side>().map_err(ApiError::BadRequest)?;
+    let diff = blocking(move || -> Result<String, ApiError> {
+        let from = match from {
+            Some(from) => from,
+            None => state
+                .store
+                .rule_versions(&lab.name)?
+                .first()
+                .map(|v| Side::Version(v.id))
+                .ok_or_else(|| ApiError::NotFound(format!("No saved rules for {}", lab.name)))?,
+        };
+        Ok(history::unified_diff(
+            &rules_content(&state, lab, from)?,
+            &rules_content(&state, lab, to)?,
+            &format!("{} {}", lab.name, from),
+            &format!("{} {}", lab.name, to),
+        ))
+    })
+    .await?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], diff).into_response())
+}
+
+/// Remet en place une version des règles d'un lab. Le contenu remplacé
+/// est lui-même conservé: un retour arrière se défait de la même façon.
+/// Appelé via POST /api/rules/:lab/rollback/:id
+///
+/// 404 pour une version inconnue, 409 si le lab est occupé, 403 pour un
+/// étudiant hors de ses labs.
+async fn api_rules_rollback(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    RuleVersionParams { lab, id }: RuleVersionParams,
+) -> Result<Json<RuleVersion>, ApiError> {
+    let result = {
+        let (state, user) = (state.clone(), user.clone());
+        blocking(move || rollback_rules(&state, &user, client, lab, id)).await
+    };
+    let params = serde_json::json!({ "version": id });
+    audit(&state, AuditEntry::new(&user, client, "rules.rollback", &lab.name, params, result.as_ref().map(|_| None))).await;
+    let version = result?;
+    println!("✓ Rolled back {} rules to version #{}", lab.name, id);
+    Ok(Json(RuleVersion { content: None, ..version }))
+}
+
+/// Réécrit le fichier de règles actif avec une version, sous le verrou du lab.
+fn rollback_rules(
+    state: &AppState,
+    user: &Identity,
+    client: SocketAddr,
+    lab: &Lab,
+    id: u64,
+) -> Result<RuleVersion, ApiError> {
+    user.check_lab(&lab.name)?;
+    let Some(path) = lab.rules_path(&get_project_root()) else {
+        return Err(ApiError::NotFound(format!("Lab {} has no rules", lab.name)));
     };
+    let version = find_rule_version(state, lab, id)?;
+    let operation = format!("rollback to #{}", id);
+    let _guard = state
+        .locks
+        .try_acquire(&lab.name, &operation, &user.client(client))
+        .map_err(ApiError::Locked)?;
+    save_rules(state, lab, user, &operation)?;
+    fs::write(&path, version.content.as_deref().unwrap_or_default())
+        .map_err(|e| ApiError::Internal(format!("Cannot write {}: {}", path.display(), e)))?;
+    Ok(version)
+}
 
-    let template_dir = root.join(format!("commander/templates/{}", ids));
+fn find_rule_version(state: &AppState, lab: &Lab, id: u64) -> Result<RuleVersion, ApiError> {
+    state
+        .store
+        .rule_version(&lab.name, id)?
+        .ok_or_else(|| ApiError::NotFound(format!("No rules version {} for {}", id, lab.name)))
+}
 
-    if let Ok(entries) = fs::read_dir(&template_dir) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let filename = entry.file_name().to_string_lossy().to_string();
-            if filename.starts_with(&src_pattern) {
-                if let Err(e) = fs::copy(entry.path(), &dest_file) {
-                    eprintln!("Error copying template: {}", e);
-                } else {
-                    println!("✓ Applied {} level {} -> {:?}", ids, level, dest_file);
-                }
-                break;
-            }
+/// Contenu d'un côté d'un diff: version enregistrée ou fichier actif (vide s'il manque).
+fn rules_content(state: &AppState, lab: &Lab, side: Side) -> Result<String, ApiError> {
+    match side {
+        Side::Version(id) => Ok(find_rule_version(state, lab, id)?.content.unwrap_or_default()),
+        Side::Current => {
+            let path = lab
+                .rules_path(&get_project_root())
+                .ok_or_else(|| ApiError::NotFound(format!("Lab {} has no rules", lab.name)))?;
+            Ok(read_rules(&path)?.unwrap_or_default())
         }
     }
+}
+
+/// Lit un fichier de règles actif (None s'il n'existe pas). 409 s'il n'est
+/// pas en UTF-8: l'historique ne conserve pas de copie altérée.
+fn read_rules(path: &std::path::Path) -> Result<Option<String>, ApiError> {
+    match fs::read(path) {
+        Ok(content) => history::rules_text(&path.display().to_string(), content).map(Some).map_err(ApiError::Conflict),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ApiError::Internal(format!("Cannot read {}: {}", path.display(), e))),
+    }
+}
 
-    dashboard().await
+/// Réponse 409 des actions HTMX sur un lab occupé (message affiché en toast).
+fn lab_busy(lock: LabLockState) -> Response {
+    (StatusCode::CONFLICT, lock.message()).into_response()
 }
 
-/// Récupère les 100 dernières lignes de logs d'un container.
-/// Utilise l'API Docker via Bollard.
-async fn get_logs(Path(id): Path<String>) -> Html<String> {
-    let docker = match Docker::connect_with_local_defaults() {
+/// Récupère les 100 dernières lignes de logs d'un container d'un lab.
+/// Utilise l'API Docker via Bollard. Les logs sont échappés: ils contiennent
+/// des chemins et en-têtes HTTP choisis par l'attaquant.
+async fn get_logs(LabContainer(container): LabContainer) -> Html<String> {
+    let docker = match config::docker() {
         Ok(d) => d,
         Err(_) => return Html("<pre>Error connecting to Docker</pre>".to_string()),
     };
@@ -1192,39 +1781,64 @@ async fn get_logs(Path(id): Path<String>) -> Html<String> {
         ..Default::default()
     };
 
-    let mut logs = docker.logs(&id, Some(options));
+    let mut logs = docker.logs(&container.id, Some(options));
     let mut output = String::new();
 
     while let Some(Ok(log)) = logs.next().await {
         output.push_str(&log.to_string());
     }
 
-    Html(format!("<pre style='background:#000;padding:1rem;border-radius:8px;max-height:400px;overflow:auto;font-size:0.8rem;'>{}</pre>", output))
+    Html(format!("<pre style='background:#000;padding:1rem;border-radius:8px;max-height:400px;overflow:auto;font-size:0.8rem;'>{}</pre>", escape(&output)))
 }
 
-/// Réinitialise les logs d'un IDS (supprime et recrée le répertoire).
+/// Réinitialise les logs d'un lab (supprime et recrée logs_dir).
 /// Utile pour repartir d'un état propre avant un test.
-async fn reset_logs(Path(ids): Path<String>) -> Html<String> {
-    let root = get_project_root();
-    let logs_dir = match ids.as_str() {
-        "suricata" => root.join("suricata-lab/logs"),
-        _ => return dashboard().await,
+/// 404 pour un lab inconnu ou sans logs_dir.
+async fn reset_logs(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    LabParam(lab): LabParam,
+) -> Response {
+    let Some(logs_dir) = lab.logs_dir(&get_project_root()) else {
+        return ApiError::NotFound(format!("Lab {} has no logs_dir", lab.name)).into_response();
     };
+    let result = match state.locks.try_acquire(&lab.name, "reset logs", &user.client(client)) {
+        Err(lock) => Err(ApiError::Locked(lock)),
+        Ok(guard) => {
+            let logs_dir = logs_dir.clone();
+            blocking(move || {
+                let _guard = guard;
+                match logs_dir.exists() {
+                    true => fs::remove_dir_all(&logs_dir)
+                        .and_then(|_| fs::create_dir_all(&logs_dir))
+                        .map_err(|e| ApiError::Internal(format!("Cannot reset {}: {}", logs_dir.display(), e))),
+                    false => Ok(()),
+                }
+            })
+            .await
+        }
+    };
+    let params = serde_json::json!({ "logs_dir": logs_dir });
+    audit(&state, AuditEntry::new(&user, client, "logs.reset", &lab.name, params, result.as_ref().map(|_| None))).await;
 
-    if logs_dir.exists() {
-        let _ = fs::remove_dir_all(&logs_dir);
-        let _ = fs::create_dir_all(&logs_dir);
-        println!("✓ Reset logs for {}", ids);
+    match result {
+        Ok(()) => println!("✓ Reset logs for {}", lab.name),
+        Err(ApiError::Locked(lock)) => return lab_busy(lock),
+        Err(e) => {
+            eprintln!("{}", e.message());
+            return e.into_response();
+        }
     }
 
-    dashboard().await
+    dashboard().await.into_response()
 }
 
 async fn system_info() -> Html<String> {
-    let bridges = get_bridge_interfaces();
+    let bridges = blocking(get_bridge_interfaces).await;
     let mut html = String::from("<h3>Bridge Interfaces</h3><ul>");
     for (iface, ip) in bridges {
-        html.push_str(&format!("<li><code>{}</code>: {}</li>", iface, ip));
+        html.push_str(&format!("<li><code>{}</code>: {}</li>", escape(&iface), escape(&ip)));
     }
     html.push_str("</ul>");
     Html(html)
@@ -1232,206 +1846,929 @@ async fn system_info() -> Html<String> {
 
 /// Retourne le status des containers en JSON pour le polling JS.
 /// Appelé via GET /api/status
-async fn api_status() -> axum::Json<Vec<HashMap<String, String>>> {
-    let containers = get_container_details();
-    axum::Json(containers)
+///
+/// Pour chaque container: état, santé, code de sortie, IPs par réseau et labels.
+/// Lu dans l'instantané (snapshot.rs): 304 si If-None-Match correspond à l'ETag.
+async fn api_status(headers: HeaderMap) -> Response {
+    let snapshot = snapshot::current();
+    let etag = snapshot.etag();
+    let cache = [(header::ETAG, etag.clone()), (header::CACHE_CONTROL, "no-cache".to_string())];
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|t| t.trim().trim_start_matches("W/") == etag || t.trim() == "*"));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, cache).into_response();
+    }
+    (cache, Json(&snapshot.containers)).into_response()
 }
 
 /// Health check endpoint pour les tests automatisés.
-/// Retourne l'état du système: Docker, labs actifs, etc.
+/// Retourne l'état du système: Docker, labs actifs, readiness de chaque
+/// lab (ready/degraded/failed/stopped et raisons), etc.
 async fn api_health() -> axum::Json<serde_json::Value> {
-    // Vérifier Docker
-    let docker_ok = tokio::process::Command::new("docker")
-        .args(["version"])
-        .output()
-        .await
-        .map(|o| o.status.success())
-        .unwrap_or(false);
-
-    // Récupérer les containers actifs
-    let containers = get_container_details();
-
-    // Détecter les labs actifs
-    let snort_running = containers.iter().any(|c| {
-        c.get("name").map(|n| n.starts_with("snort_")).unwrap_or(false)
-        && c.get("status").map(|s| s.contains("Up")).unwrap_or(false)
-    });
-    let suricata_running = containers.iter().any(|c| {
-        c.get("name").map(|n| n.starts_with("suricata_")).unwrap_or(false)
-        && c.get("status").map(|s| s.contains("Up")).unwrap_or(false)
-    });
-    let zeek_running = containers.iter().any(|c| {
-        c.get("name").map(|n| n.starts_with("zeek_")).unwrap_or(false)
-        && c.get("status").map(|s| s.contains("Up")).unwrap_or(false)
-    });
+    // Docker, containers et labs actifs: instantané de snapshot.rs
+    let snapshot = snapshot::current();
+    // Sondes des labs démarrés (readiness.rs)
+    let readiness = readiness::current();
+    let labs_ok = readiness.values().all(|r| r.state <= readiness::Readiness::Ready);
 
     axum::Json(serde_json::json!({
-        "status": if docker_ok { "healthy" } else { "degraded" },
-        "docker": docker_ok,
-        "labs": {
-            "snort": snort_running,
-            "suricata": suricata_running,
-            "zeek": zeek_running
-        },
-        "containers_count": containers.len(),
+        "status": if snapshot.docker_ok && labs_ok { "healthy" } else { "degraded" },
+        "docker": snapshot.docker_ok,
+        "labs": snapshot.labs,
+        "readiness": readiness,
+        "containers_count": snapshot.containers.len(),
+        "snapshot_updated_at": snapshot.updated_at,
         "timestamp": chrono::Utc::now().to_rfc3339()
     }))
 }
 
-/// Retourne les dernières alertes d'un IDS en JSON.
-/// Appelé via GET /api/alerts/:ids (snort, suricata, zeek)
-async fn api_alerts(Path(ids): Path<String>) -> axum::Json<serde_json::Value> {
-    let container_name = format!("{}_ids", ids);
+/// Vérifications préalables de la machine hôte (preflight.rs): socket
+/// Docker, compose, nmap/hping3, sous-réseaux, ports, AppArmor.
+/// Appelé via GET /api/preflight
+async fn api_preflight() -> Json<preflight::Report> {
+    Json(preflight::run().await)
+}
 
-    // Récupérer les logs via docker logs
-    let output = tokio::process::Command::new("docker")
-        .args(["logs", "--tail", "50", &container_name])
-        .output()
-        .await;
+/// Chronologie des containers des labs (start, die, kill, health_status, oom).
+/// Appelé via GET /api/events?lab=..&container=..&action=..&since=..&until=..&limit=..
+///
+/// Du plus récent au plus ancien; sert à corréler une alerte manquante
+/// avec un redémarrage de l'IDS ou un SIGHUP du reloader (events.rs).
+async fn api_events(
+    State(state): State<AppState>,
+    query: Result<Query<EventQuery>, QueryRejection>,
+) -> Result<Json<Vec<EventRecord>>, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    Ok(Json(blocking(move || state.store.events(&query)).await?))
+}
 
-    match output {
-        Ok(o) => {
-            let stdout = String::from_utf8_lossy(&o.stdout);
-            let stderr = String::from_utf8_lossy(&o.stderr);
-
-            // Combiner stdout et stderr (certains IDS écrivent sur stderr)
-            let combined = format!("{}{}", stdout, stderr);
-
-            // Filtrer les lignes qui ressemblent à des alertes
-            let alerts: Vec<&str> = combined
-                .lines()
-                .filter(|line| {
-                    // Filtrer les lignes intéressantes selon l'IDS
-                    match ids.as_str() {
-                        "snort" => line.contains("alert") || line.contains("ALERT") || line.contains("[**]"),
-                        "suricata" => line.contains("alert") || line.contains("Alert") || line.contains("event_type"),
-                        "zeek" => !line.starts_with('#') && line.len() > 10,
-                        _ => true,
-                    }
-                })
-                .take(30)
-                .collect();
-
-            axum::Json(serde_json::json!({
-                "ids": ids,
-                "container": container_name,
-                "alerts": alerts,
-                "count": alerts.len()
-            }))
+/// Recherche dans les alertes de tous les IDS.
+/// Appelé via GET /api/alerts?sid=..&since=..&until=..&src_ip=..&dst_ip=..
+///                            &dst_port=..&priority=..&q=..&cursor=..&limit=..&order=asc|desc
+///
+/// Les alertes sont lues directement dans les fichiers de logs montés
+/// (eve.json, alert_fast.txt, notice.log) puis converties en alertes
+/// structurées (voir alerts.rs, tail.rs et query.rs).
+async fn api_alerts_search(
+    State(state): State<AppState>,
+    query: Result<Query<AlertQuery>, QueryRejection>,
+) -> Result<Json<AlertPage>, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    Ok(Json(query_alerts(&state, query).await?))
+}
+
+/// Recherche dans les alertes d'un IDS donné.
+/// Appelé via GET /api/alerts/:ids (snort, suricata, zeek), mêmes filtres que /api/alerts.
+async fn api_alerts(
+    State(state): State<AppState>,
+    EngineParam(engine): EngineParam,
+    query: Result<Query<AlertQuery>, QueryRejection>,
+) -> Result<Json<AlertPage>, ApiError> {
+    let Query(mut query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    query.engine = Some(engine);
+    Ok(Json(query_alerts(&state, query).await?))
+}
+
+async fn query_alerts(state: &AppState, query: AlertQuery) -> Result<AlertPage, ApiError> {
+    let store = state.store.clone();
+    Ok(blocking(move || store.query(&query)).await?)
+}
+
+/// Flux SSE des nouvelles alertes, au fil de leur écriture dans les logs IDS.
+/// Appelé via GET /api/alerts/stream?engine=suricata (tous les IDS si absent)
+///
+/// Accepte les mêmes filtres que /api/alerts (sid, src_ip, q...).
+/// Chaque alerte est un événement `alert` dont `data` est l'objet JSON
+/// de l'API et `id` l'identifiant d'ingestion.
+async fn api_alerts_stream(
+    State(state): State<AppState>,
+    query: Result<Query<AlertQuery>, QueryRejection>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let rx = state.alerts_tx.subscribe();
+
+    let stream = futures_util::stream::unfold((rx, query), |(mut rx, query)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(record) if query.matches(&record.alert) => {
+                    let event = Event::default()
+                        .event("alert")
+                        .id(record.id.to_string())
+                        .json_data(&record)
+                        .unwrap_or_default();
+                    return Some((Ok(event), (rx, query)));
+                }
+                Ok(_) => continue,
+                // Client trop lent: les alertes manquées restent disponibles via /api/alerts
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
         }
-        Err(e) => {
-            axum::Json(serde_json::json!({
-                "ids": ids,
-                "error": format!("Failed to get logs: {}", e),
-                "alerts": [],
-                "count": 0
-            }))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Tâche de fond: lit les fichiers d'alertes chaque seconde, les enregistre
+/// dans l'historique SQLite et diffuse les nouvelles alertes aux clients SSE.
+async fn ingest_alerts(state: AppState) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+        // Lecture des fichiers et écriture SQLite: hors des threads du runtime
+        let (tailer, store) = (state.tailer.clone(), state.store.clone());
+        let ingested = tokio::task::spawn_blocking(move || {
+            let (fresh, positions) = {
+                let mut tailer = tailer.lock().unwrap();
+                let fresh = tailer.poll();
+                (fresh, tailer.positions())
+            };
+            store.ingest(fresh, &positions)
+        })
+        .await;
+        match ingested {
+            Ok(Ok(records)) => {
+                for record in records {
+                    // Erreur = aucun client connecté, rien à faire
+                    let _ = state.alerts_tx.send(record);
+                }
+            }
+            Ok(Err(e)) => eprintln!("Error storing alerts: {}", e),
+            Err(e) => eprintln!("Error storing alerts: {}", e),
+        }
+    }
+}
+
+/// Tâche de fond: applique la politique de rétention toutes les heures.
+async fn purge_alerts(store: Arc<AlertStore>, retention: Retention) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
+    loop {
+        interval.tick().await;
+        let store = store.clone();
+        match tokio::task::spawn_blocking(move || store.purge(retention)).await {
+            Ok(Ok(0)) => {}
+            Ok(Ok(n)) => println!("✓ Purged {} old alerts", n),
+            Ok(Err(e)) => eprintln!("Error purging alerts: {}", e),
+            Err(e) => eprintln!("Error purging alerts: {}", e),
         }
     }
 }
 
 // ============================================================================
-// GESTION DES LABS
+// SESSIONS D'ATTAQUE (RUNS)
 // ============================================================================
-// Ces handlers démarrent ou arrêtent un lab complet via docker compose.
-// Un lab = tous les containers définis dans le docker-compose.yml du lab.
+// Un run encadre une attaque (scan nmap...) pour retrouver les alertes
+// qu'elle a déclenchées au niveau de règles alors actif (voir runs.rs).
+
+/// Liste des runs, du plus récent au plus ancien.
+/// Appelé via GET /api/runs
+async fn api_runs(State(state): State<AppState>) -> Result<Json<Vec<Run>>, ApiError> {
+    Ok(Json(blocking(move || state.store.runs()).await?))
+}
 
-/// Démarre un lab complet via `docker compose up -d`.
-/// Appelé via POST /lab/start/{lab}
+/// Démarre un run et mémorise le niveau de règles actif du moteur.
+/// Appelé via POST /api/runs avec {"engine": "...", "target": "IP", "command": "..."}
+async fn api_run_start(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    body: Result<Json<RunStart>, JsonRejection>,
+) -> Result<Json<Run>, ApiError> {
+    let Json(start) = body.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let result = match check_engine_lab(&user, start.engine) {
+        Ok(()) => {
+            let (store, start) = (state.store.clone(), start.clone());
+            blocking(move || {
+                let rule_level = engine_rule_level(start.engine);
+                Ok(store.start_run(start.engine, &rule_level, start.target, &start.command)?)
+            })
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    let lab = labs::registry().for_engine(start.engine).map_or(start.engine.as_str(), |l| l.name.as_str());
+    let params = serde_json::json!({ "engine": start.engine.as_str(), "target": start.target, "command": start.command });
+    let detail = result.as_ref().map(|run| Some(format!("run #{}", run.id)));
+    audit(&state, AuditEntry::new(&user, client, "run.start", lab, params, detail)).await;
+    let run = result?;
+    println!("✓ Run {} started ({} level {}, target {})", run.id, run.engine, run.rule_level, run.target);
+    Ok(Json(run))
+}
+
+/// Clôt un run en cours: sa fenêtre d'alertes est désormais figée.
+/// Appelé via POST /api/runs/:id/stop
+async fn api_run_stop(
+    State(state): State<AppState>,
+    Extension(user): Extension<Identity>,
+    Path(id): Path<u64>,
+) -> Result<Json<Run>, ApiError> {
+    let run = find_run(&state, id).await?;
+    check_engine_lab(&user, run.engine)?;
+    if state.executions.is_running(run.id) {
+        return Err(ApiError::Conflict(format!("Run {} is an attack still in progress", id)));
+    }
+    let store = state.store.clone();
+    if !blocking(move || store.stop_run(run.id, None)).await? {
+        return Err(ApiError::Conflict(format!("Run {} already stopped", id)));
+    }
+    Ok(Json(find_run(&state, id).await?))
+}
+
+/// Détail d'un run avec le nombre d'alertes par SID dans sa fenêtre.
+/// Appelé via GET /api/runs/:id
+async fn api_run(State(state): State<AppState>, Path(id): Path<u64>) -> Result<Json<RunSummary>, ApiError> {
+    let run = find_run(&state, id).await?;
+    let sids = {
+        let run = run.clone();
+        blocking(move || state.store.sid_counts(&run)).await?
+    };
+    Ok(Json(RunSummary {
+        total_alerts: sids.iter().map(|s| s.count).sum(),
+        sids,
+        run,
+    }))
+}
+
+/// Alertes déclenchées pendant un run.
+/// Appelé via GET /api/runs/:id/alerts, mêmes filtres que /api/alerts.
 ///
-/// Arguments:
-/// - lab: "snort", "suricata", ou "zeek"
+/// Le moteur et la fenêtre [since, until] sont imposés par le run; un
+/// since/until fourni ne peut que la restreindre.
+async fn api_run_alerts(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    query: Result<Query<AlertQuery>, QueryRejection>,
+) -> Result<Json<AlertPage>, ApiError> {
+    let run = find_run(&state, id).await?;
+    let Query(mut query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    query.engine = Some(run.engine);
+    query.since = Some(query.since.map_or(run.started_at, |t| t.max(run.started_at)));
+    query.until = Some(query.until.map_or(run.window_end(), |t| t.min(run.window_end())));
+    Ok(Json(query_alerts(&state, query).await?))
+}
+
+/// Catalogue des techniques exécutables par le commander.
+/// Appelé via GET /api/attacks
+async fn api_attacks() -> Json<&'static [Technique]> {
+    Json(attack::TECHNIQUES)
+}
+
+/// Lance une technique du cookbook contre la cible d'un lab.
+/// Appelé via POST /api/attacks avec {"lab": "suricata", "technique": "mtu8"}
 ///
-/// Exécute: docker compose up -d dans le répertoire {lab}-lab/
-async fn start_lab(Path(lab): Path<String>) -> Html<String> {
-    let root = get_project_root();
-    let lab_dir = root.join(format!("{}-lab", lab));
-
-    // Utiliser tokio::process::Command pour l'exécution async
-    // --build: reconstruit les images si nécessaire (target-server, etc.)
-    // --remove-orphans: nettoie les containers orphelins d'une exécution précédente
-    let output = tokio::process::Command::new("docker")
-        .args(["compose", "up", "-d", "--build", "--remove-orphans"])
-        .current_dir(&lab_dir)
-        .output()
-        .await;
+/// Retourne immédiatement le run ouvert; la sortie de nmap se suit sur
+/// GET /api/runs/:id/output et le run est clos avec le code de retour.
+async fn api_attack_start(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    body: Result<Json<AttackRequest>, JsonRejection>,
+) -> Result<Json<Run>, ApiError> {
+    let Json(request) = body.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let technique = attack::technique(&request.technique)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown technique: {}", request.technique)))?;
+    let lab = find_lab(&request.lab)?;
+
+    let result = start_attack(&state, &user, client, lab, technique).await;
+    let params = serde_json::json!({ "technique": technique.id, "target": lab.target_ip });
+    let detail = result.as_ref().map(|run| Some(format!("run #{}", run.id)));
+    audit(&state, AuditEntry::new(&user, client, "attack.start", &lab.name, params, detail)).await;
+    result.map(Json)
+}
 
-    match output {
-        Ok(o) => {
-            if o.status.success() {
-                println!("✓ Started lab: {}", lab);
-            } else {
-                eprintln!("Failed to start {}: {}", lab, String::from_utf8_lossy(&o.stderr));
+/// Ouvre le run d'une technique et lance nmap en tâche de fond.
+async fn start_attack(
+    state: &AppState,
+    user: &Identity,
+    client: SocketAddr,
+    lab: &'static Lab,
+    technique: &'static Technique,
+) -> Result<Run, ApiError> {
+    user.check_lab(&lab.name)?;
+    let (engine, target) = attack_target(lab)?;
+    // Le lab reste verrouillé pendant le scan (pas de matrice ni d'arrêt en parallèle)
+    let guard = state
+        .locks
+        .try_acquire(&lab.name, &format!("attack {}", technique.id), &user.client(client))
+        .map_err(ApiError::Locked)?;
+
+    let (run, program, args) = {
+        let state = state.clone();
+        blocking(move || open_attack_run(&state, lab, engine, target, technique)).await?
+    };
+    guard.set_operation(format!("attack {} (run #{})", technique.id, run.id));
+    let (state, run_id) = (state.clone(), run.id);
+    tokio::spawn(async move {
+        let _guard = guard;
+        execute_attack_run(&state, run_id, &program, &args, None).await;
+    });
+    Ok(run)
+}
+
+/// Moteur et cible d'un lab attaquable (engine et target_ip dans labs.toml).
+fn attack_target(lab: &Lab) -> Result<(Engine, IpAddr), ApiError> {
+    match (lab.engine, lab.target_ip) {
+        (Some(engine), Some(target)) => Ok((engine, target)),
+        _ => Err(ApiError::BadRequest(format!("Lab {} has no IDS target to attack", lab.name))),
+    }
+}
+
+/// Ouvre le run d'une technique contre la cible du lab et prépare la commande nmap.
+fn open_attack_run(
+    state: &AppState,
+    lab: &Lab,
+    engine: Engine,
+    target: IpAddr,
+    technique: &Technique,
+) -> rusqlite::Result<(Run, PathBuf, Vec<String>)> {
+    let program = attack::nmap_bin();
+    let args = attack::nmap_args(technique, target, &lab.port_list());
+    let command = format!("{} {}", program.display(), args.join(" "));
+    let rule_level = get_current_rule_level(lab);
+    let run = state.store.start_run(engine, &rule_level, target, &command)?;
+    state.executions.register(run.id);
+    println!("✓ Run {} started: {}", run.id, command);
+    Ok((run, program, args))
+}
+
+/// Exécute l'attaque d'un run (tuée après `timeout`) puis clôt le run.
+async fn execute_attack_run(
+    state: &AppState,
+    run_id: u64,
+    program: &std::path::Path,
+    args: &[String],
+    timeout: Option<std::time::Duration>,
+) -> Option<i32> {
+    let execution = attack::execute(&state.executions, run_id, program, args);
+    let exit_code = match timeout {
+        Some(limit) => tokio::time::timeout(limit, execution).await.unwrap_or_else(|_| {
+            state.executions.push(run_id, OutputEvent::Line { text: format!("Killed after {:?}", limit) });
+            None
+        }),
+        None => execution.await,
+    };
+    // Clore le run avant d'annoncer la fin: le navigateur relit alors un run terminé
+    let store = state.store.clone();
+    if let Err(e) = blocking(move || store.stop_run(run_id, exit_code)).await {
+        eprintln!("Error closing run {}: {}", run_id, e);
+    }
+    state.executions.finish(run_id, exit_code);
+    println!("✓ Run {} finished (exit code {:?})", run_id, exit_code);
+    exit_code
+}
+
+/// Sortie d'une attaque en SSE: lignes déjà émises puis lignes en direct.
+/// Appelé via GET /api/runs/:id/output
+///
+/// Événements `line` (data = texte) puis un événement `exit`
+/// (data = {"type":"exit","exit_code":N}) qui termine le flux.
+async fn api_run_output(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let (history, rx) = state
+        .executions
+        .subscribe(id)
+        .ok_or_else(|| ApiError::NotFound(format!("No attack output for run {}", id)))?;
+
+    Ok(output_sse(history, rx))
+}
+
+/// Flux SSE d'une sortie (attaque ou job): historique puis événements en direct.
+fn output_sse(
+    history: Vec<OutputEvent>,
+    rx: Option<broadcast::Receiver<OutputEvent>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let to_event = |event: &OutputEvent| match event {
+        OutputEvent::Line { text } => Event::default().event("line").data(text),
+        OutputEvent::Exit { .. } => Event::default().event("exit").json_data(event).unwrap_or_default(),
+    };
+    let replay: Vec<Result<Event, Infallible>> = history.iter().map(|e| Ok(to_event(e))).collect();
+    let live = futures_util::stream::unfold(rx, move |rx| async move {
+        let mut rx = rx?;
+        loop {
+            match rx.recv().await {
+                Ok(event @ OutputEvent::Exit { .. }) => return Some((Ok(to_event(&event)), None)),
+                Ok(event) => return Some((Ok(to_event(&event)), Some(rx))),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
             }
         }
-        Err(e) => eprintln!("Error starting lab: {}", e),
+    });
+
+    Sse::new(futures_util::stream::iter(replay).chain(live)).keep_alive(KeepAlive::default())
+}
+
+/// Niveau de règles du lab d'un moteur (runs lancés hors du commander).
+fn engine_rule_level(engine: Engine) -> String {
+    labs::registry().for_engine(engine).map_or_else(|| "?".into(), get_current_rule_level)
+}
+
+/// Lab du registre désigné dans une requête API.
+fn find_lab(name: &str) -> Result<&'static Lab, ApiError> {
+    labs::registry()
+        .get(name)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown lab: {}", name)))
+}
+
+/// Un run est rattaché au lab de son moteur (droits des étudiants).
+fn check_engine_lab(user: &Identity, engine: Engine) -> Result<(), ApiError> {
+    match labs::registry().for_engine(engine) {
+        Some(lab) => user.check_lab(&lab.name),
+        None => Ok(()),
     }
+}
 
-    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-    dashboard().await
+async fn find_run(state: &AppState, id: u64) -> Result<Run, ApiError> {
+    let store = state.store.clone();
+    blocking(move || store.run(id))
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown run: {}", id)))
 }
 
-/// Arrête TOUS les labs via `docker compose down` sur chaque répertoire.
-/// Appelé via POST /lab/stop-all
-async fn stop_all_labs() -> Html<String> {
-    let root = get_project_root();
-    let labs = ["snort", "suricata", "zeek", "kibana"];
-
-    // Arrêter tous les labs en parallèle
-    let futures: Vec<_> = labs.iter().map(|lab| {
-        let lab_dir = root.join(format!("{}-lab", lab));
-        let lab_name = lab.to_string();
-        async move {
-            let output = tokio::process::Command::new("docker")
-                .args(["compose", "down", "--remove-orphans"])
-                .current_dir(&lab_dir)
-                .output()
-                .await;
-
-            match output {
-                Ok(o) => {
-                    if o.status.success() {
-                        println!("✓ Stopped lab: {}", lab_name);
-                    } else {
-                        eprintln!("Failed to stop {}: {}", lab_name, String::from_utf8_lossy(&o.stderr));
-                    }
+// ============================================================================
+// MATRICE D'ÉVASION
+// ============================================================================
+// Job de fond qui rejoue toutes les techniques du cookbook à chaque niveau
+// de règles d'un lab (voir matrix.rs).
+
+/// Liste des matrices, de la plus récente à la plus ancienne.
+/// Appelé via GET /api/matrix
+async fn api_matrices(State(state): State<AppState>) -> Result<Json<Vec<Matrix>>, ApiError> {
+    Ok(Json(blocking(move || state.store.matrices()).await?))
+}
+
+/// Détail d'une matrice (cellules déjà mesurées si le job est en cours).
+/// Appelé via GET /api/matrix/:id
+async fn api_matrix(State(state): State<AppState>, Path(id): Path<u64>) -> Result<Json<Matrix>, ApiError> {
+    blocking(move || state.store.matrix(id))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown matrix: {}", id)))
+}
+
+/// Démarre le job de matrice d'un lab.
+/// Appelé via POST /api/matrix avec {"lab": "suricata"}
+///
+/// 409 si un job tourne déjà: il modifie les règles actives du lab.
+async fn api_matrix_start(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    body: Result<Json<MatrixRequest>, JsonRejection>,
+) -> Result<Json<Matrix>, ApiError> {
+    let Json(request) = body.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let lab = find_lab(&request.lab)?;
+    let result = start_matrix(&state, &user, client, lab).await;
+    let detail = result.as_ref().map(|m| Some(format!("matrix #{}", m.id)));
+    audit(&state, AuditEntry::new(&user, client, "matrix.start", &lab.name, serde_json::json!({}), detail)).await;
+    result.map(Json)
+}
+
+/// Verrouille le lab, crée la matrice et lance sa mesure en tâche de fond.
+async fn start_matrix(
+    state: &AppState,
+    user: &Identity,
+    client: SocketAddr,
+    lab: &'static Lab,
+) -> Result<Matrix, ApiError> {
+    user.check_lab(&lab.name)?;
+    let (engine, target) = attack_target(lab)?;
+    let Some(rules_path) = lab.rules_path(&get_project_root()) else {
+        return Err(ApiError::BadRequest(format!("Lab {} has no rule templates", lab.name)));
+    };
+    // Le lab reste verrouillé jusqu'à la restauration des règles
+    let guard = state
+        .locks
+        .try_acquire(&lab.name, "evasion matrix", &user.client(client))
+        .map_err(ApiError::Locked)?;
+    // Réserver la matrice avant de toucher à l'historique ou à la base
+    if state.matrix_busy.swap(true, Ordering::SeqCst) {
+        return Err(ApiError::Conflict("An evasion matrix is already running".into()));
+    }
+    let created = {
+        let (state, user) = (state.clone(), user.clone());
+        blocking(move || {
+            save_rules(&state, lab, &user, "evasion matrix")?;
+            Ok(state.store.create_matrix(&lab.name, engine)?)
+        })
+        .await
+    };
+    let matrix = match created {
+        Ok(m) => m,
+        Err(e) => {
+            state.matrix_busy.store(false, Ordering::SeqCst);
+            return Err(e);
+        }
+    };
+    println!("✓ Matrix {} started for {}", matrix.id, matrix.lab);
+    guard.set_operation(format!("matrix #{}", matrix.id));
+
+    let id = matrix.id;
+    let state = state.clone();
+    tokio::spawn(async move {
+        let _guard = guard;
+        let original_rules = {
+            let rules_path = rules_path.clone();
+            blocking(move || fs::read(rules_path).ok()).await
+        };
+
+        let result = measure_matrix(&state, id, lab, engine, target, MatrixTiming::from_config(config::get())).await;
+
+        // Remettre les règles que l'utilisateur avait avant le job
+        let (status, error) = match result {
+            Ok(()) => (MatrixStatus::Completed, None),
+            Err(e) => (MatrixStatus::Failed, Some(e)),
+        };
+        let store = state.store.clone();
+        blocking(move || {
+            if let Some(content) = original_rules {
+                if let Err(e) = fs::write(&rules_path, content) {
+                    eprintln!("Error restoring {:?}: {}", rules_path, e);
                 }
-                Err(e) => eprintln!("Error stopping {}: {}", lab_name, e),
             }
+            if let Err(e) = store.finish_matrix(id, status, error.as_deref()) {
+                eprintln!("Error closing matrix {}: {}", id, e);
+            }
+        })
+        .await;
+        state.matrix_busy.store(false, Ordering::SeqCst);
+        println!("✓ Matrix {} {}", id, status.as_str());
+    });
+
+    Ok(matrix)
+}
+
+/// Applique chaque niveau puis lance chaque technique, cellule par cellule.
+async fn measure_matrix(
+    state: &AppState,
+    id: u64,
+    lab: &'static Lab,
+    engine: Engine,
+    target: IpAddr,
+    timing: MatrixTiming,
+) -> Result<(), String> {
+    for level in matrix::LEVELS {
+        blocking(move || templates::install(&get_project_root(), lab, &templates::Level::Builtin(level))).await?;
+        tokio::time::sleep(templates::reload_delay(lab)).await;
+
+        for technique in attack::TECHNIQUES {
+            let (run, program, args) = {
+                let state = state.clone();
+                blocking(move || open_attack_run(&state, lab, engine, target, technique)).await.map_err(|e| e.to_string())?
+            };
+            execute_attack_run(state, run.id, &program, &args, Some(timing.technique_timeout)).await;
+
+            // Laisser l'IDS écrire ses alertes et ingest_alerts les enregistrer
+            tokio::time::sleep(timing.settle).await;
+            let store = state.store.clone();
+            blocking(move || {
+                let run = store.run(run.id)?.unwrap_or(run);
+                let alerts = store.sid_counts(&run)?.iter().map(|c| c.count).sum();
+                let cell = MatrixCell { level, technique: technique.id.to_string(), run_id: run.id, alerts, detected: alerts > 0 };
+                store.add_matrix_cell(id, &cell)
+            })
+            .await
+            .map_err(|e| e.to_string())?;
         }
-    }).collect();
+    }
+    Ok(())
+}
 
-    futures_util::future::join_all(futures).await;
+// ============================================================================
+// RAPPORTS
+// ============================================================================
 
-    println!("✓ All labs stopped");
-    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-    dashboard().await
+/// Exporte le rapport de campagne (matrices, top SIDs, chronologie des runs).
+/// Appelé via GET /api/report/{html,md,json,csv}?matrices=1,4&runs=12
+///
+/// Sans sélection, le rapport couvre la dernière matrice terminée de
+/// chaque lab (voir report.rs).
+async fn api_report(
+    State(state): State<AppState>,
+    Path(format): Path<String>,
+    query: Result<Query<ReportQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let format = format.parse::<ReportFormat>().map_err(ApiError::NotFound)?;
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let matrix_ids = ReportQuery::ids(&query.matrices).map_err(ApiError::BadRequest)?;
+    let run_ids = ReportQuery::ids(&query.runs).map_err(ApiError::BadRequest)?;
+
+    let report = blocking(move || -> Result<report::Report, ApiError> {
+        let store = &state.store;
+        let matrices = if matrix_ids.is_empty() && run_ids.is_empty() {
+            let mut latest: Vec<Matrix> = Vec::new();
+            for m in store.matrices()? {
+                if m.status == MatrixStatus::Completed && !latest.iter().any(|l| l.lab == m.lab) {
+                    latest.push(m);
+                }
+            }
+            latest
+        } else {
+            let mut selected = Vec::new();
+            for id in matrix_ids {
+                selected.push(store.matrix(id)?.ok_or_else(|| ApiError::NotFound(format!("Unknown matrix: {}", id)))?);
+            }
+            selected
+        };
+        let mut runs = Vec::new();
+        for id in run_ids {
+            runs.push(store.run(id)?.ok_or_else(|| ApiError::NotFound(format!("Unknown run: {}", id)))?);
+        }
+        Ok(report::build(store, matrices, runs)?)
+    })
+    .await?;
+    let filename = format!("ids-report-{}.{}", report.generated_at.format("%Y%m%d-%H%M%S"), format.extension());
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        report::render(&report, format),
+    )
+        .into_response())
 }
 
-/// Arrête un lab complet via `docker compose down`.
-/// Appelé via POST /lab/stop/{lab}
-async fn stop_lab(Path(lab): Path<String>) -> Html<String> {
-    let root = get_project_root();
-    let lab_dir = root.join(format!("{}-lab", lab));
+// ============================================================================
+// GESTION DES LABS
+// ============================================================================
+// Ces handlers démarrent, arrêtent ou reconstruisent un lab complet via
+// docker compose. Un lab = tous les containers définis dans le
+// docker-compose.yml du lab. Chaque opération est un job (jobs.rs).
 
-    // Utiliser tokio::process::Command pour l'exécution async
-    let output = tokio::process::Command::new("docker")
-        .args(["compose", "down"])
-        .current_dir(&lab_dir)
-        .output()
-        .await;
+/// Lance une opération docker compose sur un lab.
+/// Appelé via POST /api/jobs avec {"lab": "snort", "action": "start|stop|rebuild"}
+///
+/// Retourne immédiatement le job; sa sortie se suit sur
+/// GET /api/jobs/:id/output et son statut final sur GET /api/jobs/:id.
+/// Lab occupé par une autre opération: 409, ou job queued avec "wait": true.
+async fn api_job_start(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    body: Result<Json<JobRequest>, JsonRejection>,
+) -> Result<Json<Job>, ApiError> {
+    let Json(request) = body.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let lab = find_lab(&request.lab)?;
+    submit_job(&state, &user, client, lab, request.action, request.wait).await.map(Json)
+}
 
-    match output {
-        Ok(o) => {
-            if o.status.success() {
-                println!("✓ Stopped lab: {}", lab);
-            } else {
-                eprintln!("Failed to stop {}: {}", lab, String::from_utf8_lossy(&o.stderr));
+/// Arrête tous les labs: un job stop par lab, mis en file derrière
+/// l'opération en cours d'un lab occupé.
+/// Appelé via POST /api/jobs/stop-all (et POST /lab/stop-all)
+async fn api_jobs_stop_all(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+) -> Result<Json<Vec<Job>>, ApiError> {
+    let mut jobs = Vec::new();
+    for lab in labs::registry().labs() {
+        jobs.push(submit_job(&state, &user, client, lab, JobAction::Stop, true).await?);
+    }
+    println!("✓ Stop all labs: {} jobs", jobs.len());
+    let params = serde_json::json!({ "labs": jobs.iter().map(|j| &j.lab).collect::<Vec<_>>() });
+    let detail = format!("jobs {}", jobs.iter().map(|j| format!("#{}", j.id)).collect::<Vec<_>>().join(", "));
+    audit(&state, AuditEntry::new(&user, client, "lab.stop_all", "*", params, Ok(Some(detail)))).await;
+    Ok(Json(jobs))
+}
+
+/// Démarre un lab (ancienne route du dashboard, équivalente à POST /api/jobs).
+/// Appelé via POST /lab/start/:lab
+async fn start_lab(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    LabParam(lab): LabParam,
+) -> Result<Json<Job>, ApiError> {
+    submit_job(&state, &user, client, lab, JobAction::Start, false).await.map(Json)
+}
+
+/// Arrête un lab (ancienne route du dashboard, équivalente à POST /api/jobs).
+/// Appelé via POST /lab/stop/:lab
+async fn stop_lab(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Extension(user): Extension<Identity>,
+    LabParam(lab): LabParam,
+) -> Result<Json<Job>, ApiError> {
+    submit_job(&state, &user, client, lab, JobAction::Stop, false).await.map(Json)
+}
+
+/// Crée le job d'une opération sur un lab et l'exécute en tâche de fond,
+/// dès que le verrou du lab est libre.
+async fn submit_job(
+    state: &AppState,
+    user: &Identity,
+    client: SocketAddr,
+    lab: &'static Lab,
+    action: JobAction,
+    wait: bool,
+) -> Result<Job, ApiError> {
+    let compose_dir = lab.compose_dir(&get_project_root());
+    let holder = user.client(client);
+    let audit_action = format!("lab.{}", action.as_str());
+    let params = serde_json::json!({ "action": action.as_str(), "wait": wait });
+
+    let guard = match state.locks.try_acquire(&lab.name, action.as_str(), &holder) {
+        Ok(guard) => Some(guard),
+        Err(_) if wait => None,
+        Err(lock) => {
+            let error = ApiError::Locked(lock);
+            audit(state, AuditEntry::new(user, client, &audit_action, &lab.name, params, Err(&error))).await;
+            return Err(error);
+        }
+    };
+    let job = state.jobs.create(&lab.name, action, guard.is_none());
+    let operation = format!("job #{} {}", job.id, action.as_str());
+    let status = if guard.is_some() { "started" } else { "queued" };
+    println!("✓ Job {} {}: {} {}", job.id, status, action.as_str(), lab.name);
+    let detail = format!("job #{} {}", job.id, status);
+    audit(state, AuditEntry::new(user, client, &audit_action, &lab.name, params, Ok(Some(detail)))).await;
+
+    let (jobs, locks) = (state.jobs.clone(), state.locks.clone());
+    let id = job.id;
+    tokio::spawn(async move {
+        let _guard = match guard {
+            Some(guard) => {
+                guard.set_operation(operation);
+                guard
             }
+            None => locks.acquire(&lab.name, &operation, &holder).await,
+        };
+        let exit_code = jobs.run(id, action, &compose_dir).await;
+        // Relire l'état Docker avant d'annoncer la fin: le dashboard rechargé est à jour
+        snapshot::refresh().await;
+        jobs.finish(id, exit_code);
+        match jobs.get(id).and_then(|j| j.error) {
+            None => println!("✓ Job {} finished", id),
+            Some(error) => eprintln!("Job {} failed: {}", id, error),
+        }
+    });
+
+    Ok(job)
+}
+
+/// Labs occupés: opération en cours, client et file d'attente.
+/// Appelé via GET /api/locks
+async fn api_locks(State(state): State<AppState>) -> Json<Vec<LabLockState>> {
+    Json(state.locks.states())
+}
+
+/// Jobs en cours et récents, du plus récent au plus ancien.
+/// Appelé via GET /api/jobs
+async fn api_jobs(State(state): State<AppState>) -> Json<Vec<Job>> {
+    Json(state.jobs.list())
+}
+
+/// Détail d'un job: étape en cours, statut final, erreur.
+/// Appelé via GET /api/jobs/:id
+async fn api_job(State(state): State<AppState>, Path(id): Path<u64>) -> Result<Json<Job>, ApiError> {
+    state
+        .jobs
+        .get(id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown job: {}", id)))
+}
+
+/// Sortie de docker compose en SSE, mêmes événements que /api/runs/:id/output.
+/// Appelé via GET /api/jobs/:id/output
+async fn api_job_output(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let (history, rx) = state
+        .jobs
+        .output()
+        .subscribe(id)
+        .ok_or_else(|| ApiError::NotFound(format!("No output for job {}", id)))?;
+    Ok(output_sse(history, rx))
+}
+
+// ============================================================================
+// JOURNAL D'AUDIT
+// ============================================================================
+// Actions de pilotage (containers, règles, logs, jobs, matrices) avec leur
+// auteur et leur résultat, voir audit.rs. Réservé aux instructeurs.
+
+/// Entrées du journal, de la plus récente à la plus ancienne.
+/// Appelé via GET /api/audit?since=..&until=..&actor=..&lab=..&action=..&outcome=..&limit=..
+async fn api_audit(
+    State(state): State<AppState>,
+    query: Result<Query<AuditQuery>, QueryRejection>,
+) -> Result<Json<Vec<AuditRecord>>, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    Ok(Json(blocking(move || state.store.audit(&query)).await?))
+}
+
+/// Export du journal en JSON lines, de la plus ancienne à la plus récente.
+/// Appelé via GET /api/audit/export (mêmes filtres que /api/audit, sans limite)
+async fn api_audit_export(
+    State(state): State<AppState>,
+    query: Result<Query<AuditQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let records = blocking(move || state.store.audit_export(&query)).await?;
+    let filename = format!("ids-audit-{}.jsonl", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        audit::to_jsonl(&records),
+    )
+        .into_response())
+}
+
+// ============================================================================
+// AUTHENTIFICATION
+// ============================================================================
+// Formulaire de connexion et session du navigateur (cookie), voir auth.rs.
+// Les scripts utilisent un token d'API (Authorization: Bearer ...).
+
+/// Champs du formulaire de connexion.
+#[derive(serde::Deserialize)]
+struct LoginForm {
+    username: String,
+    password: String,
+}
+
+/// Page de connexion. Sans comptes (authentification désactivée): dashboard.
+/// Appelé via GET /login
+async fn login_page() -> Response {
+    if !auth::enabled() {
+        return Redirect::to("/").into_response();
+    }
+    login_html(None).into_response()
+}
+
+fn login_html(error: Option<&str>) -> Html<String> {
+    let error = error
+        .map(|e| format!(r#"<p class="login-error">{}</p>"#, escape(e)))
+        .unwrap_or_default();
+    Html(format!(r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>Connexion - IDS Lab Commander</title>
+    <link rel="stylesheet" href="/static/css/dashboard.css">
+</head>
+<body>
+    <div class="card login-card">
+        <div class="card-header"><h2>🛡️ IDS Lab Commander</h2></div>
+        <div class="card-body">
+            {error}
+            <form method="post" action="/login">
+                <label>Utilisateur <input name="username" autocomplete="username" required autofocus></label>
+                <label>Mot de passe <input name="password" type="password" autocomplete="current-password" required></label>
+                <button class="btn start" type="submit">Connexion</button>
+            </form>
+        </div>
+    </div>
+</body>
+</html>"#))
+}
+
+/// Vérifie le compte et ouvre une session (cookie ids_session).
+/// Appelé via POST /login (formulaire username, password)
+async fn login(Form(form): Form<LoginForm>) -> Response {
+    let username = form.username.clone();
+    // Argon2 est volontairement coûteux: hors des threads du runtime
+    let session = tokio::task::spawn_blocking(move || auth::login(&form.username, &form.password))
+        .await
+        .ok()
+        .flatten();
+    match session {
+        Some(id) => {
+            println!("✓ {} logged in", username);
+            ([(header::SET_COOKIE, auth::session_cookie_header(Some(&id)))], Redirect::to("/")).into_response()
+        }
+        None => {
+            eprintln!("Failed login for {:?}", username);
+            (StatusCode::UNAUTHORIZED, login_html(Some("Utilisateur ou mot de passe incorrect"))).into_response()
         }
-        Err(e) => eprintln!("Error stopping lab: {}", e),
     }
+}
 
-    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-    dashboard().await
+/// Ferme la session du navigateur.
+/// Appelé via POST /logout
+async fn logout(headers: HeaderMap) -> Response {
+    auth::logout(&headers);
+    ([(header::SET_COOKIE, auth::session_cookie_header(None))], Redirect::to("/login")).into_response()
+}
+
+/// Utilisateur de la requête, son rôle, ses labs et le token CSRF à renvoyer
+/// dans X-CSRF-Token (auth.rs).
+/// Appelé via GET /api/me
+async fn api_me(
+    Extension(user): Extension<Identity>,
+    csrf: Option<Extension<CsrfToken>>,
+) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "auth": auth::enabled(),
+        "user": user,
+        "csrf_token": csrf.map(|Extension(CsrfToken(token))| token),
+    }))
 }
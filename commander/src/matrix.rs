@@ -0,0 +1,106 @@
+// =============================================================================
+// IDS LAB COMMANDER - Matrice d'évasion (technique × niveau)
+// =============================================================================
+//
+// DESCRIPTION:
+// Version intégrée de scripts/lua/scan_all_levels.lua: pour un lab
+// donné, chaque niveau de règles 1..5 est appliqué (templates.rs), puis
+// chaque technique du cookbook (attack.rs) est lancée dans son propre run.
+// Une cellule de la matrice indique si au moins une alerte a été levée
+// pendant ce run.
+//
+// DÉROULEMENT D'UN JOB (POST /api/matrix):
+//   pour level in 1..=5:
+//     installer le template, attendre le rechargement (reload_delay)
+//     pour technique in TECHNIQUES:
+//       run + nmap (limité à technique_timeout)
+//       attendre que les alertes soient écrites et ingérées (settle)
+//       compter les alertes du run → cellule
+//   restaurer les règles actives d'avant le job
+//
+// Un seul job à la fois: il modifie les règles actives du lab.
+//
+// =============================================================================
+
+use crate::alerts::Engine;
+use crate::config::Config;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Niveaux de règles testés.
+pub const LEVELS: [u8; 5] = [1, 2, 3, 4, 5];
+
+/// État d'un job de matrice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatrixStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+impl MatrixStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatrixStatus::Running => "running",
+            MatrixStatus::Completed => "completed",
+            MatrixStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "running" => MatrixStatus::Running,
+            "completed" => MatrixStatus::Completed,
+            _ => MatrixStatus::Failed,
+        }
+    }
+}
+
+/// Résultat d'une technique à un niveau.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixCell {
+    pub level: u8,
+    pub technique: String,
+    /// Run de l'attaque (détail des alertes via /api/runs/:id)
+    pub run_id: u64,
+    pub alerts: u64,
+    pub detected: bool,
+}
+
+/// Matrice de détection d'un moteur.
+#[derive(Debug, Clone, Serialize)]
+pub struct Matrix {
+    pub id: u64,
+    /// Lab mesuré (labs.toml)
+    pub lab: String,
+    pub engine: Engine,
+    pub status: MatrixStatus,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub cells: Vec<MatrixCell>,
+}
+
+/// Corps de POST /api/matrix.
+#[derive(Debug, Deserialize)]
+pub struct MatrixRequest {
+    /// Nom du lab dans labs.toml
+    pub lab: String,
+}
+
+/// Délais du job, réglables par la configuration (config.rs).
+#[derive(Debug, Clone, Copy)]
+pub struct MatrixTiming {
+    /// Durée maximale d'une technique (matrix_technique_timeout_secs, défaut 120 s)
+    pub technique_timeout: Duration,
+    /// Attente après une technique avant de compter les alertes (matrix_settle_secs, défaut 3 s)
+    pub settle: Duration,
+}
+
+impl MatrixTiming {
+    pub fn from_config(config: &Config) -> Self {
+        MatrixTiming { technique_timeout: config.matrix_technique_timeout, settle: config.matrix_settle }
+    }
+}
@@ -0,0 +1,364 @@
+// =============================================================================
+// IDS LAB COMMANDER - Vérifications préalables de la machine hôte
+// =============================================================================
+//
+// DESCRIPTION:
+// Avant de démarrer les labs, vérifie que la machine hôte peut les faire
+// tourner et les attaquer. Chaque vérification rend pass, warn ou fail avec
+// la commande ou la marche à suivre pour corriger.
+//
+// VÉRIFICATIONS:
+//   docker_socket    API Docker joignable sur le socket configuré
+//   compose          plugin docker compose v2 (champ `name:` des compose files)
+//   nmap, hping3     présents, et capables d'envoyer des paquets bruts
+//   subnet <lab>     sous-réseau du lab sans collision avec les routes de
+//                    l'hôte ni un autre réseau Docker
+//   port <n>         ports publiés par les labs libres sur l'hôte (ports de
+//                    editor_url, dashboard_url et host_ports dans labs.toml)
+//   apparmor         confinement des outils d'attaque
+//
+// USAGE:
+//   ids-commander preflight [--json]   (code de sortie 1 si un fail)
+//   GET /api/preflight
+//
+// =============================================================================
+
+use crate::attack;
+use crate::docker::{self, NetworkInfo};
+use crate::labs::{self, Lab};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+
+/// Version minimale de docker compose: `name:` au niveau racine des compose files.
+const MIN_COMPOSE: (u32, u32) = (2, 3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Résultat d'une vérification.
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    /// Correction proposée (warn et fail)
+    pub remediation: Option<String>,
+}
+
+/// Résultat de toutes les vérifications (GET /api/preflight).
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Pire statut des vérifications
+    pub status: Status,
+    pub checks: Vec<Check>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl Report {
+    /// Affichage de `ids-commander preflight`.
+    pub fn print(&self) {
+        for check in &self.checks {
+            let icon = match check.status {
+                Status::Pass => "✓",
+                Status::Warn => "⚠",
+                Status::Fail => "✗",
+            };
+            println!("{} {:<18} {}", icon, check.name, check.detail);
+            if let Some(remediation) = &check.remediation {
+                for line in remediation.lines() {
+                    println!("  {:<18} → {}", "", line);
+                }
+            }
+        }
+    }
+}
+
+fn pass(name: impl Into<String>, detail: impl Into<String>) -> Check {
+    Check { name: name.into(), status: Status::Pass, detail: detail.into(), remediation: None }
+}
+
+fn problem(status: Status, name: impl Into<String>, detail: impl Into<String>, remediation: impl Into<String>) -> Check {
+    Check { name: name.into(), status, detail: detail.into(), remediation: Some(remediation.into()) }
+}
+
+/// Lance toutes les vérifications.
+pub async fn run() -> Report {
+    let (docker_ok, docker_msg) = docker::check_access().await;
+    let mut checks = vec![docker_socket(docker_ok, docker_msg), compose().await];
+    checks.push(tool("nmap", &attack::nmap_bin(), Status::Fail, "apt install nmap"));
+    checks.push(tool("hping3", Path::new("hping3"), Status::Warn, "apt install hping3"));
+
+    let (bridges, containers) = match docker_ok {
+        true => (docker::bridges().await.unwrap_or_default(), docker::lab_containers().await.unwrap_or_default()),
+        false => (vec![], vec![]),
+    };
+    let routes = std::fs::read_to_string("/proc/net/route").map(|t| parse_routes(&t)).unwrap_or_default();
+    for lab in labs::registry().labs() {
+        checks.push(subnet(lab, &routes, &bridges));
+    }
+    for lab in labs::registry().labs() {
+        let running = containers.iter().any(|c| c.lab == lab.name && c.is_running());
+        for (port, service) in lab.published_ports() {
+            checks.push(host_port(port, &lab.name, &service, running));
+        }
+    }
+    checks.push(apparmor());
+
+    Report {
+        status: checks.iter().map(|c| c.status).max().unwrap_or(Status::Pass),
+        checks,
+        checked_at: Utc::now(),
+    }
+}
+
+fn docker_socket(ok: bool, message: String) -> Check {
+    let socket = crate::config::get().docker_socket.display().to_string();
+    match ok {
+        true => pass("docker_socket", format!("{} ({})", message, socket)),
+        false => problem(
+            Status::Fail,
+            "docker_socket",
+            format!("{}: {}", socket, message),
+            "sudo usermod -aG docker $USER puis se reconnecter (newgrp docker)\n\
+             ou --docker-socket / DOCKER_SOCKET si le socket est ailleurs",
+        ),
+    }
+}
+
+/// `docker compose version --short` ("2.29.7" ou "v2.29.7").
+async fn compose() -> Check {
+    let output = tokio::process::Command::new("docker").args(["compose", "version", "--short"]).output().await;
+    let version = match output {
+        Ok(o) if o.status.success() => {
+            String::from_utf8_lossy(&o.stdout).lines().next().unwrap_or_default().trim().to_string()
+        }
+        _ => {
+            return problem(
+                Status::Fail,
+                "compose",
+                "docker compose plugin not found",
+                "apt install docker-compose-plugin (docker-compose v1 n'est pas supporté)",
+            )
+        }
+    };
+    match parse_version(&version) {
+        Some(v) if v >= MIN_COMPOSE => pass("compose", format!("docker compose {}", version)),
+        _ => problem(
+            Status::Fail,
+            "compose",
+            format!("docker compose {} (>= {}.{} required)", version, MIN_COMPOSE.0, MIN_COMPOSE.1),
+            "apt install --only-upgrade docker-compose-plugin",
+        ),
+    }
+}
+
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim_start_matches('v').split('.');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+/// Outil d'attaque présent et autorisé à forger des paquets (root ou
+/// capabilities cap_net_raw/cap_net_admin).
+fn tool(name: &str, bin: &Path, missing: Status, install: &str) -> Check {
+    let Some(path) = find_executable(bin) else {
+        return problem(missing, name, format!("{} not found in PATH", bin.display()), install);
+    };
+    if effective_uid() == Some(0) {
+        return pass(name, format!("{} (running as root)", path.display()));
+    }
+    let caps = std::process::Command::new("getcap")
+        .arg(&path)
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string());
+    match caps {
+        Ok(caps) if caps.contains("cap_net_raw") && caps.contains("cap_net_admin") => {
+            pass(name, format!("{} ({})", path.display(), caps.split_whitespace().last().unwrap_or_default()))
+        }
+        _ => problem(
+            Status::Warn,
+            name,
+            format!("{}: no raw socket capability (SYN scans and fragmentation need root)", path.display()),
+            format!("sudo setcap cap_net_raw,cap_net_admin+eip {}", path.display()),
+        ),
+    }
+}
+
+/// Chemin réel d'un exécutable (absolu ou cherché dans le PATH).
+fn find_executable(bin: &Path) -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = match bin.is_absolute() {
+        true => vec![bin.to_path_buf()],
+        false => std::env::split_paths(&std::env::var_os("PATH")?).map(|dir| dir.join(bin)).collect(),
+    };
+    candidates.into_iter().find(|p| p.is_file()).and_then(|p| p.canonicalize().ok())
+}
+
+fn effective_uid() -> Option<u32> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("Uid:"))?;
+    line.split_whitespace().nth(2)?.parse().ok()
+}
+
+/// Réseau IPv4 (adresse, longueur du préfixe).
+type Net = (Ipv4Addr, u8);
+
+fn parse_net(cidr: &str) -> Option<Net> {
+    let (ip, prefix) = cidr.split_once('/')?;
+    Some((ip.parse().ok()?, prefix.parse().ok().filter(|p| *p <= 32)?))
+}
+
+fn overlaps(a: Net, b: Net) -> bool {
+    let prefix = a.1.min(b.1);
+    let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+    u32::from(a.0) & mask == u32::from(b.0) & mask
+}
+
+/// Routes de /proc/net/route: (interface, réseau). La route par défaut
+/// (masque nul) est ignorée, elle recouvre tout.
+fn parse_routes(table: &str) -> Vec<(String, Net)> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // Adresses en hexadécimal, dans l'ordre des octets de l'hôte
+            let hex = |s: &str| u32::from_str_radix(s, 16).ok().map(|n| Ipv4Addr::from(n.to_le_bytes()));
+            let (destination, mask) = (hex(fields.get(1)?)?, hex(fields.get(7)?)?);
+            let prefix = u32::from(mask).leading_ones() as u8;
+            (prefix > 0).then(|| (fields[0].to_string(), (destination, prefix)))
+        })
+        .collect()
+}
+
+/// Interface bridge d'un réseau Docker (br- et 12 caractères de l'id).
+fn bridge_interface(network: &NetworkInfo) -> String {
+    format!("br-{}", network.id.chars().take(12).collect::<String>())
+}
+
+/// Sous-réseau du lab: les réseaux du projet compose du lab (et leurs
+/// routes) sont attendus, tout autre recouvrement est une collision.
+fn subnet(lab: &Lab, routes: &[(String, Net)], bridges: &[NetworkInfo]) -> Check {
+    let name = format!("subnet {}", lab.name);
+    let Some(net) = parse_net(&lab.subnet) else {
+        return pass(name, format!("{} (IPv6, not checked)", lab.subnet));
+    };
+    let own = |n: &NetworkInfo| n.name.starts_with(&format!("{}_", lab.compose_project()));
+
+    let networks: Vec<&str> = bridges
+        .iter()
+        .filter(|n| !own(n) && n.subnets.iter().filter_map(|s| parse_net(s)).any(|s| overlaps(s, net)))
+        .map(|n| n.name.as_str())
+        .collect();
+    if !networks.is_empty() {
+        return problem(
+            Status::Fail,
+            name,
+            format!("{} overlaps Docker network {}", lab.subnet, networks.join(", ")),
+            format!("docker network rm {} (ou changer subnet dans labs.toml et le docker-compose.yml du lab)", networks.join(" ")),
+        );
+    }
+
+    let own_interfaces: Vec<String> = bridges.iter().filter(|n| own(n)).map(bridge_interface).collect();
+    let foreign: Vec<String> = routes
+        .iter()
+        .filter(|(iface, route)| overlaps(*route, net) && !own_interfaces.contains(iface))
+        .map(|(iface, (ip, prefix))| format!("{}/{} via {}", ip, prefix, iface))
+        .collect();
+    match foreign.is_empty() {
+        true => pass(name, format!("{} free", lab.subnet)),
+        false => problem(
+            Status::Warn,
+            name,
+            format!("{} overlaps host route {}", lab.subnet, foreign.join(", ")),
+            "VPN ou réseau local sur la même plage: le trafic vers la cible peut partir ailleurs.\n\
+             Changer subnet dans labs.toml et le docker-compose.yml du lab",
+        ),
+    }
+}
+
+/// Port libre, ou déjà pris par le lab qui le publie.
+fn host_port(port: u16, lab: &str, service: &str, lab_running: bool) -> Check {
+    let name = format!("port {}", port);
+    match TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))) {
+        Ok(_) => pass(name, format!("free ({} of lab {})", service, lab)),
+        Err(_) if lab_running => pass(name, format!("used by {} (lab {} running)", service, lab)),
+        Err(e) => problem(
+            Status::Fail,
+            name,
+            format!("in use ({}), needed by {} of lab {}", e, service, lab),
+            format!("sudo ss -ltnp 'sport = :{0}' ; docker ps --filter publish={0}", port),
+        ),
+    }
+}
+
+/// AppArmor confine les outils installés en snap (raw sockets refusés).
+fn apparmor() -> Check {
+    let enabled = std::fs::read_to_string("/sys/module/apparmor/parameters/enabled")
+        .map(|s| s.trim() == "Y")
+        .unwrap_or(false);
+    if !enabled {
+        return pass("apparmor", "disabled");
+    }
+    let confined: Vec<String> = [attack::nmap_bin(), PathBuf::from("hping3")]
+        .iter()
+        .filter_map(|bin| find_executable(bin))
+        .filter(|path| path.starts_with("/snap"))
+        .map(|path| path.display().to_string())
+        .collect();
+    match confined.is_empty() {
+        true => pass("apparmor", "enabled, attack tools not confined"),
+        false => problem(
+            Status::Warn,
+            "apparmor",
+            format!("snap packages confined by AppArmor: {}", confined.join(", ")),
+            "sudo snap remove nmap && sudo apt install nmap",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_routes_collide_with_lab_subnets() {
+        let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+                     eth0\t00000000\t0102A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n\
+                     tun0\t00001CAC\t00000000\t0001\t0\t0\t0\t0000FCFF\t0\t0\t0\n\
+                     br-0123456789ab\t00001DAC\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0\n";
+        let routes = parse_routes(table);
+        assert_eq!(routes, vec![
+            ("tun0".to_string(), (Ipv4Addr::new(172, 28, 0, 0), 14)),
+            ("br-0123456789ab".to_string(), (Ipv4Addr::new(172, 29, 0, 0), 24)),
+        ]);
+        assert!(overlaps(parse_net("172.31.0.0/24").unwrap(), routes[0].1));
+        assert!(!overlaps(parse_net("172.32.0.0/24").unwrap(), routes[0].1));
+
+        let registry = labs::Registry::parse(
+            "[[lab]]\nname = \"suricata\"\ntitle = \"S\"\ncompose_dir = \"suricata-lab\"\nsubnet = \"172.29.0.0/24\"",
+        )
+        .unwrap();
+        let lab = &registry.labs()[0];
+        let network = |name: &str| NetworkInfo {
+            name: name.into(),
+            id: "0123456789abcdef".into(),
+            driver: "bridge".into(),
+            subnets: vec!["172.29.0.0/24".into()],
+        };
+        // Le bridge du lab lui-même n'est pas une collision, le VPN si
+        let check = subnet(lab, &routes, &[network("suricata-lab_suricata_net")]);
+        assert_eq!(check.status, Status::Warn);
+        assert!(check.detail.contains("tun0") && !check.detail.contains("br-"));
+        assert_eq!(subnet(lab, &routes[1..], &[network("suricata-lab_suricata_net")]).status, Status::Pass);
+        assert_eq!(subnet(lab, &[], &[network("webapp_default")]).status, Status::Fail);
+
+        assert_eq!(parse_version("v2.29.7"), Some((2, 29)));
+        assert!(parse_version("1.29.2").unwrap() < MIN_COMPOSE);
+    }
+}
@@ -0,0 +1,119 @@
+// =============================================================================
+// IDS LAB COMMANDER - Filtrage et pagination des alertes
+// =============================================================================
+//
+// DESCRIPTION:
+// Paramètres de requête de GET /api/alerts et /api/alerts/:ids.
+//
+// EXEMPLE:
+//   /api/alerts/suricata?sid=2003002&since=2026-02-28T10:18:00Z&until=2026-02-28T10:20:00Z
+//
+// PAGINATION PAR CURSEUR:
+// Chaque alerte reçoit un identifiant croissant à son ingestion (store.rs).
+// La réponse contient `next_cursor`: le repasser tel quel dans `cursor`
+// pour obtenir la page suivante. Contrairement à un offset, le curseur
+// reste stable même si de nouvelles alertes arrivent entre deux pages.
+//
+// =============================================================================
+
+use crate::alerts::{Alert, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Taille de page par défaut et maximale.
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// Alerte accompagnée de son identifiant d'ingestion (sert de curseur).
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertRecord {
+    pub id: u64,
+    #[serde(flatten)]
+    pub alert: Alert,
+}
+
+/// Ordre de tri (par identifiant, donc par ordre d'arrivée).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Filtres acceptés en query string. Tous sont optionnels et cumulatifs.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AlertQuery {
+    /// Moteur IDS (ignoré sur /api/alerts/:ids où il vient du chemin)
+    pub engine: Option<Engine>,
+    /// Début de la fenêtre (RFC 3339, inclus)
+    pub since: Option<DateTime<Utc>>,
+    /// Fin de la fenêtre (RFC 3339, inclus)
+    pub until: Option<DateTime<Utc>>,
+    pub sid: Option<u32>,
+    pub src_ip: Option<IpAddr>,
+    pub dst_ip: Option<IpAddr>,
+    pub dst_port: Option<u16>,
+    pub priority: Option<u8>,
+    /// Recherche texte dans le message (insensible à la casse)
+    pub q: Option<String>,
+    /// Identifiant de la dernière alerte de la page précédente
+    pub cursor: Option<u64>,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+/// Page de résultats, schéma JSON stable de l'API.
+#[derive(Debug, Serialize)]
+pub struct AlertPage {
+    pub engine: Option<Engine>,
+    pub order: SortOrder,
+    pub count: usize,
+    pub next_cursor: Option<u64>,
+    pub alerts: Vec<AlertRecord>,
+}
+
+impl AlertQuery {
+    /// Vérifie qu'une alerte satisfait tous les filtres (hors curseur).
+    pub fn matches(&self, alert: &Alert) -> bool {
+        self.engine.is_none_or(|e| alert.engine == e)
+            && self.since.is_none_or(|t| alert.timestamp >= t)
+            && self.until.is_none_or(|t| alert.timestamp <= t)
+            && self.sid.is_none_or(|s| alert.sid == Some(s))
+            && self.src_ip.is_none_or(|ip| alert.src_ip == Some(ip))
+            && self.dst_ip.is_none_or(|ip| alert.dst_ip == Some(ip))
+            && self.dst_port.is_none_or(|p| alert.dst_port == Some(p))
+            && self.priority.is_none_or(|p| alert.priority == Some(p))
+            && self
+                .q
+                .as_deref()
+                .is_none_or(|q| alert.message.to_lowercase().contains(&q.to_lowercase()))
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::{AlertParser, LogFormat};
+
+    #[test]
+    fn matches_combines_filters() {
+        let line = "02/28/2026-10:21:16.460508  [**] [1:2000007:1] HTTP Request Detected [**] [Classification: (null)] [Priority: 3] {TCP} 172.29.0.1:45416 -> 172.29.0.100:80";
+        let alert = AlertParser::new(LogFormat::SuricataFast).parse_line(line).unwrap();
+
+        let query = AlertQuery { engine: Some(Engine::Suricata), sid: Some(2000007), dst_port: Some(80), q: Some("http".into()), ..Default::default() };
+        assert!(query.matches(&alert));
+
+        let query = AlertQuery { engine: Some(Engine::Snort), ..Default::default() };
+        assert!(!query.matches(&alert));
+
+        let query = AlertQuery { until: Some("2026-02-28T10:21:00Z".parse().unwrap()), ..Default::default() };
+        assert!(!query.matches(&alert));
+    }
+}
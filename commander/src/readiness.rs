@@ -0,0 +1,324 @@
+// =============================================================================
+// IDS LAB COMMANDER - Readiness des labs
+// =============================================================================
+//
+// DESCRIPTION:
+// Un container "Up" ne suffit pas: la cible peut ne pas répondre, Snort peut
+// être mort dans son container ou Suricata avoir ignoré des règles mal
+// écrites. Une tâche de fond (`run_prober`) sonde chaque lab démarré toutes
+// les PROBE_INTERVAL secondes et en déduit un état, avec ses raisons.
+//
+// SONDES:
+//   containers    tous les containers du lab tournent (et sont healthy)
+//   target_ports  ports de la cible (labs.toml) joignables depuis le commander
+//   ids_process   le processus de l'IDS tourne dans son container
+//   rules         pas d'erreur de règles dans la sortie de l'IDS depuis son
+//                 démarrage ou son dernier rechargement
+//   log_activity  un fichier de logs_dir a été écrit depuis moins de LOG_STALE
+//
+// ÉTATS:
+//   ready     toutes les sondes passent
+//   degraded  le lab fonctionne partiellement (port fermé, règles ignorées,
+//             logs inactifs, service annexe arrêté)
+//   failed    le lab est inutilisable (IDS arrêté, cible injoignable)
+//   stopped   aucun container démarré, pas de sonde
+//
+// Les résultats sont servis par GET /api/health et affichés sur les cartes
+// des labs.
+//
+// =============================================================================
+
+use crate::alerts::Engine;
+use crate::docker::{self, ContainerInfo};
+use crate::labs::{self, Lab, Role};
+use crate::{config, snapshot};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tokio::net::TcpStream;
+
+/// Intervalle entre deux sondes de tous les labs.
+const PROBE_INTERVAL: Duration = Duration::from_secs(15);
+/// Délai de connexion aux ports de la cible.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Au-delà, les logs de l'IDS sont considérés inactifs.
+const LOG_STALE: Duration = Duration::from_secs(10 * 60);
+/// Lignes de sortie de l'IDS examinées pour les erreurs de règles.
+const RULE_LOG_LINES: usize = 2000;
+
+static READINESS: Mutex<BTreeMap<String, LabReadiness>> = Mutex::new(BTreeMap::new());
+
+/// État d'un lab ou d'une sonde, du meilleur au pire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Readiness {
+    Stopped,
+    Ready,
+    Degraded,
+    Failed,
+}
+
+/// Résultat d'une sonde.
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    /// ready, degraded ou failed
+    pub state: Readiness,
+    pub detail: String,
+}
+
+/// Readiness d'un lab (GET /api/health).
+#[derive(Debug, Clone, Serialize)]
+pub struct LabReadiness {
+    pub lab: String,
+    pub state: Readiness,
+    /// Détail des sondes qui ne passent pas, la plus grave en premier
+    pub reasons: Vec<String>,
+    pub checks: Vec<Check>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl LabReadiness {
+    fn new(lab: &str, checks: Vec<Check>) -> LabReadiness {
+        let mut failing: Vec<&Check> = checks.iter().filter(|c| c.state > Readiness::Ready).collect();
+        failing.sort_by_key(|c| std::cmp::Reverse(c.state));
+        LabReadiness {
+            lab: lab.to_string(),
+            state: checks.iter().map(|c| c.state).max().unwrap_or(Readiness::Ready),
+            reasons: failing.iter().map(|c| c.detail.clone()).collect(),
+            checks,
+            checked_at: Utc::now(),
+        }
+    }
+}
+
+fn check(name: &'static str, state: Readiness, detail: impl Into<String>) -> Check {
+    Check { name, state, detail: detail.into() }
+}
+
+/// Dernière readiness connue de chaque lab.
+pub fn current() -> BTreeMap<String, LabReadiness> {
+    READINESS.lock().unwrap().clone()
+}
+
+/// Tâche de fond: sonde tous les labs à intervalle régulier.
+pub async fn run_prober() {
+    let mut interval = tokio::time::interval(PROBE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let snapshot = snapshot::current();
+        let results = futures_util::future::join_all(
+            labs::registry().labs().iter().map(|lab| probe(lab, &snapshot.containers)),
+        )
+        .await;
+        *READINESS.lock().unwrap() = results.into_iter().map(|r| (r.lab.clone(), r)).collect();
+    }
+}
+
+/// Sonde un lab à partir de ses containers dans l'instantané Docker.
+async fn probe(lab: &Lab, containers: &[ContainerInfo]) -> LabReadiness {
+    let containers: Vec<&ContainerInfo> = containers.iter().filter(|c| c.lab == lab.name).collect();
+    if !containers.iter().any(|c| c.is_running()) {
+        let mut readiness = LabReadiness::new(&lab.name, vec![]);
+        readiness.state = Readiness::Stopped;
+        return readiness;
+    }
+
+    let mut checks = vec![containers_check(&containers)];
+    if let Some(ip) = lab.target_ip.filter(|_| !lab.ports.is_empty()) {
+        checks.push(target_ports(ip, &lab.ports).await);
+    }
+    let ids = containers.iter().find(|c| c.role == Some(Role::Ids));
+    if let Some(engine) = lab.engine {
+        match ids {
+            Some(ids) if ids.is_running() => {
+                checks.push(ids_process(engine, &ids.name).await);
+                checks.push(rules(engine, &ids.name).await);
+            }
+            Some(ids) => checks.push(check(
+                "ids_process",
+                Readiness::Failed,
+                format!("{} is {} (exit code {})", ids.name, ids.state, ids.exit_code.unwrap_or_default()),
+            )),
+            None => checks.push(check("ids_process", Readiness::Failed, format!("no {} container", engine))),
+        }
+    }
+    if let Some(dir) = lab.logs_dir(&config::get().project_root) {
+        checks.push(log_activity(&dir, SystemTime::now()));
+    }
+    LabReadiness::new(&lab.name, checks)
+}
+
+/// Containers arrêtés ou unhealthy (l'IDS est jugé par ids_process).
+fn containers_check(containers: &[&ContainerInfo]) -> Check {
+    let problems: Vec<String> = containers
+        .iter()
+        .filter(|c| c.role != Some(Role::Ids))
+        .filter_map(|c| match (c.is_running(), c.health.as_deref()) {
+            (false, _) => Some(format!("{} is {}", c.name, c.state)),
+            (true, Some("unhealthy")) => Some(format!("{} is unhealthy", c.name)),
+            _ => None,
+        })
+        .collect();
+    match problems.is_empty() {
+        true => check("containers", Readiness::Ready, format!("{} container(s) up", containers.len())),
+        false => check("containers", Readiness::Degraded, problems.join(", ")),
+    }
+}
+
+/// Connexion TCP à chaque port de la cible, en parallèle.
+async fn target_ports(ip: IpAddr, ports: &[u16]) -> Check {
+    let attempts = ports.iter().map(|&port| async move {
+        let result = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(SocketAddr::new(ip, port))).await;
+        match result {
+            Ok(Ok(_)) => Ok(port),
+            Ok(Err(e)) => Err(format!("{} ({})", port, e)),
+            Err(_) => Err(format!("{} (timeout)", port)),
+        }
+    });
+    let results = futures_util::future::join_all(attempts).await;
+    let closed: Vec<String> = results.into_iter().filter_map(Result::err).collect();
+
+    if closed.is_empty() {
+        check("target_ports", Readiness::Ready, format!("{}: ports {} reachable", ip, join_ports(ports)))
+    } else if closed.len() == ports.len() {
+        check("target_ports", Readiness::Failed, format!("target {} unreachable: {}", ip, closed.join(", ")))
+    } else {
+        check("target_ports", Readiness::Degraded, format!("target {}: port {} unreachable", ip, closed.join(", ")))
+    }
+}
+
+fn join_ports(ports: &[u16]) -> String {
+    ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Le processus de l'IDS (snort, suricata, zeek) tourne dans son container.
+async fn ids_process(engine: Engine, container: &str) -> Check {
+    match docker::processes(container).await {
+        Ok(commands) if commands.iter().any(|c| c.contains(engine.as_str())) => {
+            check("ids_process", Readiness::Ready, format!("{} running in {}", engine, container))
+        }
+        Ok(_) => check("ids_process", Readiness::Failed, format!("no {} process in {}", engine, container)),
+        Err(e) => check("ids_process", Readiness::Degraded, format!("cannot list processes of {}: {}", container, e)),
+    }
+}
+
+/// Erreurs de règles dans la sortie de l'IDS depuis son démarrage.
+async fn rules(engine: Engine, container: &str) -> Check {
+    match docker::logs_since_start(container, RULE_LOG_LINES).await {
+        Ok(output) => match rule_errors(engine, &output).as_slice() {
+            [] => check("rules", Readiness::Ready, "rules loaded without error"),
+            errors => check("rules", Readiness::Degraded, format!("{} rule error(s): {}", errors.len(), errors[0])),
+        },
+        Err(e) => check("rules", Readiness::Degraded, format!("cannot read output of {}: {}", container, e)),
+    }
+}
+
+/// Lignes d'erreur de chargement des règles, après le dernier rechargement
+/// (SIGHUP de Snort, USR2 de Suricata: les erreurs précédentes sont périmées).
+fn rule_errors(engine: Engine, output: &str) -> Vec<String> {
+    let lines: Vec<&str> = output.lines().map(str::trim).collect();
+    let start = lines
+        .iter()
+        .rposition(|l| l.to_lowercase().contains("reload"))
+        .map_or(0, |i| i + 1);
+    lines[start..]
+        .iter()
+        .filter(|line| {
+            let lower = line.to_lowercase();
+            match engine {
+                // Snort 3: "ERROR: /etc/snort/rules/local.rules:12 ..." puis "FATAL: see prior 1 errors"
+                Engine::Snort => line.starts_with("ERROR") || line.starts_with("FATAL"),
+                // Suricata: "Error: detect: error parsing signature ..." (<Error> avant la 7)
+                Engine::Suricata => {
+                    lower.contains("error")
+                        && ["signature", "rule", "detect"].iter().any(|w| lower.contains(w))
+                }
+                // Zeek: "error in /usr/local/zeek/share/zeek/site/local.zeek, line 3: ..."
+                Engine::Zeek => lower.contains("error in ") || lower.starts_with("fatal error"),
+            }
+        })
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Dernière écriture dans les logs de l'IDS (répertoire et sous-répertoires,
+/// Zeek écrit dans logs/current).
+fn log_activity(dir: &Path, now: SystemTime) -> Check {
+    let Some(modified) = latest_write(dir, 2) else {
+        return check("log_activity", Readiness::Degraded, format!("no log file in {}", dir.display()));
+    };
+    let age = now.duration_since(modified).unwrap_or_default();
+    let state = if age > LOG_STALE { Readiness::Degraded } else { Readiness::Ready };
+    check("log_activity", state, format!("last log write {} ago", format_age(age)))
+}
+
+fn latest_write(dir: &Path, depth: usize) -> Option<SystemTime> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            match metadata.is_dir() {
+                true if depth > 0 => latest_write(&entry.path(), depth - 1),
+                true => None,
+                false => metadata.modified().ok(),
+            }
+        })
+        .max()
+}
+
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        s @ 0..=119 => format!("{}s", s),
+        s @ 120..=7199 => format!("{} min", s / 60),
+        s => format!("{} h", s / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_errors_and_log_activity_set_the_state() {
+        let snort = "--------------------------------------------------\n\
+                     ERROR: /etc/snort/rules/local.rules:3 unknown rule keyword: conten.\n\
+                     FATAL: see prior 1 errors\n";
+        assert_eq!(rule_errors(Engine::Snort, snort).len(), 2);
+        // Erreurs corrigées au rechargement suivant
+        let reloaded = format!("{}== reloading configuration\n== reload complete\n", snort);
+        assert!(rule_errors(Engine::Snort, &reloaded).is_empty());
+
+        let suricata = "Info: detect: 12 rules successfully loaded, 1 rules failed\n\
+                        Error: detect-parse: unknown rule keyword 'conten'.\n\
+                        Error: af-packet: eth1: failed to find interface\n";
+        assert_eq!(rule_errors(Engine::Suricata, suricata), vec!["Error: detect-parse: unknown rule keyword 'conten'."]);
+        let zeek = "error in /usr/local/zeek/share/zeek/site/local.zeek, line 3: unknown identifier\n";
+        assert_eq!(rule_errors(Engine::Zeek, zeek).len(), 1);
+
+        let dir = std::env::temp_dir().join(format!("ids-readiness-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("current")).unwrap();
+        assert_eq!(log_activity(&dir, SystemTime::now()).state, Readiness::Degraded);
+        std::fs::write(dir.join("current").join("notice.log"), "x").unwrap();
+        assert_eq!(log_activity(&dir, SystemTime::now()).state, Readiness::Ready);
+        let later = log_activity(&dir, SystemTime::now() + LOG_STALE * 2);
+        assert_eq!((later.state, later.detail.as_str()), (Readiness::Degraded, "last log write 20 min ago"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let lab = LabReadiness::new(
+            "snort",
+            vec![
+                check("log_activity", Readiness::Degraded, "last log write 20 min ago"),
+                check("ids_process", Readiness::Failed, "no snort process in snort_ids"),
+                check("rules", Readiness::Ready, "rules loaded without error"),
+            ],
+        );
+        assert_eq!(lab.state, Readiness::Failed);
+        assert_eq!(lab.reasons, vec!["no snort process in snort_ids", "last log write 20 min ago"]);
+    }
+}
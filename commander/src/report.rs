@@ -0,0 +1,468 @@
+// =============================================================================
+// IDS LAB COMMANDER - Rapports de campagne d'évasion
+// =============================================================================
+//
+// DESCRIPTION:
+// Génère le rapport d'une campagne à partir des matrices (matrix.rs), des
+// runs (runs.rs) et des alertes stockées (store.rs). Appelé via
+// GET /api/report/:format.
+//
+// CONTENU:
+// - Matrice de détection technique × niveau pour chaque lab
+// - Top des SIDs déclenchés sur l'ensemble des runs du rapport
+// - Chronologie de chaque run (alertes horodatées relativement au début)
+//
+// FORMATS:
+// - html:  page autonome (CSS inline, aucune ressource externe)
+// - md:    tables Markdown pour le wiki
+// - json:  structure complète de `Report`
+// - csv:   une ligne par cellule de matrice (lab, moteur, niveau, technique...)
+//
+// =============================================================================
+
+use crate::alerts::Engine;
+use crate::attack::TECHNIQUES;
+use crate::html::escape;
+use crate::matrix::{Matrix, LEVELS};
+use crate::query::{AlertQuery, AlertRecord, SortOrder};
+use crate::runs::{Run, SidCount};
+use crate::store::AlertStore;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Nombre maximal d'alertes listées dans la chronologie d'un run.
+const TIMELINE_LIMIT: usize = 200;
+
+/// Nombre de SIDs du classement.
+const TOP_SIDS: usize = 15;
+
+/// Format de sortie demandé.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Html,
+    Markdown,
+    Json,
+    Csv,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "html" => Ok(ReportFormat::Html),
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            other => Err(format!("Unknown report format: {} (html, md, json, csv)", other)),
+        }
+    }
+}
+
+impl ReportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Html => "text/html; charset=utf-8",
+            ReportFormat::Markdown => "text/markdown; charset=utf-8",
+            ReportFormat::Json => "application/json",
+            ReportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Html => "html",
+            ReportFormat::Markdown => "md",
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Sélection du rapport: GET /api/report/:format?matrices=1,4&runs=12,13
+/// Sans sélection: dernière matrice terminée de chaque lab.
+#[derive(Debug, Default, Deserialize)]
+pub struct ReportQuery {
+    pub matrices: Option<String>,
+    pub runs: Option<String>,
+}
+
+impl ReportQuery {
+    /// Identifiants d'une liste "1,4,7".
+    pub fn ids(list: &Option<String>) -> Result<Vec<u64>, String> {
+        list.as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().map_err(|_| format!("Invalid id: {}", s)))
+            .collect()
+    }
+}
+
+/// SID classé, avec son moteur.
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineSidCount {
+    pub engine: Engine,
+    #[serde(flatten)]
+    pub sid: SidCount,
+}
+
+/// Run et ses alertes, dans l'ordre chronologique.
+#[derive(Debug, Serialize)]
+pub struct RunTimeline {
+    #[serde(flatten)]
+    pub run: Run,
+    pub total_alerts: u64,
+    /// Au plus TIMELINE_LIMIT alertes
+    pub alerts: Vec<AlertRecord>,
+}
+
+/// Rapport complet (schéma de l'export JSON).
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub generated_at: DateTime<Utc>,
+    pub matrices: Vec<Matrix>,
+    pub top_sids: Vec<EngineSidCount>,
+    pub runs: Vec<RunTimeline>,
+}
+
+/// Assemble le rapport des matrices et runs donnés.
+/// Les runs des cellules de matrice sont inclus automatiquement.
+pub fn build(store: &AlertStore, matrices: Vec<Matrix>, extra_runs: Vec<Run>) -> rusqlite::Result<Report> {
+    let mut runs: Vec<Run> = Vec::new();
+    for run_id in matrices.iter().flat_map(|m| m.cells.iter().map(|c| c.run_id)) {
+        if let Some(run) = store.run(run_id)? {
+            runs.push(run);
+        }
+    }
+    runs.extend(extra_runs);
+    runs.sort_by_key(|r| r.id);
+    runs.dedup_by_key(|r| r.id);
+
+    let mut totals: HashMap<(Engine, Option<u32>, String), u64> = HashMap::new();
+    let mut timelines = Vec::with_capacity(runs.len());
+    for run in runs {
+        let counts = store.sid_counts(&run)?;
+        for c in &counts {
+            *totals.entry((run.engine, c.sid, c.message.clone())).or_default() += c.count;
+        }
+        let query = AlertQuery {
+            engine: Some(run.engine),
+            since: Some(run.started_at),
+            until: Some(run.window_end()),
+            limit: Some(TIMELINE_LIMIT),
+            order: SortOrder::Asc,
+            ..Default::default()
+        };
+        timelines.push(RunTimeline {
+            total_alerts: counts.iter().map(|c| c.count).sum(),
+            alerts: store.query(&query)?.alerts,
+            run,
+        });
+    }
+
+    let mut top_sids: Vec<EngineSidCount> = totals
+        .into_iter()
+        .map(|((engine, sid, message), count)| EngineSidCount { engine, sid: SidCount { sid, message, count } })
+        .collect();
+    top_sids.sort_by(|a, b| b.sid.count.cmp(&a.sid.count).then(a.sid.sid.cmp(&b.sid.sid)));
+    top_sids.truncate(TOP_SIDS);
+
+    Ok(Report { generated_at: Utc::now(), matrices, top_sids, runs: timelines })
+}
+
+/// Rend le rapport dans le format demandé.
+pub fn render(report: &Report, format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Html => to_html(report),
+        ReportFormat::Markdown => to_markdown(report),
+        ReportFormat::Json => serde_json::to_string_pretty(report).unwrap_or_default(),
+        ReportFormat::Csv => to_csv(report),
+    }
+}
+
+// ============================================================================
+// RENDUS
+// ============================================================================
+
+/// Lignes de la matrice: (nom de la technique, cellule de chaque niveau).
+fn matrix_rows(matrix: &Matrix) -> Vec<(String, Vec<String>)> {
+    let mut ids: Vec<&str> = TECHNIQUES.iter().map(|t| t.id).collect();
+    for cell in &matrix.cells {
+        if !ids.contains(&cell.technique.as_str()) {
+            ids.push(&cell.technique);
+        }
+    }
+    ids.into_iter()
+        .filter(|id| matrix.cells.iter().any(|c| c.technique == *id))
+        .map(|id| {
+            let name = TECHNIQUES.iter().find(|t| t.id == id).map_or(id, |t| t.name);
+            let cells = LEVELS
+                .iter()
+                .map(|level| match matrix.cells.iter().find(|c| c.technique == id && c.level == *level) {
+                    Some(c) if c.detected => format!("DETECTED ({})", c.alerts),
+                    Some(_) => "evaded".to_string(),
+                    None => "-".to_string(),
+                })
+                .collect();
+            (name.to_string(), cells)
+        })
+        .collect()
+}
+
+fn matrix_title(matrix: &Matrix) -> String {
+    format!("{} - matrix #{} ({})", matrix.lab, matrix.id, matrix.started_at.format("%Y-%m-%d %H:%M UTC"))
+}
+
+fn sid_label(sid: Option<u32>) -> String {
+    sid.map_or("-".to_string(), |s| s.to_string())
+}
+
+/// Ligne de chronologie: décalage depuis le début du run, SID, message, flux.
+fn timeline_line(run: &Run, alert: &AlertRecord) -> String {
+    let a = &alert.alert;
+    let offset = (a.timestamp - run.started_at).num_milliseconds() as f64 / 1000.0;
+    let endpoint = |ip: Option<std::net::IpAddr>, port: Option<u16>| match (ip, port) {
+        (Some(ip), Some(port)) => format!("{}:{}", ip, port),
+        (Some(ip), None) => ip.to_string(),
+        _ => "-".to_string(),
+    };
+    format!(
+        "+{:.3}s [{}] {} {} -> {}",
+        offset,
+        sid_label(a.sid),
+        a.message,
+        endpoint(a.src_ip, a.src_port),
+        endpoint(a.dst_ip, a.dst_port)
+    )
+}
+
+fn md_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn to_markdown(report: &Report) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# IDS evasion report\n\nGenerated {}\n", report.generated_at.to_rfc3339());
+
+    let _ = writeln!(out, "## Detection matrix\n");
+    if report.matrices.is_empty() {
+        let _ = writeln!(out, "_No evasion matrix measured._\n");
+    }
+    for matrix in &report.matrices {
+        let _ = writeln!(out, "### {}\n", matrix_title(matrix));
+        let _ = writeln!(out, "| Technique | {} |", LEVELS.map(|l| format!("Level {}", l)).join(" | "));
+        let _ = writeln!(out, "|---|{}", "---|".repeat(LEVELS.len()));
+        for (name, cells) in matrix_rows(matrix) {
+            let _ = writeln!(out, "| {} | {} |", md_cell(&name), cells.join(" | "));
+        }
+        out.push('\n');
+    }
+
+    let _ = writeln!(out, "## Top SIDs\n");
+    let _ = writeln!(out, "| Engine | SID | Message | Alerts |\n|---|---|---|---|");
+    for s in &report.top_sids {
+        let _ = writeln!(out, "| {} | {} | {} | {} |", s.engine, sid_label(s.sid.sid), md_cell(&s.sid.message), s.sid.count);
+    }
+
+    let _ = writeln!(out, "\n## Run timelines\n");
+    for t in &report.runs {
+        let _ = writeln!(out, "### Run #{} - {} level {}\n", t.run.id, t.run.engine, t.run.rule_level);
+        let _ = writeln!(out, "- Command: `{}`", t.run.command);
+        let _ = writeln!(out, "- Target: {}", t.run.target);
+        let _ = writeln!(out, "- Window: {} → {}", t.run.started_at.to_rfc3339(), t.run.ended_at.map_or("running".into(), |e| e.to_rfc3339()));
+        let _ = writeln!(out, "- Exit code: {}", t.run.exit_code.map_or("-".into(), |c| c.to_string()));
+        let _ = writeln!(out, "- Alerts: {}\n", t.total_alerts);
+        if !t.alerts.is_empty() {
+            let _ = writeln!(out, "```");
+            for alert in &t.alerts {
+                let _ = writeln!(out, "{}", timeline_line(&t.run, alert));
+            }
+            let _ = writeln!(out, "```\n");
+        }
+    }
+    out
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn to_csv(report: &Report) -> String {
+    let runs: HashMap<u64, &Run> = report.runs.iter().map(|t| (t.run.id, &t.run)).collect();
+    let mut out = String::from("matrix_id,lab,engine,level,technique,detected,alerts,run_id,command,started_at,ended_at,exit_code\n");
+    for matrix in &report.matrices {
+        for cell in &matrix.cells {
+            let run = runs.get(&cell.run_id);
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{},{},{}",
+                matrix.id,
+                csv_field(&matrix.lab),
+                matrix.engine,
+                cell.level,
+                csv_field(&cell.technique),
+                cell.detected,
+                cell.alerts,
+                cell.run_id,
+                csv_field(run.map_or("", |r| r.command.as_str())),
+                run.map_or(String::new(), |r| r.started_at.to_rfc3339()),
+                run.and_then(|r| r.ended_at).map_or(String::new(), |e| e.to_rfc3339()),
+                run.and_then(|r| r.exit_code).map_or(String::new(), |c| c.to_string()),
+            );
+        }
+    }
+    out
+}
+
+fn to_html(report: &Report) -> String {
+    let mut body = String::new();
+
+    let _ = writeln!(body, "<h2>Detection matrix</h2>");
+    if report.matrices.is_empty() {
+        let _ = writeln!(body, "<p><em>No evasion matrix measured.</em></p>");
+    }
+    for matrix in &report.matrices {
+        let _ = writeln!(body, "<h3>{}</h3>\n<table>", escape(&matrix_title(matrix)));
+        let _ = writeln!(body, "<tr><th>Technique</th>{}</tr>", LEVELS.map(|l| format!("<th>Level {}</th>", l)).join(""));
+        for (name, cells) in matrix_rows(matrix) {
+            let cells: String = cells
+                .iter()
+                .map(|c| {
+                    let class = if c.starts_with("DETECTED") { "detected" } else if c == "evaded" { "evaded" } else { "" };
+                    format!(r#"<td class="{}">{}</td>"#, class, escape(c))
+                })
+                .collect();
+            let _ = writeln!(body, "<tr><td>{}</td>{}</tr>", escape(&name), cells);
+        }
+        let _ = writeln!(body, "</table>");
+    }
+
+    let _ = writeln!(body, "<h2>Top SIDs</h2>\n<table>\n<tr><th>Engine</th><th>SID</th><th>Message</th><th>Alerts</th></tr>");
+    for s in &report.top_sids {
+        let _ = writeln!(
+            body,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            s.engine,
+            sid_label(s.sid.sid),
+            escape(&s.sid.message),
+            s.sid.count
+        );
+    }
+    let _ = writeln!(body, "</table>\n<h2>Run timelines</h2>");
+    for t in &report.runs {
+        let _ = writeln!(
+            body,
+            "<h3>Run #{} - {} level {}</h3>\n<p><code>{}</code><br>Target {} · {} → {} · exit code {} · {} alert(s)</p>",
+            t.run.id,
+            t.run.engine,
+            escape(&t.run.rule_level),
+            escape(&t.run.command),
+            t.run.target,
+            t.run.started_at.to_rfc3339(),
+            t.run.ended_at.map_or("running".into(), |e| e.to_rfc3339()),
+            t.run.exit_code.map_or("-".into(), |c| c.to_string()),
+            t.total_alerts
+        );
+        if !t.alerts.is_empty() {
+            let lines: Vec<String> = t.alerts.iter().map(|a| escape(&timeline_line(&t.run, a))).collect();
+            let _ = writeln!(body, "<pre>{}</pre>", lines.join("\n"));
+        }
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="UTF-8">
+<title>IDS evasion report - {generated}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #1a1a2e; }}
+table {{ border-collapse: collapse; margin-bottom: 1.5rem; }}
+th, td {{ border: 1px solid #ccc; padding: 0.3rem 0.6rem; text-align: center; }}
+td:first-child {{ text-align: left; }}
+.detected {{ background: #fecaca; }}
+.evaded {{ background: #bbf7d0; }}
+pre {{ background: #f4f4f5; padding: 0.75rem; font-size: 0.8rem; overflow-x: auto; }}
+</style>
+</head>
+<body>
+<h1>IDS evasion report</h1>
+<p>Generated {generated}</p>
+{body}
+</body>
+</html>
+"#,
+        generated = report.generated_at.to_rfc3339(),
+        body = body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{MatrixCell, MatrixStatus};
+
+    fn sample_report() -> Report {
+        let started_at: DateTime<Utc> = "2026-02-28T10:20:00Z".parse().unwrap();
+        let run = Run {
+            id: 7,
+            engine: Engine::Suricata,
+            rule_level: "3 - Moderate".into(),
+            target: "172.29.0.100".parse().unwrap(),
+            command: "nmap -sS --mtu 8 172.29.0.100".into(),
+            started_at,
+            ended_at: Some(started_at + chrono::Duration::seconds(5)),
+            exit_code: Some(0),
+        };
+        let matrix = Matrix {
+            id: 1,
+            lab: "suricata".into(),
+            engine: Engine::Suricata,
+            status: MatrixStatus::Completed,
+            error: None,
+            started_at,
+            ended_at: None,
+            cells: vec![MatrixCell { level: 3, technique: "mtu8".into(), run_id: 7, alerts: 2, detected: true }],
+        };
+        Report {
+            generated_at: started_at,
+            matrices: vec![matrix],
+            top_sids: vec![EngineSidCount {
+                engine: Engine::Suricata,
+                sid: SidCount { sid: Some(2000001), message: "<script> | pipe".into(), count: 2 },
+            }],
+            runs: vec![RunTimeline { run, total_alerts: 2, alerts: vec![] }],
+        }
+    }
+
+    #[test]
+    fn renders_matrix_in_every_format() {
+        let report = sample_report();
+
+        let md = render(&report, ReportFormat::Markdown);
+        assert!(md.contains("| MTU 8 | - | - | DETECTED (2) | - | - |"));
+        assert!(md.contains("<script> \\| pipe"));
+
+        let html = render(&report, ReportFormat::Html);
+        assert!(html.contains(r#"<td class="detected">DETECTED (2)</td>"#));
+        assert!(html.contains("&lt;script&gt;"));
+
+        let csv = render(&report, ReportFormat::Csv);
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("matrix_id,lab,engine,level,technique,detected"));
+        assert!(lines.next().unwrap().starts_with("1,suricata,suricata,3,mtu8,true,2,7,nmap -sS --mtu 8 172.29.0.100,"));
+
+        let json: serde_json::Value = serde_json::from_str(&render(&report, ReportFormat::Json)).unwrap();
+        assert_eq!(json["matrices"][0]["cells"][0]["detected"], true);
+        assert_eq!(json["runs"][0]["exit_code"], 0);
+    }
+}
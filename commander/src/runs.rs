@@ -0,0 +1,75 @@
+// =============================================================================
+// IDS LAB COMMANDER - Sessions d'attaque ("runs")
+// =============================================================================
+//
+// DESCRIPTION:
+// Un run relie une session d'attaque (ex: un scan nmap lancé contre la
+// cible) aux alertes qu'elle a produites. Il mémorise le contexte du test:
+// - le moteur IDS observé et le niveau de règles actif au démarrage
+// - l'IP cible et la commande d'attaque
+// - la fenêtre temporelle [started_at, ended_at]
+//
+// Les alertes du run sont celles du moteur dont l'horodatage tombe dans
+// cette fenêtre; elles sont servies par GET /api/runs/:id/alerts avec les
+// filtres habituels (query.rs), et résumées par SID dans GET /api/runs/:id.
+//
+// EXEMPLE:
+//   POST /api/runs        {"engine":"suricata","target":"172.29.0.100","command":"nmap -sS 172.29.0.100"}
+//   POST /api/runs/1/stop
+//   GET  /api/runs/1      → run + nombre d'alertes par SID
+//
+// =============================================================================
+
+use crate::alerts::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Session d'attaque enregistrée.
+#[derive(Debug, Clone, Serialize)]
+pub struct Run {
+    pub id: u64,
+    pub engine: Engine,
+    /// Niveau de règles actif au démarrage (ex: "3 - Moderate", "Custom")
+    pub rule_level: String,
+    pub target: IpAddr,
+    pub command: String,
+    pub started_at: DateTime<Utc>,
+    /// None tant que le run est en cours
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Code de retour de l'attaque lancée par le commander (attack.rs);
+    /// None pour un run ouvert/fermé à la main
+    pub exit_code: Option<i32>,
+}
+
+/// Corps de POST /api/runs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunStart {
+    pub engine: Engine,
+    pub target: IpAddr,
+    pub command: String,
+}
+
+/// Nombre d'alertes d'une signature pendant un run.
+#[derive(Debug, Clone, Serialize)]
+pub struct SidCount {
+    pub sid: Option<u32>,
+    pub message: String,
+    pub count: u64,
+}
+
+/// Réponse de GET /api/runs/:id.
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    #[serde(flatten)]
+    pub run: Run,
+    pub total_alerts: u64,
+    pub sids: Vec<SidCount>,
+}
+
+impl Run {
+    /// Fin de la fenêtre d'alertes: l'arrêt du run, ou maintenant s'il est en cours.
+    pub fn window_end(&self) -> DateTime<Utc> {
+        self.ended_at.unwrap_or_else(Utc::now)
+    }
+}
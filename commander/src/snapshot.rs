@@ -0,0 +1,199 @@
+// =============================================================================
+// IDS LAB COMMANDER - Instantané de l'état Docker
+// =============================================================================
+//
+// DESCRIPTION:
+// Une tâche de fond (`run_poller`) tient à jour un instantané partagé des
+// containers, réseaux et labs. Les handlers lisent cet instantané au lieu
+// d'interroger Docker à chaque rendu du dashboard ou poll de /api/status.
+//
+// RAFRAÎCHISSEMENT:
+// - à chaque rafale d'événements Docker (container, network)
+// - toutes les RESYNC secondes (textes "Up 5 minutes", événements perdus)
+// - après une action du dashboard (start/stop, docker compose)
+// Si le flux d'événements est coupé (Docker arrêté), reconnexion après RETRY.
+//
+// Les événements des containers des labs sont aussi enregistrés dans la
+// base (events.rs); à la reconnexion le flux reprend au dernier événement
+// connu pour combler l'interruption.
+//
+// La version n'avance que si le contenu change: elle sert d'ETag à
+// /api/status, un dashboard inactif reçoit des 304 sans corps.
+//
+// =============================================================================
+
+use crate::docker::{self, ContainerInfo, NetworkInfo};
+use crate::events::DockerEvent;
+use crate::store::AlertStore;
+use crate::{config, labs};
+use bollard::models::EventMessage;
+use bollard::system::EventsOptions;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+
+/// Regroupement des événements d'une rafale (docker compose up).
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// Rafraîchissement périodique même sans événement.
+const RESYNC: Duration = Duration::from_secs(30);
+/// Attente avant de se réabonner aux événements Docker.
+const RETRY: Duration = Duration::from_secs(5);
+
+static SNAPSHOT: OnceLock<watch::Sender<Arc<Snapshot>>> = OnceLock::new();
+/// Un seul rafraîchissement à la fois (poller et actions du dashboard).
+static REFRESH: Mutex<()> = Mutex::const_new(());
+
+/// État Docker vu par le commander.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Snapshot {
+    /// Incrémentée à chaque changement de contenu
+    pub version: u64,
+    /// Démarrage du serveur (distingue les ETags de deux exécutions)
+    pub epoch: i64,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub docker_ok: bool,
+    /// Version du démon ou erreur d'accès
+    pub docker_msg: String,
+    pub containers: Vec<ContainerInfo>,
+    pub bridges: Vec<NetworkInfo>,
+    /// Lab → au moins un container démarré
+    pub labs: BTreeMap<String, bool>,
+}
+
+impl Snapshot {
+    /// ETag de /api/status.
+    pub fn etag(&self) -> String {
+        format!("\"{:x}-{}\"", self.epoch, self.version)
+    }
+
+    pub fn lab_running(&self, name: &str) -> bool {
+        self.labs.get(name).copied().unwrap_or(false)
+    }
+
+    fn same_state(&self, other: &Snapshot) -> bool {
+        self.docker_ok == other.docker_ok
+            && self.docker_msg == other.docker_msg
+            && self.containers == other.containers
+            && self.bridges == other.bridges
+    }
+}
+
+/// Installe l'instantané initial (vide, Docker non vérifié).
+pub fn init() {
+    let snapshot = Snapshot { epoch: Utc::now().timestamp(), ..Default::default() };
+    let _ = SNAPSHOT.set(watch::channel(Arc::new(snapshot)).0);
+}
+
+fn sender() -> &'static watch::Sender<Arc<Snapshot>> {
+    SNAPSHOT.get().expect("snapshot not initialized")
+}
+
+/// Dernier instantané publié.
+pub fn current() -> Arc<Snapshot> {
+    sender().borrow().clone()
+}
+
+/// Relit l'état Docker et publie un nouvel instantané s'il a changé.
+pub async fn refresh() {
+    let _guard = REFRESH.lock().await;
+    let (docker_ok, docker_msg) = docker::check_access().await;
+    let (containers, bridges) = if docker_ok {
+        (
+            docker::lab_containers().await.unwrap_or_default(),
+            docker::bridges().await.unwrap_or_default(),
+        )
+    } else {
+        (vec![], vec![])
+    };
+    let labs = labs::registry()
+        .labs()
+        .iter()
+        .map(|lab| {
+            let running = containers.iter().any(|c| c.lab == lab.name && c.is_running());
+            (lab.name.clone(), running)
+        })
+        .collect();
+
+    sender().send_if_modified(|current| {
+        let next = Snapshot {
+            version: current.version + 1,
+            epoch: current.epoch,
+            updated_at: Some(Utc::now()),
+            docker_ok,
+            docker_msg,
+            containers,
+            bridges,
+            labs,
+        };
+        if current.same_state(&next) {
+            return false;
+        }
+        *current = Arc::new(next);
+        true
+    });
+}
+
+/// Enregistre un événement du flux s'il concerne un container de lab
+/// (écriture SQLite hors des threads du runtime).
+async fn record(store: &Arc<AlertStore>, message: &EventMessage) {
+    let Some(event) = DockerEvent::from_message(message, labs::registry()) else { return };
+    let store = store.clone();
+    match tokio::task::spawn_blocking(move || store.record_event(&event)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => eprintln!("Failed to record Docker event: {}", e),
+        Err(e) => eprintln!("Failed to record Docker event: {}", e),
+    }
+}
+
+/// Tâche de fond: suit les événements Docker, les enregistre et
+/// rafraîchit l'instantané.
+pub async fn run_poller(store: Arc<AlertStore>) {
+    let filters = HashMap::from([("type".to_string(), vec!["container".to_string(), "network".to_string()])]);
+    loop {
+        refresh().await;
+        let docker = match config::docker() {
+            Ok(d) => d,
+            Err(_) => {
+                tokio::time::sleep(RETRY).await;
+                continue;
+            }
+        };
+        // Reprendre au dernier événement enregistré (doublons ignorés par la base)
+        let since = store
+            .last_event_time()
+            .ok()
+            .flatten()
+            .map(|t| format!("{}.{:09}", t.timestamp(), t.timestamp_subsec_nanos()));
+        let mut events = docker.events(Some(EventsOptions::<String> { since, filters: filters.clone(), ..Default::default() }));
+        let mut resync = tokio::time::interval(RESYNC);
+        resync.tick().await;
+
+        loop {
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(Ok(message)) => {
+                        record(&store, &message).await;
+                        // Attendre la fin de la rafale avant de relire l'état
+                        while let Ok(Some(Ok(message))) = tokio::time::timeout(DEBOUNCE, events.next()).await {
+                            record(&store, &message).await;
+                        }
+                        refresh().await;
+                    }
+                    Some(Err(e)) => {
+                        if current().docker_ok {
+                            eprintln!("Docker events stream error: {}", e);
+                        }
+                        break;
+                    }
+                    None => break,
+                },
+                _ = resync.tick() => refresh().await,
+            }
+        }
+        tokio::time::sleep(RETRY).await;
+    }
+}
@@ -0,0 +1,988 @@
+// =============================================================================
+// IDS LAB COMMANDER - Historique persistant des alertes (SQLite)
+// =============================================================================
+//
+// DESCRIPTION:
+// Base SQLite embarquée dans laquelle le commander ingère les alertes des
+// trois IDS. Les alertes survivent ainsi à reset_logs (qui vide
+// suricata-lab/logs) et à la recréation des containers par start_lab.
+//
+// CONTENU:
+// - alerts:          une ligne par alerte, indexée sur le temps, le moteur,
+//                    le SID et les IPs
+// - tail_positions:  position de lecture de chaque fichier de logs, mise à
+//                    jour dans la même transaction que l'insertion des
+//                    alertes (pas de doublons après un redémarrage)
+// - runs:            sessions d'attaque et leur fenêtre temporelle (runs.rs)
+// - matrices:        jobs de matrice d'évasion et leurs cellules (matrix.rs)
+// - docker_events:   démarrages, arrêts, signaux des containers (events.rs)
+// - audit_log:       actions de pilotage (audit.rs), en ajout seul
+// - rule_versions:   contenus successifs des règles actives (history.rs)
+//
+// RÉTENTION:
+// Les alertes plus anciennes que `retention_days` sont purgées, et la table
+// est plafonnée à `max_alerts` lignes (les plus anciennes partent d'abord).
+// Les événements Docker suivent la même durée de rétention. Le journal
+// d'audit et l'historique des règles ne sont jamais purgés.
+//
+// SCHÉMA VERSIONNÉ:
+// PRAGMA user_version indique la dernière migration appliquée; chaque
+// évolution du schéma ajoute une entrée à MIGRATIONS.
+//
+// =============================================================================
+
+use crate::alerts::{Alert, Engine};
+use crate::audit::{AuditEntry, AuditQuery, AuditRecord, Outcome};
+use crate::config::Config;
+use crate::events::{DockerEvent, EventQuery, EventRecord};
+use crate::history::RuleVersion;
+use crate::query::{AlertPage, AlertQuery, AlertRecord, SortOrder};
+use crate::matrix::{Matrix, MatrixCell, MatrixStatus};
+use crate::runs::{Run, SidCount};
+use crate::tail::TailPosition;
+use crate::templates;
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Migrations du schéma, appliquées dans l'ordre.
+const MIGRATIONS: &[&str] = &[
+    // 1 - Alertes et positions de lecture
+    "CREATE TABLE alerts (
+        id             INTEGER PRIMARY KEY AUTOINCREMENT,
+        ts_us          INTEGER NOT NULL,
+        engine         TEXT NOT NULL,
+        gid            INTEGER,
+        sid            INTEGER,
+        rev            INTEGER,
+        message        TEXT NOT NULL,
+        classification TEXT,
+        priority       INTEGER,
+        protocol       TEXT,
+        src_ip         TEXT,
+        src_port       INTEGER,
+        dst_ip         TEXT,
+        dst_port       INTEGER,
+        ingested_at    INTEGER NOT NULL
+    );
+    CREATE INDEX idx_alerts_ts ON alerts(ts_us);
+    CREATE INDEX idx_alerts_engine ON alerts(engine, ts_us);
+    CREATE INDEX idx_alerts_sid ON alerts(sid);
+    CREATE INDEX idx_alerts_src_ip ON alerts(src_ip);
+    CREATE INDEX idx_alerts_dst_ip ON alerts(dst_ip);
+    CREATE TABLE tail_positions (
+        path   TEXT PRIMARY KEY,
+        inode  INTEGER NOT NULL,
+        offset INTEGER NOT NULL
+    );",
+    // 2 - Sessions d'attaque
+    "CREATE TABLE runs (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        engine     TEXT NOT NULL,
+        rule_level TEXT NOT NULL,
+        target     TEXT NOT NULL,
+        command    TEXT NOT NULL,
+        started_us INTEGER NOT NULL,
+        ended_us   INTEGER
+    );",
+    // 3 - Code de retour des attaques lancées par le commander
+    "ALTER TABLE runs ADD COLUMN exit_code INTEGER;",
+    // 4 - Matrices d'évasion
+    "CREATE TABLE matrices (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        engine     TEXT NOT NULL,
+        status     TEXT NOT NULL,
+        error      TEXT,
+        started_us INTEGER NOT NULL,
+        ended_us   INTEGER
+    );
+    CREATE TABLE matrix_cells (
+        matrix_id INTEGER NOT NULL REFERENCES matrices(id),
+        level     INTEGER NOT NULL,
+        technique TEXT NOT NULL,
+        run_id    INTEGER NOT NULL,
+        alerts    INTEGER NOT NULL,
+        PRIMARY KEY (matrix_id, level, technique)
+    );",
+    // 5 - Lab mesuré par une matrice (registre labs.toml)
+    "ALTER TABLE matrices ADD COLUMN lab TEXT;
+    UPDATE matrices SET lab = engine;",
+    // 6 - Événements Docker des containers des labs
+    "CREATE TABLE docker_events (
+        id        INTEGER PRIMARY KEY AUTOINCREMENT,
+        time_us   INTEGER NOT NULL,
+        container TEXT NOT NULL,
+        lab       TEXT NOT NULL,
+        action    TEXT NOT NULL,
+        detail    TEXT,
+        UNIQUE (time_us, container, action)
+    );
+    CREATE INDEX idx_docker_events_time ON docker_events(time_us);",
+    // 7 - Journal d'audit, UPDATE et DELETE refusés
+    "CREATE TABLE audit_log (
+        id        INTEGER PRIMARY KEY AUTOINCREMENT,
+        time_us   INTEGER NOT NULL,
+        actor     TEXT NOT NULL,
+        source_ip TEXT NOT NULL,
+        action    TEXT NOT NULL,
+        lab       TEXT NOT NULL,
+        params    TEXT NOT NULL,
+        outcome   TEXT NOT NULL,
+        detail    TEXT
+    );
+    CREATE INDEX idx_audit_log_time ON audit_log(time_us);
+    CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+        BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+    CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+        BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;",
+    // 8 - Historique des règles actives
+    "CREATE TABLE rule_versions (
+        id       INTEGER PRIMARY KEY AUTOINCREMENT,
+        lab      TEXT NOT NULL,
+        time_us  INTEGER NOT NULL,
+        actor    TEXT NOT NULL,
+        reason   TEXT NOT NULL,
+        sha256   TEXT NOT NULL,
+        content  TEXT NOT NULL
+    );
+    CREATE INDEX idx_rule_versions_lab ON rule_versions(lab, id);",
+];
+
+/// Politique de rétention de l'historique.
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    pub retention_days: u32,
+    pub max_alerts: u64,
+}
+
+impl Retention {
+    /// Politique configurée (alert_retention_days, alert_max_rows).
+    pub fn from_config(config: &Config) -> Self {
+        Retention { retention_days: config.alert_retention_days, max_alerts: config.alert_max_rows }
+    }
+}
+
+/// Applique les migrations manquantes. Chacune, avec son user_version, est
+/// une transaction: une migration interrompue ne laisse pas de schéma à
+/// moitié créé, elle est rejouée entière au démarrage suivant.
+fn migrate(conn: &mut Connection, migrations: &[&str]) -> rusqlite::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |r| r.get(0))?;
+    for (i, sql) in migrations.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Accès à la base SQLite du commander.
+pub struct AlertStore {
+    conn: Mutex<Connection>,
+}
+
+impl AlertStore {
+    /// Ouvre (ou crée) la base et applique les migrations manquantes.
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(mut conn: Connection) -> rusqlite::Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&mut conn, MIGRATIONS)?;
+        Ok(AlertStore { conn: Mutex::new(conn) })
+    }
+
+    /// Insère des alertes et enregistre les positions de lecture,
+    /// dans une seule transaction. Retourne les alertes numérotées.
+    pub fn ingest(&self, alerts: Vec<Alert>, positions: &[TailPosition]) -> rusqlite::Result<Vec<AlertRecord>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let now = Utc::now().timestamp_micros();
+        let mut records = Vec::with_capacity(alerts.len());
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO alerts (ts_us, engine, gid, sid, rev, message, classification, priority,
+                                     protocol, src_ip, src_port, dst_ip, dst_port, ingested_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            )?;
+            for alert in alerts {
+                insert.execute(params![
+                    alert.timestamp.timestamp_micros(),
+                    alert.engine.as_str(),
+                    alert.gid,
+                    alert.sid,
+                    alert.rev,
+                    alert.message,
+                    alert.classification,
+                    alert.priority,
+                    alert.protocol,
+                    alert.src_ip.map(|ip| ip.to_string()),
+                    alert.src_port,
+                    alert.dst_ip.map(|ip| ip.to_string()),
+                    alert.dst_port,
+                    now,
+                ])?;
+                records.push(AlertRecord { id: tx.last_insert_rowid() as u64, alert });
+            }
+
+            let mut save = tx.prepare_cached(
+                "INSERT INTO tail_positions (path, inode, offset) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(path) DO UPDATE SET inode = excluded.inode, offset = excluded.offset",
+            )?;
+            for pos in positions {
+                save.execute(params![pos.path.to_string_lossy(), pos.inode as i64, pos.offset as i64])?;
+            }
+        }
+        tx.commit()?;
+        Ok(records)
+    }
+
+    /// Positions de lecture sauvegardées.
+    pub fn positions(&self) -> rusqlite::Result<Vec<TailPosition>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT path, inode, offset FROM tail_positions")?;
+        let rows = stmt.query_map([], |r| {
+            Ok(TailPosition {
+                path: PathBuf::from(r.get::<_, String>(0)?),
+                inode: r.get::<_, i64>(1)? as u64,
+                offset: r.get::<_, i64>(2)? as u64,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Exécute une requête de l'API (filtres + pagination par curseur).
+    pub fn query(&self, query: &AlertQuery) -> rusqlite::Result<AlertPage> {
+        let mut clauses: Vec<&str> = Vec::new();
+        let mut args: Vec<Value> = Vec::new();
+
+        if let Some(engine) = query.engine {
+            clauses.push("engine = ?");
+            args.push(engine.as_str().to_string().into());
+        }
+        if let Some(t) = query.since {
+            clauses.push("ts_us >= ?");
+            args.push(t.timestamp_micros().into());
+        }
+        if let Some(t) = query.until {
+            clauses.push("ts_us <= ?");
+            args.push(t.timestamp_micros().into());
+        }
+        if let Some(sid) = query.sid {
+            clauses.push("sid = ?");
+            args.push(sid.into());
+        }
+        if let Some(ip) = query.src_ip {
+            clauses.push("src_ip = ?");
+            args.push(ip.to_string().into());
+        }
+        if let Some(ip) = query.dst_ip {
+            clauses.push("dst_ip = ?");
+            args.push(ip.to_string().into());
+        }
+        if let Some(port) = query.dst_port {
+            clauses.push("dst_port = ?");
+            args.push(port.into());
+        }
+        if let Some(p) = query.priority {
+            clauses.push("priority = ?");
+            args.push(p.into());
+        }
+        if let Some(q) = &query.q {
+            clauses.push("instr(lower(message), lower(?)) > 0");
+            args.push(q.clone().into());
+        }
+        if let Some(cursor) = query.cursor {
+            clauses.push(match query.order {
+                SortOrder::Asc => "id > ?",
+                SortOrder::Desc => "id < ?",
+            });
+            args.push((cursor as i64).into());
+        }
+
+        let limit = query.limit();
+        let sql = format!(
+            "SELECT id, ts_us, engine, gid, sid, rev, message, classification, priority,
+                    protocol, src_ip, src_port, dst_ip, dst_port
+             FROM alerts {} ORDER BY id {} LIMIT {}",
+            if clauses.is_empty() { String::new() } else { format!("WHERE {}", clauses.join(" AND ")) },
+            match query.order {
+                SortOrder::Asc => "ASC",
+                SortOrder::Desc => "DESC",
+            },
+            limit + 1
+        );
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let mut alerts: Vec<AlertRecord> = stmt
+            .query_map(params_from_iter(args), row_to_record)?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let has_more = alerts.len() > limit;
+        alerts.truncate(limit);
+        let next_cursor = if has_more { alerts.last().map(|r| r.id) } else { None };
+
+        Ok(AlertPage {
+            engine: query.engine,
+            order: query.order,
+            count: alerts.len(),
+            next_cursor,
+            alerts,
+        })
+    }
+
+    /// Applique la politique de rétention. Retourne le nombre d'alertes supprimées.
+    pub fn purge(&self, retention: Retention) -> rusqlite::Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let cutoff = (Utc::now() - chrono::Duration::days(retention.retention_days as i64)).timestamp_micros();
+        conn.execute("DELETE FROM docker_events WHERE time_us < ?1", [cutoff])?;
+        let mut removed = conn.execute("DELETE FROM alerts WHERE ts_us < ?1", [cutoff])?;
+
+        let max_id: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM alerts ORDER BY id DESC LIMIT 1 OFFSET ?1",
+                [retention.max_alerts as i64],
+                |r| r.get(0),
+            )
+            .optional()?;
+        if let Some(id) = max_id {
+            removed += conn.execute("DELETE FROM alerts WHERE id <= ?1", [id])?;
+        }
+        Ok(removed)
+    }
+
+    /// Enregistre un événement Docker. Retourne false s'il était déjà connu
+    /// (rejoué par le flux après une reconnexion).
+    pub fn record_event(&self, event: &DockerEvent) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO docker_events (time_us, container, lab, action, detail) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![event.time.timestamp_micros(), event.container, event.lab, event.action, event.detail],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Date du dernier événement Docker enregistré (reprise du flux au démarrage).
+    pub fn last_event_time(&self) -> rusqlite::Result<Option<DateTime<Utc>>> {
+        let conn = self.conn.lock().unwrap();
+        let us: Option<i64> = conn.query_row("SELECT MAX(time_us) FROM docker_events", [], |r| r.get(0))?;
+        Ok(us.and_then(DateTime::from_timestamp_micros))
+    }
+
+    /// Événements Docker filtrés, du plus récent au plus ancien.
+    pub fn events(&self, query: &EventQuery) -> rusqlite::Result<Vec<EventRecord>> {
+        let mut clauses: Vec<&str> = Vec::new();
+        let mut args: Vec<Value> = Vec::new();
+
+        if let Some(t) = query.since {
+            clauses.push("time_us >= ?");
+            args.push(t.timestamp_micros().into());
+        }
+        if let Some(t) = query.until {
+            clauses.push("time_us <= ?");
+            args.push(t.timestamp_micros().into());
+        }
+        if let Some(container) = &query.container {
+            clauses.push("container = ?");
+            args.push(container.clone().into());
+        }
+        if let Some(lab) = &query.lab {
+            clauses.push("lab = ?");
+            args.push(lab.clone().into());
+        }
+        if let Some(action) = &query.action {
+            clauses.push("action = ?");
+            args.push(action.clone().into());
+        }
+
+        let sql = format!(
+            "SELECT id, time_us, container, lab, action, detail FROM docker_events {} ORDER BY time_us DESC, id DESC LIMIT {}",
+            if clauses.is_empty() { String::new() } else { format!("WHERE {}", clauses.join(" AND ")) },
+            query.limit()
+        );
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), |r| {
+            Ok(EventRecord {
+                id: r.get::<_, i64>(0)? as u64,
+                event: DockerEvent {
+                    time: DateTime::from_timestamp_micros(r.get(1)?).unwrap_or_default(),
+                    container: r.get(2)?,
+                    lab: r.get(3)?,
+                    action: r.get(4)?,
+                    detail: r.get(5)?,
+                },
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Ajoute une entrée au journal d'audit.
+    pub fn record_audit(&self, entry: &AuditEntry) -> rusqlite::Result<u64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO audit_log (time_us, actor, source_ip, action, lab, params, outcome, detail)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                entry.time.timestamp_micros(),
+                entry.actor,
+                entry.source_ip,
+                entry.action,
+                entry.lab,
+                entry.params.to_string(),
+                entry.outcome.as_str(),
+                entry.detail
+            ],
+        )?;
+        Ok(conn.last_insert_rowid() as u64)
+    }
+
+    /// Entrées du journal d'audit filtrées, de la plus récente à la plus
+    /// ancienne (query.limit()).
+    pub fn audit(&self, query: &AuditQuery) -> rusqlite::Result<Vec<AuditRecord>> {
+        self.load_audit(query, &format!("ORDER BY time_us DESC, id DESC LIMIT {}", query.limit()))
+    }
+
+    /// Toutes les entrées filtrées, de la plus ancienne à la plus récente (export).
+    pub fn audit_export(&self, query: &AuditQuery) -> rusqlite::Result<Vec<AuditRecord>> {
+        self.load_audit(query, "ORDER BY time_us, id")
+    }
+
+    fn load_audit(&self, query: &AuditQuery, order: &str) -> rusqlite::Result<Vec<AuditRecord>> {
+        let mut clauses: Vec<&str> = Vec::new();
+        let mut args: Vec<Value> = Vec::new();
+
+        if let Some(t) = query.since {
+            clauses.push("time_us >= ?");
+            args.push(t.timestamp_micros().into());
+        }
+        if let Some(t) = query.until {
+            clauses.push("time_us <= ?");
+            args.push(t.timestamp_micros().into());
+        }
+        for (column, value) in [
+            ("actor = ?", &query.actor),
+            ("lab = ?", &query.lab),
+            ("action = ?", &query.action),
+        ] {
+            if let Some(value) = value {
+                clauses.push(column);
+                args.push(value.clone().into());
+            }
+        }
+        if let Some(outcome) = query.outcome {
+            clauses.push("outcome = ?");
+            args.push(outcome.as_str().to_string().into());
+        }
+
+        let sql = format!(
+            "SELECT id, time_us, actor, source_ip, action, lab, params, outcome, detail FROM audit_log {} {}",
+            if clauses.is_empty() { String::new() } else { format!("WHERE {}", clauses.join(" AND ")) },
+            order
+        );
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), |r| {
+            let params: String = r.get(6)?;
+            let outcome: String = r.get(7)?;
+            Ok(AuditRecord {
+                id: r.get::<_, i64>(0)? as u64,
+                entry: AuditEntry {
+                    time: DateTime::from_timestamp_micros(r.get(1)?).unwrap_or_default(),
+                    actor: r.get(2)?,
+                    source_ip: r.get(3)?,
+                    action: r.get(4)?,
+                    lab: r.get(5)?,
+                    params: serde_json::from_str(&params).unwrap_or_default(),
+                    outcome: outcome.parse().unwrap_or(Outcome::Failed),
+                    detail: r.get(8)?,
+                },
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Conserve le contenu des règles actives d'un lab avant leur
+    /// remplacement. Retourne None si la dernière version du lab est
+    /// déjà identique.
+    pub fn save_rule_version(
+        &self,
+        lab: &str,
+        actor: &str,
+        reason: &str,
+        content: &str,
+    ) -> rusqlite::Result<Option<RuleVersion>> {
+        let conn = self.conn.lock().unwrap();
+        let sha256 = templates::content_hash(content.as_bytes());
+        let latest: Option<String> = conn
+            .query_row(
+                "SELECT sha256 FROM rule_versions WHERE lab = ?1 ORDER BY id DESC LIMIT 1",
+                [lab],
+                |r| r.get(0),
+            )
+            .optional()?;
+        if latest.as_deref() == Some(sha256.as_str()) {
+            return Ok(None);
+        }
+        let time = DateTime::from_timestamp_micros(Utc::now().timestamp_micros()).unwrap_or_default();
+        conn.execute(
+            "INSERT INTO rule_versions (lab, time_us, actor, reason, sha256, content) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![lab, time.timestamp_micros(), actor, reason, sha256, content],
+        )?;
+        Ok(Some(RuleVersion {
+            id: conn.last_insert_rowid() as u64,
+            lab: lab.to_string(),
+            time,
+            actor: actor.to_string(),
+            reason: reason.to_string(),
+            sha256,
+            size: content.len() as u64,
+            content: None,
+        }))
+    }
+
+    /// Versions des règles d'un lab, de la plus récente à la plus ancienne (sans contenu).
+    pub fn rule_versions(&self, lab: &str) -> rusqlite::Result<Vec<RuleVersion>> {
+        self.load_rule_versions(lab, None, false)
+    }
+
+    /// Une version des règles d'un lab, avec son contenu.
+    pub fn rule_version(&self, lab: &str, id: u64) -> rusqlite::Result<Option<RuleVersion>> {
+        Ok(self.load_rule_versions(lab, Some(id), true)?.pop())
+    }
+
+    fn load_rule_versions(&self, lab: &str, id: Option<u64>, content: bool) -> rusqlite::Result<Vec<RuleVersion>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, lab, time_us, actor, reason, sha256, length(CAST(content AS BLOB)),
+                    CASE WHEN ?3 THEN content END
+             FROM rule_versions WHERE lab = ?1 AND (?2 IS NULL OR id = ?2) ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map(params![lab, id.map(|i| i as i64), content], |r| {
+            Ok(RuleVersion {
+                id: r.get::<_, i64>(0)? as u64,
+                lab: r.get(1)?,
+                time: DateTime::from_timestamp_micros(r.get(2)?).unwrap_or_default(),
+                actor: r.get(3)?,
+                reason: r.get(4)?,
+                sha256: r.get(5)?,
+                size: r.get::<_, i64>(6)? as u64,
+                content: r.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Enregistre le début d'un run.
+    pub fn start_run(&self, engine: Engine, rule_level: &str, target: IpAddr, command: &str) -> rusqlite::Result<Run> {
+        let conn = self.conn.lock().unwrap();
+        // Précision de la base (microsecondes), pour une réponse identique à GET /api/runs/:id
+        let started_at = DateTime::from_timestamp_micros(Utc::now().timestamp_micros()).unwrap_or_default();
+        conn.execute(
+            "INSERT INTO runs (engine, rule_level, target, command, started_us) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![engine.as_str(), rule_level, target.to_string(), command, started_at.timestamp_micros()],
+        )?;
+        Ok(Run {
+            id: conn.last_insert_rowid() as u64,
+            engine,
+            rule_level: rule_level.to_string(),
+            target,
+            command: command.to_string(),
+            started_at,
+            ended_at: None,
+            exit_code: None,
+        })
+    }
+
+    /// Clôt un run en cours, avec le code de retour de l'attaque si elle a été
+    /// lancée par le commander. Retourne false si le run était déjà terminé ou inconnu.
+    pub fn stop_run(&self, id: u64, exit_code: Option<i32>) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE runs SET ended_us = ?1, exit_code = ?2 WHERE id = ?3 AND ended_us IS NULL",
+            params![Utc::now().timestamp_micros(), exit_code, id as i64],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn run(&self, id: u64) -> rusqlite::Result<Option<Run>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(&format!("{} WHERE id = ?1", SELECT_RUN), [id as i64], row_to_run)
+            .optional()
+    }
+
+    /// Tous les runs, du plus récent au plus ancien.
+    pub fn runs(&self) -> rusqlite::Result<Vec<Run>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("{} ORDER BY id DESC", SELECT_RUN))?;
+        let rows = stmt.query_map([], row_to_run)?;
+        rows.collect()
+    }
+
+    /// Nombre d'alertes par signature dans la fenêtre d'un run.
+    pub fn sid_counts(&self, run: &Run) -> rusqlite::Result<Vec<SidCount>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT sid, message, COUNT(*) AS n FROM alerts
+             WHERE engine = ?1 AND ts_us >= ?2 AND ts_us <= ?3
+             GROUP BY sid, message ORDER BY n DESC, sid",
+        )?;
+        let rows = stmt.query_map(
+            params![run.engine.as_str(), run.started_at.timestamp_micros(), run.window_end().timestamp_micros()],
+            |r| Ok(SidCount { sid: r.get(0)?, message: r.get(1)?, count: r.get::<_, i64>(2)? as u64 }),
+        )?;
+        rows.collect()
+    }
+
+    /// Enregistre le début d'un job de matrice.
+    pub fn create_matrix(&self, lab: &str, engine: Engine) -> rusqlite::Result<Matrix> {
+        let conn = self.conn.lock().unwrap();
+        let started_at = DateTime::from_timestamp_micros(Utc::now().timestamp_micros()).unwrap_or_default();
+        conn.execute(
+            "INSERT INTO matrices (lab, engine, status, started_us) VALUES (?1, ?2, ?3, ?4)",
+            params![lab, engine.as_str(), MatrixStatus::Running.as_str(), started_at.timestamp_micros()],
+        )?;
+        Ok(Matrix {
+            id: conn.last_insert_rowid() as u64,
+            lab: lab.to_string(),
+            engine,
+            status: MatrixStatus::Running,
+            error: None,
+            started_at,
+            ended_at: None,
+            cells: Vec::new(),
+        })
+    }
+
+    pub fn add_matrix_cell(&self, matrix_id: u64, cell: &MatrixCell) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO matrix_cells (matrix_id, level, technique, run_id, alerts)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![matrix_id as i64, cell.level, cell.technique, cell.run_id as i64, cell.alerts as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Termine un job (completed, ou failed avec le message d'erreur).
+    pub fn finish_matrix(&self, id: u64, status: MatrixStatus, error: Option<&str>) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE matrices SET status = ?1, error = ?2, ended_us = ?3 WHERE id = ?4",
+            params![status.as_str(), error, Utc::now().timestamp_micros(), id as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Marque comme échoués les jobs interrompus par un arrêt du commander.
+    pub fn fail_interrupted_matrices(&self) -> rusqlite::Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE matrices SET status = 'failed', error = 'Interrupted by a commander restart', ended_us = ?1
+             WHERE status = 'running'",
+            [Utc::now().timestamp_micros()],
+        )
+    }
+
+    pub fn matrix(&self, id: u64) -> rusqlite::Result<Option<Matrix>> {
+        Ok(self.load_matrices(Some(id))?.pop())
+    }
+
+    /// Tous les jobs, du plus récent au plus ancien.
+    pub fn matrices(&self) -> rusqlite::Result<Vec<Matrix>> {
+        self.load_matrices(None)
+    }
+
+    fn load_matrices(&self, id: Option<u64>) -> rusqlite::Result<Vec<Matrix>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, engine, status, error, started_us, ended_us, lab FROM matrices
+             WHERE ?1 IS NULL OR id = ?1 ORDER BY id DESC",
+        )?;
+        let mut matrices: Vec<Matrix> = stmt
+            .query_map([id.map(|i| i as i64)], |r| {
+                Ok(Matrix {
+                    id: r.get::<_, i64>(0)? as u64,
+                    lab: r.get(6)?,
+                    engine: r.get::<_, String>(1)?.parse().unwrap_or(Engine::Snort),
+                    status: MatrixStatus::parse(&r.get::<_, String>(2)?),
+                    error: r.get(3)?,
+                    started_at: DateTime::from_timestamp_micros(r.get(4)?).unwrap_or_default(),
+                    ended_at: r.get::<_, Option<i64>>(5)?.and_then(DateTime::from_timestamp_micros),
+                    cells: Vec::new(),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut cells = conn.prepare_cached(
+            "SELECT level, technique, run_id, alerts FROM matrix_cells WHERE matrix_id = ?1 ORDER BY level",
+        )?;
+        for matrix in &mut matrices {
+            matrix.cells = cells
+                .query_map([matrix.id as i64], |r| {
+                    let alerts = r.get::<_, i64>(3)? as u64;
+                    Ok(MatrixCell {
+                        level: r.get(0)?,
+                        technique: r.get(1)?,
+                        run_id: r.get::<_, i64>(2)? as u64,
+                        alerts,
+                        detected: alerts > 0,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+        }
+        Ok(matrices)
+    }
+}
+
+const SELECT_RUN: &str = "SELECT id, engine, rule_level, target, command, started_us, ended_us, exit_code FROM runs";
+
+fn row_to_run(r: &Row) -> rusqlite::Result<Run> {
+    let engine: String = r.get(1)?;
+    let target: String = r.get(3)?;
+    Ok(Run {
+        id: r.get::<_, i64>(0)? as u64,
+        engine: engine.parse::<Engine>().unwrap_or(Engine::Snort),
+        rule_level: r.get(2)?,
+        target: target.parse().unwrap_or(IpAddr::from([0, 0, 0, 0])),
+        command: r.get(4)?,
+        started_at: DateTime::from_timestamp_micros(r.get(5)?).unwrap_or_default(),
+        ended_at: r.get::<_, Option<i64>>(6)?.and_then(DateTime::from_timestamp_micros),
+        exit_code: r.get(7)?,
+    })
+}
+
+fn row_to_record(r: &Row) -> rusqlite::Result<AlertRecord> {
+    let engine: String = r.get(2)?;
+    let ip = |i: usize| -> rusqlite::Result<_> { Ok(r.get::<_, Option<String>>(i)?.and_then(|s| s.parse().ok())) };
+    Ok(AlertRecord {
+        id: r.get::<_, i64>(0)? as u64,
+        alert: Alert {
+            timestamp: DateTime::from_timestamp_micros(r.get(1)?).unwrap_or_default(),
+            engine: engine.parse::<Engine>().unwrap_or(Engine::Snort),
+            gid: r.get(3)?,
+            sid: r.get(4)?,
+            rev: r.get(5)?,
+            message: r.get(6)?,
+            classification: r.get(7)?,
+            priority: r.get(8)?,
+            protocol: r.get(9)?,
+            src_ip: ip(10)?,
+            src_port: r.get(11)?,
+            dst_ip: ip(12)?,
+            dst_port: r.get(13)?,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::{AlertParser, LogFormat};
+
+    fn store_with_fixture() -> AlertStore {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../sample-logs/suricata_fast.log");
+        let alerts = AlertParser::new(LogFormat::SuricataFast).parse_str(&std::fs::read_to_string(path).unwrap());
+        let store = AlertStore::open_in_memory().unwrap();
+        store.ingest(alerts, &[]).unwrap();
+        store
+    }
+
+    #[test]
+    fn failed_migration_is_rolled_back() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let broken = ["CREATE TABLE a (id INTEGER);", "CREATE TABLE b (id INTEGER); CREATE TABLE a (id INTEGER);"];
+        assert!(migrate(&mut conn, &broken).is_err());
+        let version: usize = conn.pragma_query_value(None, "user_version", |r| r.get(0)).unwrap();
+        let tables: usize = conn
+            .query_row("SELECT count(*) FROM sqlite_master WHERE name = 'b'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!((version, tables), (1, 0));
+
+        let fixed = ["CREATE TABLE a (id INTEGER);", "CREATE TABLE b (id INTEGER);"];
+        migrate(&mut conn, &fixed).unwrap();
+        let version: usize = conn.pragma_query_value(None, "user_version", |r| r.get(0)).unwrap();
+        assert_eq!(version, 2);
+    }
+
+    #[test]
+    fn filters_by_sid_and_time_window() {
+        let store = store_with_fixture();
+        let query = AlertQuery {
+            sid: Some(2000007),
+            since: Some("2026-02-28T10:21:00Z".parse().unwrap()),
+            until: Some("2026-02-28T10:23:00Z".parse().unwrap()),
+            limit: Some(1000),
+            ..Default::default()
+        };
+        let page = store.query(&query).unwrap();
+        assert!(page.count > 0);
+        assert!(page.alerts.iter().all(|r| r.alert.sid == Some(2000007)));
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn cursor_walks_all_pages_without_overlap() {
+        let store = store_with_fixture();
+        let mut query = AlertQuery { limit: Some(7), order: SortOrder::Asc, ..Default::default() };
+        let mut seen = Vec::new();
+        loop {
+            let page = store.query(&query).unwrap();
+            seen.extend(page.alerts.iter().map(|r| r.id));
+            match page.next_cursor {
+                Some(c) => query.cursor = Some(c),
+                None => break,
+            }
+        }
+        assert_eq!(seen, (1..=50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn text_search_is_case_insensitive() {
+        let store = store_with_fixture();
+        let query = AlertQuery { q: Some("port scan".into()), ..Default::default() };
+        assert_eq!(store.query(&query).unwrap().count, 1);
+    }
+
+    #[test]
+    fn purge_caps_row_count() {
+        let store = store_with_fixture();
+        // Les alertes du fixture datent de 2026-02-28: rétention large pour ne tester que le plafond
+        let removed = store.purge(Retention { retention_days: 36500, max_alerts: 10 }).unwrap();
+        assert_eq!(removed, 40);
+        let page = store.query(&AlertQuery { order: SortOrder::Asc, ..Default::default() }).unwrap();
+        assert_eq!(page.alerts.first().map(|r| r.id), Some(41));
+    }
+
+    #[test]
+    fn run_counts_only_alerts_in_its_window() {
+        let store = store_with_fixture();
+        let run = store.start_run(Engine::Suricata, "3 - Moderate", "172.29.0.100".parse().unwrap(), "nmap -sS").unwrap();
+        assert!(store.stop_run(run.id, Some(0)).unwrap());
+        assert!(!store.stop_run(run.id, None).unwrap());
+
+        // Ramener la fenêtre du run sur les horodatages du fixture
+        let start: DateTime<Utc> = "2026-02-28T10:21:00Z".parse().unwrap();
+        let end: DateTime<Utc> = "2026-02-28T10:23:00Z".parse().unwrap();
+        store.conn.lock().unwrap()
+            .execute("UPDATE runs SET started_us = ?1, ended_us = ?2", [start.timestamp_micros(), end.timestamp_micros()])
+            .unwrap();
+
+        let run = store.run(run.id).unwrap().unwrap();
+        assert_eq!(run.exit_code, Some(0));
+        let counts = store.sid_counts(&run).unwrap();
+        let window = AlertQuery { since: Some(start), until: Some(end), limit: Some(1000), ..Default::default() };
+        let total: u64 = counts.iter().map(|c| c.count).sum();
+        assert_eq!(total as usize, store.query(&window).unwrap().count);
+        assert!(counts.windows(2).all(|w| w[0].count >= w[1].count));
+    }
+
+    #[test]
+    fn matrix_cells_round_trip() {
+        let store = AlertStore::open_in_memory().unwrap();
+        let matrix = store.create_matrix("snort", Engine::Snort).unwrap();
+        for (level, alerts) in [(1, 0), (3, 4)] {
+            let cell = MatrixCell { level, technique: "mtu8".into(), run_id: level as u64, alerts, detected: alerts > 0 };
+            store.add_matrix_cell(matrix.id, &cell).unwrap();
+        }
+        store.finish_matrix(matrix.id, MatrixStatus::Completed, None).unwrap();
+
+        let loaded = store.matrix(matrix.id).unwrap().unwrap();
+        assert_eq!(loaded.status, MatrixStatus::Completed);
+        assert!(loaded.ended_at.is_some());
+        let detected: Vec<_> = loaded.cells.iter().map(|c| (c.level, c.detected)).collect();
+        assert_eq!(detected, [(1, false), (3, true)]);
+        assert_eq!(store.matrices().unwrap().len(), 1);
+        assert!(store.matrix(99).unwrap().is_none());
+    }
+
+    #[test]
+    fn docker_events_are_deduplicated_and_filtered() {
+        let store = AlertStore::open_in_memory().unwrap();
+        let event = |secs: i64, container: &str, action: &str| DockerEvent {
+            time: DateTime::from_timestamp(1_772_273_000 + secs, 0).unwrap(),
+            container: container.into(),
+            lab: container.split('_').next().unwrap().into(),
+            action: action.into(),
+            detail: None,
+        };
+        assert!(store.record_event(&event(0, "snort_ids", "start")).unwrap());
+        assert!(store.record_event(&event(60, "snort_ids", "kill")).unwrap());
+        assert!(store.record_event(&event(90, "suricata_ids", "die")).unwrap());
+        // Rejoué après une reconnexion au flux
+        assert!(!store.record_event(&event(60, "snort_ids", "kill")).unwrap());
+
+        let all = store.events(&EventQuery::default()).unwrap();
+        let actions: Vec<_> = all.iter().map(|r| r.event.action.as_str()).collect();
+        assert_eq!(actions, ["die", "kill", "start"]);
+        assert_eq!(store.last_event_time().unwrap(), Some(all[0].event.time));
+
+        let query = EventQuery { lab: Some("snort".into()), since: Some(all[1].event.time), ..Default::default() };
+        let snort = store.events(&query).unwrap();
+        assert_eq!(snort.len(), 1);
+        assert_eq!(snort[0].event.action, "kill");
+    }
+
+    #[test]
+    fn audit_log_is_filtered_and_append_only() {
+        let store = AlertStore::open_in_memory().unwrap();
+        let entry = |secs: i64, actor: &str, lab: &str, outcome: Outcome| AuditEntry {
+            time: DateTime::from_timestamp(1_772_273_000 + secs, 0).unwrap(),
+            actor: actor.into(),
+            source_ip: "127.0.0.1".into(),
+            action: "rules.apply".into(),
+            lab: lab.into(),
+            params: serde_json::json!({ "level": "1" }),
+            outcome,
+            detail: None,
+        };
+        store.record_audit(&entry(0, "prof", "suricata", Outcome::Success)).unwrap();
+        store.record_audit(&entry(60, "alice", "snort", Outcome::Denied)).unwrap();
+        store.record_audit(&entry(90, "alice", "suricata", Outcome::Success)).unwrap();
+
+        let latest = store.audit(&AuditQuery::default()).unwrap();
+        assert_eq!(latest.iter().map(|r| r.id).collect::<Vec<_>>(), [3, 2, 1]);
+        assert_eq!(latest[1].entry, entry(60, "alice", "snort", Outcome::Denied));
+        let query = AuditQuery { actor: Some("alice".into()), outcome: Some(Outcome::Success), ..Default::default() };
+        assert_eq!(store.audit(&query).unwrap().len(), 1);
+        let export = store.audit_export(&AuditQuery { lab: Some("suricata".into()), ..Default::default() }).unwrap();
+        assert_eq!(export.iter().map(|r| r.id).collect::<Vec<_>>(), [1, 3]);
+
+        let conn = store.conn.lock().unwrap();
+        assert!(conn.execute("DELETE FROM audit_log", []).is_err());
+        assert!(conn.execute("UPDATE audit_log SET actor = 'nobody'", []).is_err());
+    }
+
+    #[test]
+    fn rule_versions_skip_unchanged_content() {
+        let store = AlertStore::open_in_memory().unwrap();
+        let first = store.save_rule_version("suricata", "prof", "apply level 3", "# hand-tuned\n").unwrap().unwrap();
+        assert!(store.save_rule_version("suricata", "alice", "apply level 3", "# hand-tuned\n").unwrap().is_none());
+        store.save_rule_version("snort", "prof", "apply level 1", "# snort\n").unwrap().unwrap();
+        let second = store.save_rule_version("suricata", "alice", "apply level 4", "# Level 3\n").unwrap().unwrap();
+
+        let versions = store.rule_versions("suricata").unwrap();
+        assert_eq!(versions.iter().map(|v| v.id).collect::<Vec<_>>(), [second.id, first.id]);
+        assert_eq!((versions[1].size, versions[1].content.as_deref()), (13, None));
+        assert_eq!(versions[0], second);
+
+        let loaded = store.rule_version("suricata", first.id).unwrap().unwrap();
+        assert_eq!(loaded.content.as_deref(), Some("# hand-tuned\n"));
+        assert_eq!(loaded.actor, "prof");
+        // Une version n'est lisible que depuis son lab
+        assert!(store.rule_version("snort", first.id).unwrap().is_none());
+    }
+}
@@ -0,0 +1,286 @@
+// =============================================================================
+// IDS LAB COMMANDER - Lecture continue des fichiers de logs IDS
+// =============================================================================
+//
+// DESCRIPTION:
+// Lit les fichiers d'alertes écrits par les IDS dans les volumes montés
+// (snort-lab/logs, suricata-lab/logs, zeek-lab/logs) à la manière d'un
+// `tail -f`: seuls les octets ajoutés depuis la lecture précédente sont lus.
+//
+// POURQUOI LES FICHIERS PLUTÔT QUE `docker logs`?
+// - Suricata écrit ses alertes dans eve.json, pas sur stdout
+// - Snort 3 avec alert_fast { file = true } écrit dans alert_fast.txt
+// - Les fichiers survivent à un redémarrage du container
+// - Pas de limite aux 50 dernières lignes de stdout
+//
+// CHOIX STRATÉGIQUES:
+// - Offset en octets mémorisé par fichier entre deux lectures, un état par
+//   lab (deux labs d'un même moteur ont chacun leurs fichiers)
+// - Seules les lignes complètes (terminées par \n) sont consommées:
+//   une ligne en cours d'écriture sera lue au prochain passage
+// - Fichier tronqué ou supprimé (reset_logs): on repart de zéro
+// - Les positions (inode + offset) sont exportées pour être persistées avec
+//   les alertes (voir store.rs): un redémarrage du commander reprend là où
+//   il s'était arrêté, sans doublons
+//
+// =============================================================================
+
+use crate::alerts::{Alert, AlertParser, LogFormat};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Fichier d'alertes d'un lab (alert_logs dans labs.toml).
+/// Plusieurs candidats peuvent être déclarés: le premier existant est lu
+/// (ex: Snort écrit alert_fast.txt ou alert_fast.log selon la version).
+pub struct LogSource {
+    /// Nom du lab (clé de l'état de lecture)
+    pub lab: String,
+    pub candidates: Vec<(PathBuf, LogFormat)>,
+}
+
+impl LogSource {
+    fn resolve(&self) -> Option<&(PathBuf, LogFormat)> {
+        self.candidates.iter().find(|(p, _)| p.exists())
+    }
+}
+
+/// Position de lecture persistée d'un fichier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TailPosition {
+    pub path: PathBuf,
+    pub inode: u64,
+    pub offset: u64,
+}
+
+/// Position de lecture dans un fichier.
+struct TailState {
+    path: PathBuf,
+    inode: u64,
+    offset: u64,
+    parser: AlertParser,
+    /// Reprise au milieu d'un fichier Zeek: l'en-tête #fields doit être relu
+    needs_header: bool,
+}
+
+impl TailState {
+    fn new(path: &Path, format: LogFormat) -> Self {
+        TailState {
+            path: path.to_path_buf(),
+            inode: 0,
+            offset: 0,
+            parser: AlertParser::new(format),
+            needs_header: false,
+        }
+    }
+}
+
+/// Lecteur incrémental de l'ensemble des fichiers d'alertes.
+pub struct AlertTailer {
+    sources: Vec<LogSource>,
+    /// État de lecture par lab
+    states: HashMap<String, TailState>,
+}
+
+impl AlertTailer {
+    pub fn new(sources: Vec<LogSource>) -> Self {
+        AlertTailer {
+            sources,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Reprend la lecture aux positions sauvegardées lors d'une exécution précédente.
+    /// Les positions dont le fichier n'est plus déclaré sont ignorées.
+    pub fn restore(&mut self, positions: Vec<TailPosition>) {
+        for pos in positions {
+            let found = self.sources.iter().find_map(|s| {
+                s.candidates
+                    .iter()
+                    .find(|(p, _)| *p == pos.path)
+                    .map(|(_, format)| (s.lab.clone(), *format))
+            });
+            if let Some((lab, format)) = found {
+                let mut state = TailState::new(&pos.path, format);
+                state.inode = pos.inode;
+                state.offset = pos.offset;
+                state.needs_header = format == LogFormat::ZeekNotice && pos.offset > 0;
+                self.states.insert(lab, state);
+            }
+        }
+    }
+
+    /// Positions courantes de tous les fichiers suivis.
+    pub fn positions(&self) -> Vec<TailPosition> {
+        self.states
+            .values()
+            .map(|s| TailPosition { path: s.path.clone(), inode: s.inode, offset: s.offset })
+            .collect()
+    }
+
+    /// Lit les nouvelles lignes de chaque fichier et retourne les alertes
+    /// apparues depuis le dernier appel.
+    pub fn poll(&mut self) -> Vec<Alert> {
+        let mut fresh = Vec::new();
+
+        for source in &self.sources {
+            let Some((path, format)) = source.resolve() else {
+                // Fichier absent (lab jamais démarré, logs réinitialisés)
+                self.states.remove(&source.lab);
+                continue;
+            };
+
+            let state = self
+                .states
+                .entry(source.lab.clone())
+                .or_insert_with(|| TailState::new(path, *format));
+            if &state.path != path {
+                *state = TailState::new(path, *format);
+            }
+
+            match read_new_lines(state, *format) {
+                Ok(alerts) => fresh.extend(alerts),
+                Err(e) => eprintln!("Error reading {:?}: {}", path, e),
+            }
+        }
+
+        fresh
+    }
+}
+
+/// Lit les lignes complètes ajoutées depuis `state.offset`.
+fn read_new_lines(state: &mut TailState, format: LogFormat) -> std::io::Result<Vec<Alert>> {
+    let mut file = File::open(&state.path)?;
+    let meta = file.metadata()?;
+    let len = meta.len();
+
+    // Fichier tronqué ou recréé: relire depuis le début (en-têtes Zeek inclus)
+    if len < state.offset || meta.ino() != state.inode {
+        state.inode = meta.ino();
+        state.offset = 0;
+        state.parser = AlertParser::new(format);
+        state.needs_header = false;
+    }
+    if state.needs_header {
+        // Rejouer les lignes d'en-tête (#...) pour connaître les colonnes
+        for line in BufReader::new(&file).lines() {
+            let line = line?;
+            if !line.starts_with('#') {
+                break;
+            }
+            state.parser.parse_line(&line);
+        }
+        state.needs_header = false;
+    }
+    if len == state.offset {
+        return Ok(vec![]);
+    }
+
+    file.seek(SeekFrom::Start(state.offset))?;
+    let mut buf = Vec::with_capacity((len - state.offset) as usize);
+    file.take(len - state.offset).read_to_end(&mut buf)?;
+
+    // Ne consommer que jusqu'au dernier saut de ligne
+    let Some(end) = buf.iter().rposition(|b| *b == b'\n') else {
+        return Ok(vec![]);
+    };
+    state.offset += (end + 1) as u64;
+
+    Ok(state.parser.parse_str(&String::from_utf8_lossy(&buf[..=end])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const LINE: &str = "02/28-10:33:23.528916 [**] [1:1000001:2] \"NMAP SYN Scan Detected\" [**] [Priority: 0] {TCP} 172.28.0.1:39384 -> 172.28.0.100:21\n";
+
+    fn tailer_for(path: &Path) -> AlertTailer {
+        AlertTailer::new(vec![LogSource {
+            lab: "snort".into(),
+            candidates: vec![(path.to_path_buf(), LogFormat::SnortFast)],
+        }])
+    }
+
+    #[test]
+    fn reads_only_appended_complete_lines() {
+        let dir = std::env::temp_dir().join(format!("ids-tail-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("alert_fast.txt");
+        std::fs::write(&path, LINE).unwrap();
+
+        let mut tailer = tailer_for(&path);
+        assert_eq!(tailer.poll().len(), 1);
+        assert!(tailer.poll().is_empty());
+
+        // Ligne partielle: ignorée jusqu'à ce qu'elle soit complète
+        let mut f = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(&LINE.as_bytes()[..40]).unwrap();
+        assert!(tailer.poll().is_empty());
+        f.write_all(&LINE.as_bytes()[40..]).unwrap();
+        assert_eq!(tailer.poll().len(), 1);
+
+        // Troncature (reset_logs): relecture depuis le début
+        std::fs::write(&path, LINE).unwrap();
+        assert_eq!(tailer.poll().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restored_zeek_position_rereads_header() {
+        let dir = std::env::temp_dir().join(format!("ids-tail-zeek-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notice.log");
+        let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../sample-logs/zeek_notice.log");
+        std::fs::copy(&fixture, &path).unwrap();
+
+        let source = || LogSource { lab: "zeek".into(), candidates: vec![(path.clone(), LogFormat::ZeekNotice)] };
+        let mut first = AlertTailer::new(vec![source()]);
+        let total = first.poll().len();
+
+        // Reprise à mi-fichier: seules les alertes restantes sont relues
+        let content = std::fs::read_to_string(&path).unwrap();
+        let half = content.match_indices('\n').nth(40).unwrap().0 as u64 + 1;
+        let mut pos = first.positions().pop().unwrap();
+        pos.offset = half;
+        let mut second = AlertTailer::new(vec![source()]);
+        second.restore(vec![pos]);
+        let rest = second.poll().len();
+        assert!(rest > 0 && rest < total);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn labs_sharing_an_engine_are_tailed_separately() {
+        let dir = std::env::temp_dir().join(format!("ids-tail-two-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+        std::fs::write(&a, LINE).unwrap();
+        std::fs::write(&b, LINE.repeat(2)).unwrap();
+
+        let source = |lab: &str, path: &Path| LogSource {
+            lab: lab.into(),
+            candidates: vec![(path.to_path_buf(), LogFormat::SnortFast)],
+        };
+        let mut tailer = AlertTailer::new(vec![source("snort-a", &a), source("snort-b", &b)]);
+        assert_eq!(tailer.poll().len(), 3);
+        // Aucune relecture: chaque fichier garde sa propre position
+        assert!(tailer.poll().is_empty());
+
+        let mut positions = tailer.positions();
+        positions.sort_by(|x, y| x.path.cmp(&y.path));
+        assert_eq!(positions.iter().map(|p| p.offset).collect::<Vec<_>>(), [LINE.len() as u64, 2 * LINE.len() as u64]);
+
+        let mut restored = AlertTailer::new(vec![source("snort-a", &a), source("snort-b", &b)]);
+        restored.restore(positions);
+        std::fs::OpenOptions::new().append(true).open(&b).unwrap().write_all(LINE.as_bytes()).unwrap();
+        assert_eq!(restored.poll().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
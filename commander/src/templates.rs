@@ -0,0 +1,383 @@
+// =============================================================================
+// IDS LAB COMMANDER - Templates de règles (niveaux 1 à 5)
+// =============================================================================
+//
+// DESCRIPTION:
+// Copie d'un template {template_dir}/level{N}_* vers le fichier de règles
+// actif du lab (active_rules dans labs.toml). Le container *_reloader du
+// lab (inotify) détecte la modification et recharge l'IDS (champ reload):
+// - Snort:    SIGHUP
+// - Suricata: USR2
+// - Zeek:     redémarrage complet (pas de rechargement à chaud)
+//
+// NIVEAUX:
+// - 1 à 5: templates level{N}_* livrés avec le projet
+// - personnalisé: tout autre fichier du template_dir, désigné par son nom
+//   sans extension (ex: ftp_only.rules → /apply/snort/ftp_only)
+//
+// MÉTADONNÉES (manifest.toml du template_dir):
+// id, nom, niveau, description, auteur et techniques visées de chaque
+// template. Le fichier de règles actif est tamponné en première ligne:
+//   # ids-commander template=suricata-level3 sha256=<empreinte du template>
+// Le dashboard affiche ainsi le template exact, ou "modified" si le contenu
+// ne correspond plus à l'empreinte (retouche dans l'éditeur). Un fichier sans
+// tampon est reconnu s'il est identique à un template.
+//
+// Utilisé par POST /apply/:lab/:level et par la matrice d'évasion.
+//
+// =============================================================================
+
+use crate::labs::Lab;
+use crate::matrix::LEVELS;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Niveau demandé à POST /apply/:lab/:level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Level {
+    /// Niveau 1 à 5
+    Builtin(u8),
+    /// Template personnalisé du template_dir (nom sans extension)
+    Custom(String),
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Builtin(n) => write!(f, "{}", n),
+            Level::Custom(name) => f.write_str(name),
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+            return match s.parse::<u8>() {
+                Ok(n) if LEVELS.contains(&n) => Ok(Level::Builtin(n)),
+                _ => Err(format!("Invalid level {:?} (expected 1-5 or a custom template name)", s)),
+            };
+        }
+        let valid = !s.is_empty()
+            && s.len() <= 64
+            && !is_builtin_name(s)
+            && s.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!("Invalid level {:?} (expected 1-5 or a custom template name)", s));
+        }
+        Ok(Level::Custom(s.to_string()))
+    }
+}
+
+/// Manifeste des templates d'un template_dir.
+const MANIFEST: &str = "manifest.toml";
+/// Début de la ligne de tampon du fichier de règles actif.
+const STAMP_PREFIX: &str = "# ids-commander template=";
+
+/// Métadonnées d'un template (bloc [[template]] de manifest.toml).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateMeta {
+    pub id: String,
+    pub file: String,
+    pub name: String,
+    /// Niveau 1 à 5, absent pour un template personnalisé
+    pub level: Option<u8>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: String,
+    /// Techniques d'attaque (attack.rs) que le template doit détecter
+    #[serde(default)]
+    pub techniques: Vec<String>,
+}
+
+impl TemplateMeta {
+    /// Métadonnées d'un fichier absent du manifeste: son nom sert de tout.
+    fn unlisted(stem: &str, file: &str) -> TemplateMeta {
+        TemplateMeta {
+            id: stem.to_string(),
+            file: file.to_string(),
+            name: stem.to_string(),
+            level: None,
+            description: String::new(),
+            author: String::new(),
+            techniques: vec![],
+        }
+    }
+
+    /// Libellé du dashboard et des runs: "3 - Moderate" ou le nom du template.
+    pub fn label(&self) -> String {
+        match self.level {
+            Some(level) => format!("{} - {}", level, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default, rename = "template")]
+    templates: Vec<TemplateMeta>,
+}
+
+/// Lit le manifeste d'un lab (vide sans template_dir ni manifest.toml).
+pub fn manifest(root: &Path, lab: &Lab) -> Result<Vec<TemplateMeta>, String> {
+    let Some(dir) = lab.template_dir(root) else { return Ok(vec![]) };
+    let path = dir.join(MANIFEST);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+    };
+    toml::from_str::<Manifest>(&content)
+        .map(|m| m.templates)
+        .map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
+
+/// Métadonnées d'un fichier du template_dir (manifeste, sinon son nom).
+pub fn template_meta(root: &Path, lab: &Lab, template: &Path) -> TemplateMeta {
+    describe(&listed(root, lab), template)
+}
+
+/// Manifeste du lab, vide (avec un message) s'il est illisible.
+fn listed(root: &Path, lab: &Lab) -> Vec<TemplateMeta> {
+    manifest(root, lab).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        vec![]
+    })
+}
+
+fn describe(listed: &[TemplateMeta], template: &Path) -> TemplateMeta {
+    let file = template.file_name().and_then(|f| f.to_str()).unwrap_or_default();
+    let stem = template.file_stem().and_then(|f| f.to_str()).unwrap_or_default();
+    listed
+        .iter()
+        .find(|m| m.file == file)
+        .cloned()
+        .unwrap_or_else(|| TemplateMeta::unlisted(stem, file))
+}
+
+/// Empreinte SHA-256 (hexadécimal) d'un contenu de règles.
+pub fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Sépare le tampon (id, empreinte) du contenu des règles actives.
+fn split_stamp(content: &[u8]) -> (Option<(String, String)>, &[u8]) {
+    let Some(rest) = content.strip_prefix(STAMP_PREFIX.as_bytes()) else { return (None, content) };
+    let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+    let line = String::from_utf8_lossy(&rest[..end]);
+    let body = rest.get(end + 1..).unwrap_or_default();
+    match line.trim_end().split_once(" sha256=") {
+        Some((id, hash)) => (Some((id.to_string(), hash.to_string())), body),
+        None => (None, content),
+    }
+}
+
+/// Template actuellement installé dans le fichier de règles actif.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActiveRules {
+    /// Template appliqué, `modified` si le contenu a été retouché depuis
+    Template { meta: TemplateMeta, modified: bool },
+    /// Règles écrites à la main, sans rapport avec un template
+    Custom,
+    /// Fichier de règles absent ou illisible
+    Missing,
+}
+
+impl fmt::Display for ActiveRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActiveRules::Template { meta, modified: false } => f.write_str(&meta.label()),
+            ActiveRules::Template { meta, modified: true } => write!(f, "{}, modified", meta.label()),
+            ActiveRules::Custom => f.write_str("Custom"),
+            ActiveRules::Missing => f.write_str("N/A"),
+        }
+    }
+}
+
+/// Identifie les règles actives d'un lab: tampon d'abord, sinon contenu
+/// identique à l'un des templates.
+pub fn active_rules(root: &Path, lab: &Lab) -> ActiveRules {
+    let Some(content) = lab.rules_path(root).and_then(|p| std::fs::read(p).ok()) else {
+        return ActiveRules::Missing;
+    };
+    let listed = listed(root, lab);
+    let templates: Vec<(TemplateMeta, PathBuf)> = template_files(root, lab)
+        .into_iter()
+        .map(|(_, path)| (describe(&listed, &path), path))
+        .collect();
+
+    match split_stamp(&content) {
+        (Some((id, hash)), body) => {
+            let meta = templates
+                .into_iter()
+                .map(|(meta, _)| meta)
+                .find(|m| m.id == id)
+                .unwrap_or_else(|| TemplateMeta::unlisted(&id, ""));
+            ActiveRules::Template { meta, modified: content_hash(body) != hash }
+        }
+        (None, body) => templates
+            .into_iter()
+            .find(|(_, path)| std::fs::read(path).is_ok_and(|t| t == body))
+            .map_or(ActiveRules::Custom, |(meta, _)| ActiveRules::Template { meta, modified: false }),
+    }
+}
+
+/// Nom réservé aux niveaux livrés: "level3", "level3_moderate"...
+fn is_builtin_name(stem: &str) -> bool {
+    stem.strip_prefix("level")
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Fichiers du template_dir triés par nom, avec leur nom sans extension.
+fn template_files(root: &Path, lab: &Lab) -> Vec<(String, PathBuf)> {
+    let Some(dir) = lab.template_dir(root) else { return vec![] };
+    let mut files: Vec<(String, PathBuf)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && !p.ends_with(MANIFEST))
+        .filter_map(|p| Some((p.file_stem()?.to_str()?.to_string(), p)))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Recherche le template d'un niveau: level{N} ou level{N}_* pour un
+/// niveau livré, fichier du même nom pour un niveau personnalisé.
+pub fn find_template(root: &Path, lab: &Lab, level: &Level) -> Option<PathBuf> {
+    template_files(root, lab)
+        .into_iter()
+        .find(|(stem, _)| match level {
+            Level::Builtin(n) => {
+                let prefix = format!("level{}", n);
+                stem == &prefix || stem.starts_with(&format!("{}_", prefix))
+            }
+            Level::Custom(name) => stem == name,
+        })
+        .map(|(_, path)| path)
+}
+
+/// Niveaux personnalisés disponibles pour un lab (boutons du dashboard).
+pub fn custom_levels(root: &Path, lab: &Lab) -> Vec<String> {
+    template_files(root, lab)
+        .into_iter()
+        .map(|(stem, _)| stem)
+        .filter(|stem| stem.parse::<Level>().is_ok_and(|l| matches!(l, Level::Custom(_))))
+        .collect()
+}
+
+/// Installe le template d'un niveau comme règles actives, précédé du
+/// tampon (id du template, empreinte). Retourne le fichier de règles écrit.
+pub fn install(root: &Path, lab: &Lab, level: &Level) -> Result<PathBuf, String> {
+    let dest = lab.rules_path(root).ok_or_else(|| format!("Lab {} has no rules", lab.name))?;
+    let template = find_template(root, lab, level)
+        .ok_or_else(|| format!("No level {} template for {}", level, lab.name))?;
+    let content = std::fs::read(&template).map_err(|e| format!("Error reading template: {}", e))?;
+    let meta = template_meta(root, lab, &template);
+    let mut stamped = format!("{}{} sha256={}\n", STAMP_PREFIX, meta.id, content_hash(&content)).into_bytes();
+    stamped.extend_from_slice(&content);
+    std::fs::write(&dest, stamped).map_err(|e| format!("Error copying template: {}", e))?;
+    Ok(dest)
+}
+
+/// Délai laissé au reloader pour recharger l'IDS après un changement de règles.
+/// Réglage reload_wait_secs, sinon selon la méthode de rechargement du lab.
+pub fn reload_delay(lab: &Lab) -> std::time::Duration {
+    crate::config::get()
+        .reload_wait
+        .or_else(|| lab.reload.map(|r| r.default_delay()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labs::Registry;
+
+    #[test]
+    fn install_copies_the_level_template() {
+        let root = std::env::temp_dir().join(format!("ids-templates-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("commander/templates/suricata")).unwrap();
+        std::fs::create_dir_all(root.join("suricata-lab/rules")).unwrap();
+        std::fs::write(root.join("commander/templates/suricata/level2_basic.rules"), "# Level 2\n").unwrap();
+        std::fs::write(root.join("commander/templates/suricata/level3_moderate.rules"), "# Level 3\n").unwrap();
+        std::fs::write(root.join("commander/templates/suricata/ftp_only.rules"), "# FTP\n").unwrap();
+
+        let registry = Registry::parse(
+            r#"[[lab]]
+            name = "suricata"
+            title = "SURICATA Lab"
+            engine = "suricata"
+            compose_dir = "suricata-lab"
+            subnet = "172.29.0.0/24"
+            active_rules = "suricata-lab/rules/local.rules"
+            template_dir = "commander/templates/suricata""#,
+        )
+        .unwrap();
+        let lab = registry.get("suricata").unwrap();
+
+        std::fs::write(
+            root.join("commander/templates/suricata/manifest.toml"),
+            "[[template]]\nid = \"suricata-level3\"\nfile = \"level3_moderate.rules\"\nname = \"Moderate\"\nlevel = 3\n",
+        )
+        .unwrap();
+
+        let dest = install(&root, lab, &"3".parse().unwrap()).unwrap();
+        let installed = std::fs::read_to_string(&dest).unwrap();
+        assert_eq!(installed, format!("{}suricata-level3 sha256={}\n# Level 3\n", STAMP_PREFIX, content_hash(b"# Level 3\n")));
+        assert_eq!(active_rules(&root, lab).to_string(), "3 - Moderate");
+        assert!(install(&root, lab, &Level::Builtin(5)).is_err());
+        // manifest.toml n'est pas un niveau personnalisé
+        assert_eq!(custom_levels(&root, lab), ["ftp_only"]);
+        install(&root, lab, &"ftp_only".parse().unwrap()).unwrap();
+        assert_eq!(active_rules(&root, lab).to_string(), "ftp_only");
+        assert!(find_template(&root, lab, &Level::Custom("ftp".into())).is_none());
+
+        // Retouche dans l'éditeur: le tampon ne correspond plus
+        std::fs::write(&dest, std::fs::read_to_string(&dest).unwrap() + "alert ip any any -> any any (sid:1;)\n").unwrap();
+        assert_eq!(active_rules(&root, lab).to_string(), "ftp_only, modified");
+        // Copie sans tampon (installée avant les manifestes): reconnue au contenu
+        std::fs::write(&dest, "# Level 2\n").unwrap();
+        assert_eq!(active_rules(&root, lab).to_string(), "level2_basic");
+        std::fs::write(&dest, "alert ip any any -> any any (sid:1;)\n").unwrap();
+        assert_eq!(active_rules(&root, lab), ActiveRules::Custom);
+
+        // Niveau vide, hors 1-5, nom réservé ou chemin: rejetés avant toute lecture
+        for bad in ["", "0", "6", "33", "level3_moderate", "../etc", "FTP", "a b"] {
+            assert!(bad.parse::<Level>().is_err(), "{:?}", bad);
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn shipped_manifests_describe_every_template() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+        let mut ids = std::collections::BTreeSet::new();
+        for engine in ["snort", "suricata", "zeek"] {
+            let content = std::fs::read_to_string(dir.join(engine).join(MANIFEST)).unwrap();
+            let manifest: Manifest = toml::from_str(&content).unwrap();
+            assert_eq!(manifest.templates.len(), LEVELS.len(), "{}", engine);
+            for meta in &manifest.templates {
+                assert!(ids.insert(meta.id.clone()), "duplicate id {}", meta.id);
+                assert!(dir.join(engine).join(&meta.file).is_file(), "{}", meta.file);
+                let level = meta.level.unwrap();
+                assert!(meta.file.starts_with(&format!("level{}_", level)), "{}", meta.file);
+                for technique in &meta.techniques {
+                    assert!(crate::attack::technique(technique).is_some(), "{}: {}", meta.id, technique);
+                }
+            }
+        }
+    }
+}
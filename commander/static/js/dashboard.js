@@ -43,6 +43,8 @@ function startAutoRefresh() {
                 isRefreshing = false;
             })
             .catch(() => { isRefreshing = false; });
+        refreshLocks();
+        refreshReadiness();
     }, 3000);
 
     console.log('🔄 Auto-refresh activé (3s)');
@@ -62,7 +64,7 @@ function updateContainerStatus(containers) {
         const row = document.querySelector(`tr[data-container="${c.name}"]`);
         if (!row) return;
 
-        const isRunning = c.status.includes('Up');
+        const isRunning = c.state === 'running';
         const statusCell = row.querySelector('td:nth-child(3)');
         const btnCell = row.querySelector('td:nth-child(4)');
 
@@ -119,24 +121,49 @@ document.body.addEventListener('htmx:afterSwap', function(event) {
     if (typeof mermaid !== 'undefined') {
         mermaid.init(undefined, document.querySelectorAll('.mermaid'));
     }
+    // The swap replaced the jobs panel and lab cards
+    renderJobs();
+    refreshLocks();
+    refreshReadiness();
+    loadUser();
 });
 
+// Errors of HTMX actions (409 lab busy...): show the server message
+document.body.addEventListener('htmx:responseError', function(event) {
+    const xhr = event.detail.xhr;
+    // Session expired: back to the login form
+    if (xhr.status === 401) {
+        window.location.href = '/login';
+        return;
+    }
+    let message = xhr.responseText;
+    try { message = JSON.parse(message).error || message; } catch (e) {}
+    showToast(message || `Erreur ${xhr.status}`, 'error');
+});
+
+// Token CSRF de la session (GET /api/me, voir loadUser), exigé par chaque
+// hx-post et chaque POST fetch
+let csrfToken = '';
+
+// Ajouté à toutes les requêtes HTMX
+document.body.addEventListener('htmx:configRequest', function(event) {
+    event.detail.headers['X-CSRF-Token'] = csrfToken;
+});
+
+// En-têtes des POST fetch (corps JSON)
+function jsonHeaders() {
+    return { 'Content-Type': 'application/json', 'X-CSRF-Token': csrfToken };
+}
+
 // Keyboard shortcuts
 document.addEventListener('keydown', function(e) {
-    // Ctrl+1: Snort lab
-    if (e.ctrlKey && e.key === '1') {
-        e.preventDefault();
-        document.querySelector('[hx-post="/lab/start/snort"]')?.click();
-    }
-    // Ctrl+2: Suricata lab
-    if (e.ctrlKey && e.key === '2') {
-        e.preventDefault();
-        document.querySelector('[hx-post="/lab/start/suricata"]')?.click();
-    }
-    // Ctrl+3: Zeek lab
-    if (e.ctrlKey && e.key === '3') {
-        e.preventDefault();
-        document.querySelector('[hx-post="/lab/start/zeek"]')?.click();
+    // Ctrl+1..9: démarrer le Nième lab (ordre de labs.toml)
+    if (e.ctrlKey && e.key >= '1' && e.key <= '9') {
+        const card = document.querySelectorAll('.lab-card')[Number(e.key) - 1];
+        if (card) {
+            e.preventDefault();
+            card.querySelector('[data-action="start"]')?.click();
+        }
     }
 });
 
@@ -186,7 +213,7 @@ document.head.appendChild(style);
 // ALERTS VIEWER
 // =============================================================================
 
-let currentAlertTab = 'snort';
+let currentAlertTab = document.querySelector('.alerts-panel')?.id.replace('alerts-', '');
 
 // Switch between alert tabs
 function showAlertTab(ids) {
@@ -210,34 +237,25 @@ function loadAlerts(ids) {
     contentEl.textContent = 'Chargement...';
     contentEl.className = 'alerts-content loading';
 
-    fetch(`/api/alerts/${ids}`)
+    fetch(`/api/alerts/${ids}?limit=200`)
         .then(r => r.json())
         .then(data => {
             if (data.error) {
-                contentEl.textContent = `Erreur: ${data.error}\n\nAssurez-vous que le container ${ids}_ids est en cours d'exécution.`;
+                contentEl.textContent = `Erreur: ${data.error}\n\nLes alertes sont lues dans ${ids}-lab/logs/.`;
                 contentEl.className = 'alerts-content error';
                 return;
             }
 
+            // Tail en direct à partir de maintenant (même si aucune alerte pour l'instant)
+            startAlertStream(ids);
+
             if (data.alerts.length === 0) {
                 contentEl.textContent = `Aucune alerte détectée.\n\nLancez un scan sur la cible pour générer des alertes:\nnmap -sS -p 21,22,80 <target_ip>`;
                 contentEl.className = 'alerts-content';
                 return;
             }
 
-            // Format alerts with syntax highlighting
-            const formatted = data.alerts.map(line => {
-                // Highlight based on severity/keywords
-                if (line.includes('Priority: 1') || line.includes('CRITICAL') || line.includes('high')) {
-                    return `<span class="alert-priority-high">${escapeHtml(line)}</span>`;
-                } else if (line.includes('Priority: 2') || line.includes('WARNING') || line.includes('medium')) {
-                    return `<span class="alert-priority-medium">${escapeHtml(line)}</span>`;
-                } else {
-                    return `<span class="alert-line">${escapeHtml(line)}</span>`;
-                }
-            }).join('\n');
-
-            contentEl.innerHTML = formatted;
+            contentEl.innerHTML = data.alerts.map(renderAlertLine).join('');
             contentEl.className = 'alerts-content';
         })
         .catch(err => {
@@ -246,6 +264,67 @@ function loadAlerts(ids) {
         });
 }
 
+// Render an alert as a highlighted line (priorité 1 = la plus haute)
+function renderAlertLine(alert) {
+    const line = escapeHtml(formatAlert(alert));
+    if (alert.priority === 1) {
+        return `<span class="alert-line alert-priority-high">${line}</span>`;
+    } else if (alert.priority === 2) {
+        return `<span class="alert-line alert-priority-medium">${line}</span>`;
+    }
+    return `<span class="alert-line">${line}</span>`;
+}
+
+// Live tail: one EventSource on /api/alerts/stream for the active tab
+let alertStream = null;
+const MAX_LIVE_ALERTS = 200;
+
+function startAlertStream(ids) {
+    stopAlertStream();
+    alertStream = new EventSource(`/api/alerts/stream?engine=${ids}`);
+    setLiveIndicator(true);
+
+    alertStream.addEventListener('alert', event => {
+        const contentEl = document.getElementById(`alerts-content-${ids}`);
+        if (!contentEl) return;
+
+        // Remplacer le message "Aucune alerte" à la première alerte reçue
+        if (!contentEl.querySelector('.alert-line')) contentEl.textContent = '';
+
+        // Les alertes sont affichées de la plus récente à la plus ancienne
+        contentEl.insertAdjacentHTML('afterbegin', renderAlertLine(JSON.parse(event.data)));
+        while (contentEl.children.length > MAX_LIVE_ALERTS) {
+            contentEl.lastElementChild.remove();
+        }
+    });
+    alertStream.onerror = () => setLiveIndicator(false);
+    alertStream.onopen = () => setLiveIndicator(true);
+}
+
+function stopAlertStream() {
+    if (alertStream) {
+        alertStream.close();
+        alertStream = null;
+    }
+    setLiveIndicator(false);
+}
+
+function setLiveIndicator(live) {
+    const el = document.getElementById('alerts-live');
+    if (el) el.classList.toggle('active', live);
+}
+
+// Format an alert object (see /api/alerts) as a single fast-log style line
+function formatAlert(a) {
+    const endpoint = (ip, port) => ip ? (port != null ? `${ip}:${port}` : ip) : '-';
+    const time = a.timestamp.slice(0, 19).replace('T', ' ');
+    const ids = a.sid != null ? `[${a.gid ?? 1}:${a.sid}:${a.rev ?? 0}] ` : '';
+    const cls = a.classification ? ` [${a.classification}]` : '';
+    const prio = a.priority != null ? ` [Priority: ${a.priority}]` : '';
+    const proto = a.protocol ? ` {${a.protocol}}` : '';
+    return `${time} ${ids}${a.message}${cls}${prio}${proto} ${endpoint(a.src_ip, a.src_port)} -> ${endpoint(a.dst_ip, a.dst_port)}`;
+}
+
 // Refresh all alerts
 function refreshAlerts() {
     loadAlerts(currentAlertTab);
@@ -262,6 +341,419 @@ function escapeHtml(text) {
 document.addEventListener('DOMContentLoaded', function() {
     // Delay to let other initializations complete
     setTimeout(() => {
-        loadAlerts('snort');
+        if (currentAlertTab) loadAlerts(currentAlertTab);
     }, 1000);
 });
+
+// =============================================================================
+// ATTACK RUNNER
+// =============================================================================
+
+let attackStream = null;
+
+// Launch a cookbook technique from the server and follow its output
+function runAttack() {
+    const lab = document.getElementById('attack-lab').value;
+    const technique = document.getElementById('attack-technique').value;
+    const outputEl = document.getElementById('attack-output');
+    const btn = document.getElementById('attack-run-btn');
+
+    btn.disabled = true;
+    outputEl.textContent = '';
+
+    fetch('/api/attacks', {
+        method: 'POST',
+        headers: jsonHeaders(),
+        body: JSON.stringify({ lab, technique })
+    })
+        .then(r => r.json())
+        .then(run => {
+            if (run.error) throw new Error(run.error);
+            outputEl.textContent = `# Run ${run.id} - ${run.command} (niveau ${run.rule_level})\n`;
+            followAttackOutput(run.id, outputEl, btn);
+        })
+        .catch(err => {
+            outputEl.textContent = `Erreur: ${err.message}`;
+            btn.disabled = false;
+        });
+}
+
+function followAttackOutput(runId, outputEl, btn) {
+    if (attackStream) attackStream.close();
+    attackStream = new EventSource(`/api/runs/${runId}/output`);
+
+    attackStream.addEventListener('line', event => {
+        outputEl.textContent += event.data + '\n';
+        outputEl.scrollTop = outputEl.scrollHeight;
+    });
+    attackStream.addEventListener('exit', event => {
+        const { exit_code } = JSON.parse(event.data);
+        outputEl.textContent += `\n# Terminé (code ${exit_code ?? 'inconnu'}) - alertes: /api/runs/${runId}\n`;
+        showToast(`Run ${runId} terminé`, exit_code === 0 ? 'success' : 'error');
+        attackStream.close();
+        attackStream = null;
+        btn.disabled = false;
+    });
+    attackStream.onerror = () => {
+        if (attackStream) attackStream.close();
+        attackStream = null;
+        btn.disabled = false;
+    };
+}
+
+// =============================================================================
+// EVASION MATRIX
+// =============================================================================
+
+let matrixPoll = null;
+
+// Start a technique x level job for the selected lab
+function startMatrix() {
+    const lab = document.getElementById('matrix-lab').value;
+    fetch('/api/matrix', {
+        method: 'POST',
+        headers: jsonHeaders(),
+        body: JSON.stringify({ lab })
+    })
+        .then(r => r.json())
+        .then(matrix => {
+            if (matrix.error) throw new Error(matrix.error);
+            showToast(`Matrice ${matrix.id} démarrée (${lab})`, 'info');
+            loadMatrix(matrix.id);
+        })
+        .catch(err => showToast(`Erreur: ${err.message}`, 'error'));
+}
+
+// Load a matrix and keep polling while the job is running
+function loadMatrix(id) {
+    clearTimeout(matrixPoll);
+    Promise.all([fetch(`/api/matrix/${id}`).then(r => r.json()), fetch('/api/attacks').then(r => r.json())])
+        .then(([matrix, techniques]) => {
+            renderMatrix(matrix, techniques);
+            if (matrix.status === 'running') {
+                matrixPoll = setTimeout(() => loadMatrix(id), 3000);
+            }
+        })
+        .catch(() => {});
+}
+
+// Table: one row per technique, one column per level
+function renderMatrix(matrix, techniques) {
+    const el = document.getElementById('matrix-result');
+    const btn = document.getElementById('matrix-run-btn');
+    if (!el) return;
+    if (btn) btn.disabled = matrix.status === 'running';
+
+    const levels = [1, 2, 3, 4, 5];
+    const cell = (technique, level) => {
+        const c = matrix.cells.find(c => c.technique === technique && c.level === level);
+        if (!c) return '<td class="matrix-pending">…</td>';
+        return c.detected
+            ? `<td class="matrix-detected" title="Run ${c.run_id}">🚨 ${c.alerts}</td>`
+            : `<td class="matrix-evaded" title="Run ${c.run_id}">✓ évadé</td>`;
+    };
+    const rows = techniques.map(t =>
+        `<tr><td>${escapeHtml(t.name)}</td>${levels.map(l => cell(t.id, l)).join('')}</tr>`
+    ).join('');
+    const status = matrix.status === 'failed'
+        ? `échec: ${escapeHtml(matrix.error || '')}`
+        : matrix.status === 'running' ? 'en cours...' : 'terminée';
+
+    el.innerHTML = `
+        <p class="matrix-status">Matrice ${matrix.id} - ${escapeHtml(matrix.lab)} (${escapeHtml(matrix.engine)}) - ${status}</p>
+        <table class="matrix-table">
+            <tr><th>Technique</th>${levels.map(l => `<th>Niv. ${l}</th>`).join('')}</tr>
+            ${rows}
+        </table>`;
+}
+
+// Show the latest matrix on page load
+document.addEventListener('DOMContentLoaded', function() {
+    fetch('/api/matrix')
+        .then(r => r.json())
+        .then(matrices => {
+            if (Array.isArray(matrices) && matrices.length > 0) loadMatrix(matrices[0].id);
+        })
+        .catch(() => {});
+});
+
+// =============================================================================
+// DOCKER EVENTS TIMELINE
+// =============================================================================
+
+// Load the latest container events, optionally for one lab
+function loadEvents() {
+    const el = document.getElementById('events-timeline');
+    if (!el) return;
+    const lab = document.getElementById('events-lab').value;
+    const params = new URLSearchParams({ limit: 50 });
+    if (lab) params.set('lab', lab);
+
+    fetch(`/api/events?${params}`)
+        .then(r => r.json())
+        .then(events => {
+            if (!Array.isArray(events) || events.length === 0) {
+                el.textContent = 'Aucun événement enregistré.';
+                return;
+            }
+            el.innerHTML = events.map(e => `
+                <div class="event-line event-${escapeHtml(e.action)}">
+                    <span class="event-time">${new Date(e.time).toLocaleString()}</span>
+                    <span class="event-container">${escapeHtml(e.container)}</span>
+                    <span class="event-action">${escapeHtml(e.action)}</span>
+                    <span class="event-detail">${escapeHtml(e.detail || '')}</span>
+                </div>`).join('');
+        })
+        .catch(() => {});
+}
+
+document.addEventListener('DOMContentLoaded', function() {
+    loadEvents();
+    setInterval(loadEvents, 10000);
+});
+
+// =============================================================================
+// LAB JOBS (docker compose start / stop / rebuild)
+// =============================================================================
+
+// Followed jobs by id: { job, el } (el survives dashboard swaps)
+const labJobs = new Map();
+
+// Submit a docker compose operation on a lab and follow its progress.
+// Lab busy (409): offer to queue the operation behind the current one.
+function submitJob(lab, action, confirmMessage, wait = false) {
+    if (confirmMessage && !confirm(confirmMessage)) return;
+    fetch('/api/jobs', {
+        method: 'POST',
+        headers: jsonHeaders(),
+        body: JSON.stringify({ lab, action, wait })
+    })
+        .then(r => r.json())
+        .then(job => {
+            if (job.lock) {
+                if (confirm(`${job.error}\n\nMettre "${action} ${lab}" en file d'attente ?`)) submitJob(lab, action, null, true);
+                return;
+            }
+            if (job.error) throw new Error(job.error);
+            showToast(`Job ${job.id}: ${action} ${lab}${job.status === 'queued' ? ' (en attente)' : ''}`, 'info');
+            followJob(job);
+        })
+        .catch(err => showToast(`Erreur: ${err.message}`, 'error'));
+}
+
+// One stop job per lab, queued behind any operation already running
+function stopAllLabs() {
+    if (!confirm('Arrêter TOUS les labs ?')) return;
+    fetch('/api/jobs/stop-all', { method: 'POST', headers: jsonHeaders() })
+        .then(r => r.json())
+        .then(jobs => {
+            if (jobs.error) throw new Error(jobs.error);
+            showToast(`Arrêt de ${jobs.length} labs`, 'info');
+            jobs.forEach(followJob);
+        })
+        .catch(err => showToast(`Erreur: ${err.message}`, 'error'));
+}
+
+function followJob(job) {
+    if (labJobs.has(job.id)) return;
+
+    const el = document.createElement('div');
+    el.className = 'job';
+    el.innerHTML = `
+        <div class="job-header">
+            <span class="job-title"></span>
+            <span class="job-step"></span>
+            <button class="job-close" title="Masquer">✕</button>
+        </div>
+        <div class="job-error"></div>
+        <pre class="job-output"></pre>`;
+    el.querySelector('.job-title').textContent = `#${job.id} ${job.action} ${job.lab}`;
+    el.querySelector('.job-close').onclick = () => {
+        labJobs.delete(job.id);
+        el.remove();
+    };
+    const entry = { job, el };
+    labJobs.set(job.id, entry);
+    updateJob(entry);
+
+    const outputEl = el.querySelector('.job-output');
+    const source = new EventSource(`/api/jobs/${job.id}/output`);
+    source.addEventListener('line', event => {
+        outputEl.textContent += event.data + '\n';
+        outputEl.scrollTop = outputEl.scrollHeight;
+        // "$ docker compose ..." opens the next step
+        if (event.data.startsWith('$ ')) {
+            entry.job.step = (outputEl.textContent.match(/^\$ /gm) || []).length;
+            entry.job.command = event.data.substring(2);
+            updateJob(entry);
+        }
+    });
+    source.addEventListener('exit', () => {
+        source.close();
+        fetch(`/api/jobs/${job.id}`)
+            .then(r => r.json())
+            .then(final => {
+                entry.job = final;
+                updateJob(entry);
+                if (final.status === 'failed') {
+                    showToast(`Job ${final.id} (${final.action} ${final.lab}) en échec`, 'error');
+                } else {
+                    showToast(`Job ${final.id}: ${final.action} ${final.lab} terminé`, 'success');
+                }
+                // Reload the dashboard (lab state); the jobs panel is restored after the swap
+                htmx.ajax('GET', '/', { target: 'body' });
+            })
+            .catch(() => {});
+    });
+    source.onerror = () => source.close();
+}
+
+// Refresh a job block and the spinner of its lab card
+function updateJob({ job, el }) {
+    const running = job.status === 'running' || job.status === 'queued';
+    el.classList.toggle('job-succeeded', job.status === 'succeeded');
+    el.classList.toggle('job-failed', job.status === 'failed');
+    el.querySelector('.job-step').textContent = job.status === 'queued'
+        ? 'en attente du lab'
+        : running ? `étape ${job.step}/${job.steps}: ${job.command}` : job.status === 'failed' ? 'échec' : 'terminé';
+    el.querySelector('.job-error').textContent = job.error || '';
+    renderJobs();
+}
+
+// Put job blocks (newest first) in the panel and mark lab cards with a running job
+function renderJobs() {
+    const panel = document.getElementById('jobs-panel');
+    if (!panel) return;
+    const entries = [...labJobs.values()].sort((a, b) => b.job.id - a.job.id);
+    panel.replaceChildren(...entries.map(e => e.el));
+
+    document.querySelectorAll('.lab-card').forEach(card => {
+        const running = entries.find(e => e.job.lab === card.dataset.lab && ['queued', 'running'].includes(e.job.status));
+        card.classList.toggle('job-running', !!running);
+        const msg = card.querySelector('.loading-msg');
+        if (msg) msg.textContent = running ? `${running.job.action} ${running.job.step}/${running.job.steps}...` : 'Please wait...';
+    });
+}
+
+// Resume jobs still running (page reloaded during a build)
+document.addEventListener('DOMContentLoaded', function() {
+    fetch('/api/jobs')
+        .then(r => r.json())
+        .then(jobs => jobs.filter(j => ['queued', 'running'].includes(j.status)).forEach(followJob))
+        .catch(() => {});
+});
+
+// Show who holds each lab lock on the lab cards
+function refreshLocks() {
+    fetch('/api/locks')
+        .then(r => r.json())
+        .then(locks => {
+            document.querySelectorAll('.lab-card').forEach(card => {
+                const el = card.querySelector('.lab-lock');
+                if (!el) return;
+                const lock = locks.find(l => l.lab === card.dataset.lab);
+                const holder = lock && lock.holder;
+                el.textContent = holder
+                    ? `🔒 ${holder.operation} (${holder.client}, ${new Date(holder.since).toLocaleTimeString()})`
+                        + (lock.queue.length ? ` +${lock.queue.length} en attente` : '')
+                    : '';
+            });
+        })
+        .catch(() => {});
+}
+
+document.addEventListener('DOMContentLoaded', refreshLocks);
+
+// Show lab readiness (probes of readiness.rs) on the lab cards
+const READINESS_LABELS = { ready: '● Ready', degraded: '◐ Degraded', failed: '✕ Failed' };
+
+function refreshReadiness() {
+    fetch('/api/health')
+        .then(r => r.json())
+        .then(health => {
+            document.querySelectorAll('.lab-card').forEach(card => {
+                const el = card.querySelector('.lab-readiness');
+                if (!el) return;
+                const r = (health.readiness || {})[card.dataset.lab];
+                const label = r && READINESS_LABELS[r.state];
+                el.className = label ? `lab-readiness readiness-${r.state}` : 'lab-readiness';
+                el.textContent = label ? (r.reasons.length ? `${label}: ${r.reasons[0]}` : label) : '';
+                el.title = r ? r.checks.map(c => `${c.name}: ${c.detail}`).join('\n') : '';
+            });
+        })
+        .catch(() => {});
+}
+
+document.addEventListener('DOMContentLoaded', refreshReadiness);
+
+// =============================================================================
+// RULE HISTORY (previous versions, diff, rollback)
+// =============================================================================
+
+// List the saved versions of a lab's active rules in its template card
+function loadRuleHistory(lab) {
+    const el = [...document.querySelectorAll('.rule-history')].find(h => h.dataset.lab === lab)?.querySelector('.history-list');
+    if (!el) return;
+    const base = `/api/rules/${encodeURIComponent(lab)}`;
+    fetch(`${base}/history`)
+        .then(r => r.json())
+        .then(versions => {
+            if (versions.error) throw new Error(versions.error);
+            if (versions.length === 0) {
+                el.textContent = 'Aucune version enregistrée.';
+                return;
+            }
+            el.innerHTML = versions.slice(0, 10).map(v => `
+                <div class="history-line">
+                    <span class="history-time">#${v.id} ${new Date(v.time).toLocaleString()}</span>
+                    <span class="history-reason">${escapeHtml(v.actor)} · ${escapeHtml(v.reason)}</span>
+                    <a href="${base}/diff?from=${v.id}" target="_blank" title="Différences avec les règles actuelles">diff</a>
+                    <button class="btn neutral" data-version="${v.id}" title="Remettre cette version">↶</button>
+                </div>`).join('');
+            el.querySelectorAll('button[data-version]').forEach(btn => {
+                btn.onclick = () => rollbackRules(lab, Number(btn.dataset.version));
+            });
+        })
+        .catch(err => showToast(`Erreur: ${err.message}`, 'error'));
+}
+
+// Restore a saved version (the replaced rules are saved too) and reload the dashboard
+function rollbackRules(lab, id) {
+    if (!confirm(`Remettre la version #${id} des règles ${lab} ?`)) return;
+    fetch(`/api/rules/${encodeURIComponent(lab)}/rollback/${id}`, { method: 'POST', headers: jsonHeaders() })
+        .then(r => r.json())
+        .then(version => {
+            if (version.error) throw new Error(version.error);
+            showToast(`Règles ${lab}: version #${version.id} remise en place`, 'success');
+            setTimeout(() => window.location.reload(), 1000);
+        })
+        .catch(err => showToast(`Erreur: ${err.message}`, 'error'));
+}
+
+// =============================================================================
+// USER SESSION
+// =============================================================================
+
+// Logged-in user, role and logout button in the header (accounts of auth.rs)
+function loadUser() {
+    const el = document.getElementById('user-info');
+    if (!el) return;
+    fetch('/api/me')
+        .then(r => r.json())
+        .then(me => {
+            csrfToken = me.csrf_token || '';
+            el.textContent = '';
+            if (!me.auth) return;
+            const labs = me.user.labs.length ? ` · ${me.user.labs.join(', ')}` : '';
+            el.append(`👤 ${me.user.name} (${me.user.role}${labs}) `);
+            const button = document.createElement('button');
+            button.className = 'btn neutral';
+            button.textContent = 'Logout';
+            button.onclick = () => fetch('/logout', { method: 'POST', headers: jsonHeaders() })
+                .finally(() => { window.location.href = '/login'; });
+            el.appendChild(button);
+        })
+        .catch(() => {});
+}
+
+document.addEventListener('DOMContentLoaded', loadUser);
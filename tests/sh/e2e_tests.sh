@@ -28,6 +28,8 @@ NC='\033[0m'
 
 # Configuration
 COMMANDER_URL="http://localhost:3000"
+# Token d'API si le commander a des comptes (ids-commander add-token)
+COMMANDER_TOKEN="${COMMANDER_TOKEN:-}"
 PROJECT_ROOT="$(cd "$(dirname "$0")/.." && pwd)"
 TESTS_PASSED=0
 TESTS_FAILED=0
@@ -36,6 +38,18 @@ TESTS_FAILED=0
 # HELPERS
 # =============================================================================
 
+# curl vers le commander, avec le token d'API s'il est défini, sinon avec le
+# token CSRF du serveur (exigé pour les POST, donné par /api/me)
+commander_curl() {
+    if [ -n "$COMMANDER_TOKEN" ]; then
+        curl -s -H "Authorization: Bearer $COMMANDER_TOKEN" "$@"
+    else
+        local csrf
+        csrf=$(curl -s "$COMMANDER_URL/api/me" | sed -n 's/.*"csrf_token":"\([0-9a-f]*\)".*/\1/p')
+        curl -s -H "X-CSRF-Token: $csrf" "$@"
+    fi
+}
+
 log_info() {
     echo -e "${BLUE}[INFO]${NC} $1"
 }
@@ -56,7 +70,7 @@ log_warning() {
 
 # Vérifie si le commander est accessible
 check_commander() {
-    if curl -s -o /dev/null -w "%{http_code}" "$COMMANDER_URL" | grep -q "200"; then
+    if commander_curl -o /dev/null -w "%{http_code}" "$COMMANDER_URL" | grep -q "200"; then
         return 0
     else
         return 1
@@ -103,7 +117,7 @@ test_api_health() {
 
 test_api_status() {
     log_info "Test: GET /api/status"
-    local response=$(curl -s "$COMMANDER_URL/api/status")
+    local response=$(commander_curl "$COMMANDER_URL/api/status")
     if echo "$response" | grep -q "\["; then
         log_success "API /api/status retourne un JSON valide"
     else
@@ -113,7 +127,7 @@ test_api_status() {
 
 test_api_alerts() {
     log_info "Test: GET /api/alerts/suricata"
-    local response=$(curl -s "$COMMANDER_URL/api/alerts/suricata")
+    local response=$(commander_curl "$COMMANDER_URL/api/alerts/suricata")
     if echo "$response" | grep -q "ids"; then
         log_success "API /api/alerts retourne un JSON valide"
     else
@@ -205,7 +219,7 @@ scenario_full_workflow() {
 
     # 3. Vérifier l'API status
     log_info "Vérification du status via API"
-    local status=$(curl -s "$COMMANDER_URL/api/status")
+    local status=$(commander_curl "$COMMANDER_URL/api/status")
     if echo "$status" | grep -q "${lab}"; then
         log_success "Container $lab visible dans /api/status"
     else
@@ -214,7 +228,7 @@ scenario_full_workflow() {
 
     # 4. Récupérer les alertes
     log_info "Récupération des alertes via API"
-    local alerts=$(curl -s "$COMMANDER_URL/api/alerts/$lab")
+    local alerts=$(commander_curl "$COMMANDER_URL/api/alerts/$lab")
     if echo "$alerts" | grep -q "ids"; then
         log_success "API alertes fonctionnelle pour $lab"
     fi
@@ -233,7 +247,7 @@ scenario_full_workflow() {
         sleep 3
 
         # Vérifier si des alertes ont été générées
-        local new_alerts=$(curl -s "$COMMANDER_URL/api/alerts/$lab")
+        local new_alerts=$(commander_curl "$COMMANDER_URL/api/alerts/$lab")
         log_success "Scan exécuté (vérifiez manuellement les alertes)"
     else
         log_warning "nmap non disponible, scan ignoré"
@@ -286,15 +300,18 @@ scenario_stop_all() {
     # Démarrer un lab
     test_lab_start "suricata"
 
-    # Utiliser l'API pour tout arrêter
-    log_info "Appel de /lab/stop-all via curl"
-    curl -s -X POST "$COMMANDER_URL/lab/stop-all" > /dev/null
+    # Utiliser l'API pour tout arrêter (un job docker compose down par lab)
+    log_info "Appel de /api/jobs (stop) via curl"
+    for lab in snort suricata zeek kibana; do
+        commander_curl -X POST "$COMMANDER_URL/api/jobs" -H "Content-Type: application/json" \
+            -d "{\"lab\": \"$lab\", \"action\": \"stop\"}" > /dev/null
+    done
 
     sleep 5
 
     # Vérifier que tout est arrêté
     if ! container_running "suricata_ids" && ! container_running "snort_ids" && ! container_running "zeek_ids"; then
-        log_success "Tous les labs arrêtés via /lab/stop-all"
+        log_success "Tous les labs arrêtés via /api/jobs"
     else
         log_error "Des containers sont encore actifs après stop-all"
     fi
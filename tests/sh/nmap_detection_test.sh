@@ -15,6 +15,13 @@ NC='\033[0m'
 
 PROJECT_ROOT="$(cd "$(dirname "$0")/.." && pwd)"
 COMMANDER_URL="http://localhost:3000"
+# Token d'API si le commander a des comptes (ids-commander add-token)
+COMMANDER_TOKEN="${COMMANDER_TOKEN:-}"
+
+# curl vers le commander, avec le token d'API s'il est défini
+commander_curl() {
+    curl -s ${COMMANDER_TOKEN:+-H "Authorization: Bearer $COMMANDER_TOKEN"} "$@"
+}
 
 log_info() { echo -e "${BLUE}[INFO]${NC} $1"; }
 log_success() { echo -e "${GREEN}[PASS]${NC} $1"; }
@@ -71,7 +78,7 @@ check_prerequisites() {
     # Configurer/vérifier sudoers
     setup_sudoers
 
-    if ! curl -s "$COMMANDER_URL/api/health" > /dev/null 2>&1; then
+    if ! commander_curl "$COMMANDER_URL/api/health" > /dev/null 2>&1; then
         log_warning "Commander non accessible sur $COMMANDER_URL"
         log_info "Les tests d'API seront ignorés"
     fi
@@ -218,7 +225,7 @@ check_api_alerts() {
 
     log_info "Vérification des alertes via API pour $ids..."
 
-    local response=$(curl -s "$COMMANDER_URL/api/alerts/$ids")
+    local response=$(commander_curl "$COMMANDER_URL/api/alerts/$ids")
     local count=$(echo "$response" | grep -o '"count":[0-9]*' | grep -o '[0-9]*')
 
     if [ -n "$count" ] && [ "$count" -gt 0 ]; then